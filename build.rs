@@ -1,31 +1,260 @@
-//! This build script copies the `memory.x` file from the crate root into
-//! a directory where the linker can always find it at build time.
-//! For many projects this is optional, as the linker always searches the
-//! project root directory -- wherever `Cargo.toml` is. However, if you
-//! are using a workspace or have a more complicated build setup, this
-//! build script becomes required. Additionally, by requesting that
-//! Cargo re-run the build script whenever `memory.x` is changed,
-//! updating `memory.x` ensures a rebuild of the application with the
-//! new memory settings.
+//! This build script:
+//! * Generates the `memory.x` linker script for the chip selected via cargo features and puts it in
+//!   a directory where the linker can always find it at build time.
+//! * Captures build metadata (git commit, build time, profile, features) for `src/build_info.rs`.
+//! * Converts the PNGs in `assets/` into raw bitmaps for `src/assets.rs`. See `build/assets.rs`.
+//! * Packs the BDF fonts in `assets/fonts/` for `src/font.rs`. See `build/fonts.rs`.
+//!
+//! Flash and RAM sizes are those of the biggest variant of each chip, which is what the Nucleo
+//! boards carry. Only the main SRAM block is used. Extras like the CCM RAM on the F405/F407 are left
+//! alone.
 
 use std::env;
 use std::fs::File;
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[path = "build/assets.rs"]
+mod assets;
+#[path = "build/fonts.rs"]
+mod fonts;
+
+/// Where the build info struct lives in flash. Leaves room for the biggest vector table of the
+/// supported chips.
+const BUILD_INFO_OFFSET: u32 = 0x200;
+/// Code starts after the build info
+const TEXT_OFFSET: u32 = 0x400;
+
+struct Chip {
+    feature: &'static str,
+    flash_kib: u32,
+    ram_kib: u32,
+}
+
+const CHIPS: &[Chip] = &[
+    Chip {
+        feature: "stm32f401",
+        flash_kib: 512,
+        ram_kib: 96,
+    },
+    Chip {
+        feature: "stm32f405",
+        flash_kib: 1024,
+        ram_kib: 128,
+    },
+    Chip {
+        feature: "stm32f407",
+        flash_kib: 1024,
+        ram_kib: 128,
+    },
+    Chip {
+        feature: "stm32f411",
+        flash_kib: 512,
+        ram_kib: 128,
+    },
+    Chip {
+        feature: "stm32f446",
+        flash_kib: 512,
+        ram_kib: 128,
+    },
+];
+
+/// Figure out which chip we're building for from the enabled cargo features
+fn selected_chip() -> &'static Chip {
+    let enabled: Vec<&Chip> = CHIPS
+        .iter()
+        .filter(|chip| {
+            let var = format!("CARGO_FEATURE_{}", chip.feature.to_uppercase());
+            env::var_os(var).is_some()
+        })
+        .collect();
+    match enabled.as_slice() {
+        [chip] => chip,
+        [] => panic!("No chip selected. Enable one of the stm32f4xx features."),
+        _ => panic!("Multiple chips selected. Enable only one of the stm32f4xx features."),
+    }
+}
+
+fn memory_x(chip: &Chip) -> String {
+    format!(
+        "/* Generated by build.rs for the {chip} */
+MEMORY
+{{
+  /* NOTE 1 K = 1 KiBi = 1024 bytes */
+  FLASH : ORIGIN = 0x08000000, LENGTH = {flash}K
+  RAM : ORIGIN = 0x20000000, LENGTH = {ram}K
+}}
+
+/* Build info goes at a fixed spot between the vector table and the code so that tools can find
+   it without symbols */
+SECTIONS
+{{
+  .build_info ORIGIN(FLASH) + {build_info:#x} :
+  {{
+    KEEP(*(.build_info));
+  }} > FLASH
+}} INSERT AFTER .vector_table;
+_stext = ORIGIN(FLASH) + {text:#x};
+
+ASSERT(ADDR(.vector_table) + SIZEOF(.vector_table) <= ADDR(.build_info),
+  \"The vector table overlaps the build info\");
+ASSERT(ADDR(.build_info) + SIZEOF(.build_info) <= _stext, \"The build info is too big\");
+",
+        chip = chip.feature.to_uppercase(),
+        flash = chip.flash_kib,
+        ram = chip.ram_kib,
+        build_info = BUILD_INFO_OFFSET,
+        text = TEXT_OFFSET,
+    )
+}
+
+/// Run a git command, returning its trimmed output. `None` if git isn't around or we aren't in a
+/// repository.
+fn git(args: &[&str]) -> Option<String> {
+    let output = Command::new("git").args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Seconds since the Unix epoch. Honors `SOURCE_DATE_EPOCH` for reproducible builds.
+fn build_timestamp() -> u64 {
+    env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.parse().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs()
+        })
+}
+
+/// Format a Unix timestamp as ISO 8601 in UTC, e.g. 2021-01-04T13:37:00Z
+fn iso8601(timestamp: u64) -> String {
+    let days = (timestamp / 86400) as i64;
+    let secs = timestamp % 86400;
+    // Days to civil date, from http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+/// Enabled cargo features, comma-separated
+fn features() -> String {
+    let mut features: Vec<String> = env::vars()
+        .filter_map(|(var, _)| {
+            var.strip_prefix("CARGO_FEATURE_")
+                .map(|feature| feature.to_lowercase().replace('_', "-"))
+        })
+        .collect();
+    features.sort();
+    features.join(",")
+}
+
+/// Byte string literal of exactly `len` bytes, truncated or padded with NULs
+fn padded(s: &str, len: usize) -> String {
+    let mut bytes = s.as_bytes().to_vec();
+    bytes.resize(len, 0);
+    format!(
+        "*b\"{}\"",
+        bytes
+            .iter()
+            .map(|b| b.escape_ascii().to_string())
+            .collect::<String>()
+    )
+}
+
+/// Generate the initializer for `BUILD_INFO` in `src/build_info.rs`. Field sizes must match the
+/// struct definition there.
+fn build_info() -> String {
+    let git_hash = git(&["rev-parse", "HEAD"]).unwrap_or_else(|| "unknown".to_string());
+    let dirty = git(&["status", "--porcelain"]).is_some_and(|status| !status.is_empty());
+    let timestamp = build_timestamp();
+    format!(
+        "BuildInfo {{
+    magic: MAGIC,
+    timestamp: {timestamp},
+    dirty: {dirty},
+    git_hash: {git_hash},
+    build_time: {build_time},
+    version: {version},
+    profile: {profile},
+    features: {features},
+}}
+",
+        timestamp = timestamp,
+        dirty = dirty as u8,
+        git_hash = padded(&git_hash, 40),
+        build_time = padded(&iso8601(timestamp), 20),
+        version = padded(&env::var("CARGO_PKG_VERSION").unwrap(), 16),
+        profile = padded(&env::var("PROFILE").unwrap(), 16),
+        features = padded(&features(), 96),
+    )
+}
+
+/// Re-run the build script when the commit or the working tree changes so that the git info stays
+/// current
+fn rerun_on_git_changes() {
+    let git_dir = match git(&["rev-parse", "--git-dir"]) {
+        Some(dir) => PathBuf::from(dir),
+        None => return,
+    };
+    for file in &["HEAD", "index"] {
+        println!("cargo:rerun-if-changed={}", git_dir.join(file).display());
+    }
+    if let Some(head_ref) = git(&["symbolic-ref", "-q", "HEAD"]) {
+        println!(
+            "cargo:rerun-if-changed={}",
+            git_dir.join(Path::new(&head_ref)).display()
+        );
+    }
+    for dir in &["src", "examples"] {
+        println!("cargo:rerun-if-changed={}", dir);
+    }
+}
 
 fn main() {
+    let chip = selected_chip();
+
     // Put `memory.x` in our output directory and ensure it's
     // on the linker search path.
     let out = &PathBuf::from(env::var_os("OUT_DIR").unwrap());
     File::create(out.join("memory.x"))
         .unwrap()
-        .write_all(include_bytes!("memory.x"))
+        .write_all(memory_x(chip).as_bytes())
         .unwrap();
     println!("cargo:rustc-link-search={}", out.display());
 
-    // By default, Cargo will re-run a build script whenever
-    // any file in the project changes. By specifying `memory.x`
-    // here, we ensure the build script is only re-run when
-    // `memory.x` is changed.
-    println!("cargo:rerun-if-changed=memory.x");
+    File::create(out.join("build_info.rs"))
+        .unwrap()
+        .write_all(build_info().as_bytes())
+        .unwrap();
+
+    assets::convert_all(Path::new("assets"), out);
+    fonts::convert_all(Path::new("assets/fonts"), out);
+
+    // Feature changes already trigger a re-run. Otherwise, only re-run when the git info may have
+    // changed rather than on every file change in the project.
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=build");
+    rerun_on_git_changes();
 }
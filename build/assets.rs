@@ -0,0 +1,284 @@
+//! Converts the PNGs in `assets/` into 1bpp raw bitmaps for `ImageRawLE<BinaryColor>`, plus a
+//! generated `assets.rs` that `src/assets.rs` includes.
+//!
+//! Each image can have an optional `<name>.cfg` next to it with `key = value` lines:
+//! * `dither`: `threshold` (default), `floyd-steinberg` or `atkinson`
+//! * `threshold`: brightness cutoff from 0-255 for a lit pixel. Defaults to 128.
+//! * `width`, `height`: resize to this size. If only one is given, the aspect ratio is kept.
+//! * `invert`: `true` to flip lit and unlit pixels
+//!
+//! Lines starting with `#` are comments.
+
+use std::fmt::Write as _;
+use std::fs::{self, File};
+use std::path::Path;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Dither {
+    Threshold,
+    FloydSteinberg,
+    Atkinson,
+}
+
+#[derive(Debug)]
+struct Options {
+    dither: Dither,
+    threshold: u8,
+    width: Option<u32>,
+    height: Option<u32>,
+    invert: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            dither: Dither::Threshold,
+            threshold: 128,
+            width: None,
+            height: None,
+            invert: false,
+        }
+    }
+}
+
+fn parse_options(path: &Path) -> Options {
+    let mut options = Options::default();
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(_) => return options,
+    };
+    for (line_no, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fail = |msg: &str| -> ! { panic!("{}:{}: {}", path.display(), line_no + 1, msg) };
+        let (key, value) = match line.split_once('=') {
+            Some((key, value)) => (key.trim(), value.trim()),
+            None => fail("expected `key = value`"),
+        };
+        match key {
+            "dither" => {
+                options.dither = match value {
+                    "threshold" | "none" => Dither::Threshold,
+                    "floyd-steinberg" => Dither::FloydSteinberg,
+                    "atkinson" => Dither::Atkinson,
+                    _ => fail("unknown dither mode"),
+                }
+            }
+            "threshold" => {
+                options.threshold = value.parse().unwrap_or_else(|_| fail("bad threshold"))
+            }
+            "width" => options.width = Some(value.parse().unwrap_or_else(|_| fail("bad width"))),
+            "height" => options.height = Some(value.parse().unwrap_or_else(|_| fail("bad height"))),
+            "invert" => options.invert = value.parse().unwrap_or_else(|_| fail("bad invert")),
+            _ => fail("unknown option"),
+        }
+    }
+    options
+}
+
+/// Grayscale image with one brightness value (0.0-255.0) per pixel
+struct Gray {
+    width: u32,
+    height: u32,
+    pixels: Vec<f32>,
+}
+
+/// Decode a PNG to grayscale. Transparent pixels are treated as black, i.e. unlit.
+fn load_png(path: &Path) -> Gray {
+    let mut decoder = png::Decoder::new(File::open(path).unwrap());
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let mut reader = decoder
+        .read_info()
+        .unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf).unwrap();
+    let channels = info.color_type.samples();
+    let pixels = buf[..info.buffer_size()]
+        .chunks(channels)
+        .map(|px| {
+            let (luma, alpha) = match px {
+                [l] => (f32::from(*l), 255.0),
+                [l, a] => (f32::from(*l), f32::from(*a)),
+                [r, g, b] => (luma(*r, *g, *b), 255.0),
+                [r, g, b, a] => (luma(*r, *g, *b), f32::from(*a)),
+                _ => unreachable!(),
+            };
+            luma * alpha / 255.0
+        })
+        .collect();
+    Gray {
+        width: info.width,
+        height: info.height,
+        pixels,
+    }
+}
+
+fn luma(r: u8, g: u8, b: u8) -> f32 {
+    0.299 * f32::from(r) + 0.587 * f32::from(g) + 0.114 * f32::from(b)
+}
+
+/// Box filter resize. Each output pixel is the average of the source pixels it covers, which
+/// degrades to nearest neighbor when scaling up.
+fn resize(src: &Gray, width: u32, height: u32) -> Gray {
+    if (width, height) == (src.width, src.height) {
+        return Gray {
+            width,
+            height,
+            pixels: src.pixels.clone(),
+        };
+    }
+    let span = |i: u32, dst: u32, src: u32| {
+        let start = (u64::from(i) * u64::from(src) / u64::from(dst)) as u32;
+        let end = (u64::from(i + 1) * u64::from(src) / u64::from(dst)) as u32;
+        start..end.max(start + 1)
+    };
+    let mut pixels = Vec::with_capacity((width * height) as usize);
+    for y in 0..height {
+        for x in 0..width {
+            let mut sum = 0.0;
+            let mut count = 0.0;
+            for sy in span(y, height, src.height) {
+                for sx in span(x, width, src.width) {
+                    sum += src.pixels[(sy * src.width + sx) as usize];
+                    count += 1.0;
+                }
+            }
+            pixels.push(sum / count);
+        }
+    }
+    Gray {
+        width,
+        height,
+        pixels,
+    }
+}
+
+/// Quantize to lit/unlit, spreading the quantization error to neighboring pixels according to the
+/// dither mode
+fn dither(img: &mut Gray, mode: Dither, threshold: u8) -> Vec<bool> {
+    // (dx, dy, weight)
+    let kernel: &[(i32, i32, f32)] = match mode {
+        Dither::Threshold => &[],
+        Dither::FloydSteinberg => &[
+            (1, 0, 7.0 / 16.0),
+            (-1, 1, 3.0 / 16.0),
+            (0, 1, 5.0 / 16.0),
+            (1, 1, 1.0 / 16.0),
+        ],
+        // Only spreads 6/8 of the error, which keeps more contrast
+        Dither::Atkinson => &[
+            (1, 0, 1.0 / 8.0),
+            (2, 0, 1.0 / 8.0),
+            (-1, 1, 1.0 / 8.0),
+            (0, 1, 1.0 / 8.0),
+            (1, 1, 1.0 / 8.0),
+            (0, 2, 1.0 / 8.0),
+        ],
+    };
+    let (width, height) = (img.width as i32, img.height as i32);
+    let mut lit = Vec::with_capacity(img.pixels.len());
+    for y in 0..height {
+        for x in 0..width {
+            let value = img.pixels[(y * width + x) as usize];
+            let on = value >= f32::from(threshold);
+            lit.push(on);
+            let error = value - if on { 255.0 } else { 0.0 };
+            for (dx, dy, weight) in kernel {
+                let (nx, ny) = (x + dx, y + dy);
+                if nx >= 0 && nx < width && ny < height {
+                    img.pixels[(ny * width + nx) as usize] += error * weight;
+                }
+            }
+        }
+    }
+    lit
+}
+
+/// Pack into rows of bytes, most significant bit first, with each row padded to a whole byte. This
+/// is what `ImageRaw<BinaryColor>` expects.
+fn pack(lit: &[bool], width: u32) -> Vec<u8> {
+    let mut packed = Vec::new();
+    for row in lit.chunks(width as usize) {
+        for byte in row.chunks(8) {
+            packed.push(
+                byte.iter()
+                    .enumerate()
+                    .fold(0, |acc, (i, &on)| acc | ((on as u8) << (7 - i))),
+            );
+        }
+    }
+    packed
+}
+
+fn const_name(stem: &str) -> String {
+    let mut name: String = stem
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        name.insert(0, '_');
+    }
+    name
+}
+
+/// Convert everything in `asset_dir` into `out_dir` and generate the Rust source listing the
+/// results
+pub fn convert_all(asset_dir: &Path, out_dir: &Path) {
+    let raw_dir = out_dir.join("assets");
+    fs::create_dir_all(&raw_dir).unwrap();
+    println!("cargo:rerun-if-changed={}", asset_dir.display());
+
+    let mut pngs: Vec<_> = fs::read_dir(asset_dir)
+        .map(|dir| {
+            dir.map(|entry| entry.unwrap().path())
+                .filter(|path| path.extension().is_some_and(|ext| ext == "png"))
+                .collect()
+        })
+        .unwrap_or_default();
+    pngs.sort();
+
+    let mut source = String::new();
+    for png in pngs {
+        let stem = png.file_stem().unwrap().to_str().unwrap();
+        let options_path = png.with_extension("cfg");
+        println!("cargo:rerun-if-changed={}", png.display());
+        println!("cargo:rerun-if-changed={}", options_path.display());
+        let options = parse_options(&options_path);
+
+        let src = load_png(&png);
+        let (width, height) = match (options.width, options.height) {
+            (Some(width), Some(height)) => (width, height),
+            (Some(width), None) => (width, (src.height * width + src.width / 2) / src.width),
+            (None, Some(height)) => ((src.width * height + src.height / 2) / src.height, height),
+            (None, None) => (src.width, src.height),
+        };
+        let mut img = resize(&src, width, height);
+        let mut lit = dither(&mut img, options.dither, options.threshold);
+        if options.invert {
+            lit.iter_mut().for_each(|on| *on = !*on);
+        }
+
+        let raw_path = raw_dir.join(format!("{}.raw", stem));
+        fs::write(&raw_path, pack(&lit, width)).unwrap();
+        writeln!(
+            source,
+            "/// Generated from `assets/{}`\npub const {}: Asset = Asset {{\n    \
+             data: include_bytes!({:?}),\n    width: {},\n    height: {},\n}};",
+            png.file_name().unwrap().to_str().unwrap(),
+            const_name(stem),
+            raw_path.display().to_string(),
+            width,
+            height
+        )
+        .unwrap();
+    }
+    fs::write(out_dir.join("assets.rs"), source).unwrap();
+}
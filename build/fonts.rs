@@ -0,0 +1,230 @@
+//! Packs the BDF fonts in `assets/fonts/` into glyph tables and 1bpp bitmaps for `src/font.rs`,
+//! plus a generated `fonts.rs` that it includes.
+//!
+//! Each font can have an optional `<name>.cfg` next to it with `key = value` lines:
+//! * `chars`: comma-separated hex code points or ranges of them to keep, e.g. `20-7e, b0, 30c4`.
+//!   Defaults to every glyph in the font.
+//!
+//! Lines starting with `#` are comments.
+
+use std::convert::{TryFrom, TryInto};
+use std::fmt::Write as _;
+use std::fs;
+use std::ops::RangeInclusive;
+use std::path::Path;
+
+struct Glyph {
+    c: char,
+    advance: u8,
+    width: u8,
+    height: u8,
+    x: i8,
+    /// From the top of the line rather than BDF's bottom-up offset from the baseline
+    top: i8,
+    /// Rows padded to a whole byte, most significant bit first
+    bitmap: Vec<u8>,
+}
+
+struct Bdf {
+    ascent: u32,
+    descent: u32,
+    glyphs: Vec<Glyph>,
+}
+
+fn parse_options(path: &Path) -> Option<Vec<RangeInclusive<u32>>> {
+    let text = fs::read_to_string(path).ok()?;
+    let mut chars = None;
+    for (line_no, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fail = |msg: &str| -> ! { panic!("{}:{}: {}", path.display(), line_no + 1, msg) };
+        let (key, value) = match line.split_once('=') {
+            Some((key, value)) => (key.trim(), value.trim()),
+            None => fail("expected `key = value`"),
+        };
+        let hex = |s: &str| u32::from_str_radix(s.trim(), 16).unwrap_or_else(|_| fail("bad char"));
+        match key {
+            "chars" => {
+                chars = Some(
+                    value
+                        .split(',')
+                        .map(|range| match range.split_once('-') {
+                            Some((start, end)) => hex(start)..=hex(end),
+                            None => hex(range)..=hex(range),
+                        })
+                        .collect(),
+                )
+            }
+            _ => fail("unknown option"),
+        }
+    }
+    chars
+}
+
+/// Just enough of BDF 2.1 for bitmap fonts with a fixed line height. Glyphs without a Unicode
+/// encoding are skipped.
+fn parse_bdf(path: &Path) -> Bdf {
+    let text = fs::read_to_string(path).unwrap();
+    let mut lines = text.lines().enumerate();
+    let fail =
+        |line_no: usize, msg: &str| -> ! { panic!("{}:{}: {}", path.display(), line_no + 1, msg) };
+    let number = |line_no: usize, s: Option<&str>| -> i32 {
+        s.and_then(|s| s.parse().ok())
+            .unwrap_or_else(|| fail(line_no, "expected a number"))
+    };
+
+    let (mut ascent, mut descent) = (None, None);
+    let mut glyphs = Vec::new();
+    let mut encoding = None;
+    let mut advance = 0;
+    let mut bbx = (0, 0, 0, 0);
+    while let Some((line_no, line)) = lines.next() {
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some("FONT_ASCENT") => ascent = Some(number(line_no, words.next())),
+            Some("FONT_DESCENT") => descent = Some(number(line_no, words.next())),
+            Some("ENCODING") => encoding = Some(number(line_no, words.next())),
+            Some("DWIDTH") => advance = number(line_no, words.next()),
+            Some("BBX") => {
+                let mut next = || number(line_no, words.next());
+                bbx = (next(), next(), next(), next());
+            }
+            Some("BITMAP") => {
+                let (width, height, x, y) = bbx;
+                let row_bytes = (width as usize).div_ceil(8);
+                let mut bitmap = Vec::with_capacity(row_bytes * height as usize);
+                for _ in 0..height {
+                    let (line_no, row) = lines.next().unwrap_or_else(|| fail(line_no, "eof"));
+                    if row.len() % 2 != 0 {
+                        fail(line_no, "odd number of hex digits in bitmap row");
+                    }
+                    // Rows can be padded with more bytes than the width needs
+                    let bytes = (0..row.len())
+                        .step_by(2)
+                        .map(|i| u8::from_str_radix(&row[i..i + 2], 16))
+                        .collect::<Result<Vec<u8>, _>>()
+                        .unwrap_or_else(|_| fail(line_no, "bad bitmap row"));
+                    if bytes.len() < row_bytes {
+                        fail(line_no, "bitmap row too short");
+                    }
+                    bitmap.extend_from_slice(&bytes[..row_bytes]);
+                }
+                let ascent = ascent.unwrap_or_else(|| fail(line_no, "no FONT_ASCENT"));
+                let c = encoding.take().and_then(|code| char::from_u32(code as u32));
+                if let Some(c) = c {
+                    glyphs.push(Glyph {
+                        c,
+                        advance: narrow(advance, path, line_no),
+                        width: narrow(width, path, line_no),
+                        height: narrow(height, path, line_no),
+                        x: narrow(x, path, line_no),
+                        top: narrow(ascent - y - height, path, line_no),
+                        bitmap,
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+    Bdf {
+        ascent: ascent.unwrap_or_else(|| fail(0, "no FONT_ASCENT")) as u32,
+        descent: descent.unwrap_or_else(|| fail(0, "no FONT_DESCENT")) as u32,
+        glyphs,
+    }
+}
+
+/// Glyph metrics are stored in a byte each
+fn narrow<T: TryFrom<i32>>(value: i32, path: &Path, line_no: usize) -> T {
+    value
+        .try_into()
+        .unwrap_or_else(|_| panic!("{}:{}: glyph too big", path.display(), line_no + 1))
+}
+
+fn const_name(stem: &str) -> String {
+    stem.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Convert every font in `font_dir` into `out_dir` and generate the Rust source listing them
+pub fn convert_all(font_dir: &Path, out_dir: &Path) {
+    let raw_dir = out_dir.join("fonts");
+    fs::create_dir_all(&raw_dir).unwrap();
+    println!("cargo:rerun-if-changed={}", font_dir.display());
+
+    let mut bdfs: Vec<_> = fs::read_dir(font_dir)
+        .map(|dir| {
+            dir.map(|entry| entry.unwrap().path())
+                .filter(|path| path.extension().is_some_and(|ext| ext == "bdf"))
+                .collect()
+        })
+        .unwrap_or_default();
+    bdfs.sort();
+
+    let mut source = String::new();
+    for path in bdfs {
+        let stem = path.file_stem().unwrap().to_str().unwrap();
+        let options_path = path.with_extension("cfg");
+        println!("cargo:rerun-if-changed={}", path.display());
+        println!("cargo:rerun-if-changed={}", options_path.display());
+
+        let mut font = parse_bdf(&path);
+        if let Some(chars) = parse_options(&options_path) {
+            font.glyphs
+                .retain(|glyph| chars.iter().any(|range| range.contains(&(glyph.c as u32))));
+        }
+        // Looked up by binary search
+        font.glyphs.sort_by_key(|glyph| glyph.c);
+        font.glyphs.dedup_by_key(|glyph| glyph.c);
+        let replacement = if font.glyphs.iter().any(|glyph| glyph.c == '?') {
+            '?'
+        } else {
+            font.glyphs.first().map_or(' ', |glyph| glyph.c)
+        };
+
+        let mut bitmaps = Vec::new();
+        let mut glyphs = String::new();
+        for glyph in &font.glyphs {
+            writeln!(
+                glyphs,
+                "        Glyph {{ c: {:?}, offset: {}, advance: {}, width: {}, height: {}, x: {}, \
+                 top: {} }},",
+                glyph.c,
+                bitmaps.len(),
+                glyph.advance,
+                glyph.width,
+                glyph.height,
+                glyph.x,
+                glyph.top
+            )
+            .unwrap();
+            bitmaps.extend_from_slice(&glyph.bitmap);
+        }
+
+        let raw_path = raw_dir.join(format!("{}.raw", stem));
+        fs::write(&raw_path, bitmaps).unwrap();
+        writeln!(
+            source,
+            "/// Generated from `assets/fonts/{}`\npub const {}: Font = Font {{\n    \
+             ascent: {},\n    descent: {},\n    replacement: {:?},\n    \
+             bitmaps: include_bytes!({:?}),\n    glyphs: &[\n{}    ],\n}};",
+            path.file_name().unwrap().to_str().unwrap(),
+            const_name(stem),
+            font.ascent,
+            font.descent,
+            replacement,
+            raw_path.display().to_string(),
+            glyphs
+        )
+        .unwrap();
+    }
+    fs::write(out_dir.join("fonts.rs"), source).unwrap();
+}
@@ -12,6 +12,7 @@ use panic_halt as _; // you can put a breakpoint on `rust_begin_unwind` to catch
 use crate::hal::{prelude::*, stm32};
 #[allow(unused_extern_crates)]
 use cortex_m_rt::entry;
+use sandbox_stm32f4_rust::board::{Board, ClockPreset};
 use stm32f4xx_hal as hal;
 
 #[entry]
@@ -20,17 +21,10 @@ fn main() -> ! {
         stm32::Peripherals::take(),
         cortex_m::peripheral::Peripherals::take(),
     ) {
-        // Set up the LED. On the NUCLEO-F401RE it's connected to pin PA5.
-        // Calling split also powers up the GPIOA peripheral clock
-        let gpioa = dp.GPIOA.split();
-        let mut led = gpioa.pa5.into_push_pull_output();
-
-        // Set up the system clock. We want to run at 48MHz for this one.
-        let rcc = dp.RCC.constrain();
-        let clocks = rcc.cfgr.sysclk(48.mhz()).freeze();
-
-        // Create a delay abstraction based on SysTick
-        let mut delay = hal::delay::Delay::new(cp.SYST, clocks);
+        // We want to run at 48MHz for this one
+        let board = Board::new(dp, cp.SYST, cp.ITM, ClockPreset::Mhz48);
+        let mut led = board.led;
+        let mut delay = board.delay;
 
         loop {
             // On for 1s, off for 1s.
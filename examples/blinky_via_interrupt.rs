@@ -9,7 +9,6 @@ use panic_halt as _; // you can put a breakpoint on `rust_begin_unwind` to catch
 
 #[allow(unused_extern_crates)]
 use crate::hal::{
-    gpio::{gpioa::PA5, Output, PushPull},
     interrupt,
     prelude::*,
     stm32::{self, Interrupt, TIM2},
@@ -18,12 +17,13 @@ use crate::hal::{
 use cmim::{Context, Move};
 use cortex_m::iprintln;
 use cortex_m_rt::entry;
+use sandbox_stm32f4_rust::board::{Board, ClockPreset, Led};
 use stm32f4xx_hal as hal;
 
 // Data that is "moved" into the interrupt via the cmim crate
 struct LedContext {
     on: bool,
-    pin: PA5<Output<PushPull>>,
+    pin: Led,
     timer: Timer<TIM2>,
 }
 
@@ -34,29 +34,22 @@ static LEDS: Move<LedContext, Interrupt> =
 
 #[entry]
 fn main() -> ! {
-    if let (Some(dp), Some(mut cp)) = (
+    if let (Some(dp), Some(cp)) = (
         stm32::Peripherals::take(),
         cortex_m::peripheral::Peripherals::take(),
     ) {
-        let stim = &mut cp.ITM.stim[0];
-        iprintln!(stim, "Boot");
-        // Set up the LED. On the NUCLEO-F401RE it's connected to pin PA5.
-        // Calling split also powers up the GPIOA peripheral clock
-        let gpioa = dp.GPIOA.split();
-        let led = gpioa.pa5.into_push_pull_output();
-
-        let rcc = dp.RCC.constrain();
-        let clocks = rcc.cfgr.sysclk(84.mhz()).freeze();
+        let mut board = Board::new(dp, cp.SYST, cp.ITM, ClockPreset::Mhz84);
+        iprintln!(&mut board.itm.stim[0], "Boot");
 
         unsafe {
             cortex_m::peripheral::NVIC::unmask(Interrupt::TIM2);
         }
 
-        let mut timer = Timer::tim2(dp.TIM2, 1.hz(), clocks);
+        let mut timer = Timer::tim2(board.spare.TIM2, 1.hz(), board.clocks);
         timer.listen(hal::timer::Event::TimeOut);
         LEDS.try_move(LedContext {
             on: false,
-            pin: led,
+            pin: board.led,
             timer,
         })
         .ok();
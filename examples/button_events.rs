@@ -0,0 +1,89 @@
+//! Prints user button events over ITM. A click toggles the LED, a double-click blinks it twice and
+//! a long press turns it off.
+
+#![no_std]
+#![no_main]
+
+use panic_halt as _; // you can put a breakpoint on `rust_begin_unwind` to catch panics
+                     // use panic_abort as _; // requires nightly
+                     // use panic_itm as _; // logs messages over ITM; requires ITM support
+                     // use panic_semihosting as _; // logs messages to the host stderr; requires a debugger
+
+use cortex_m::iprintln;
+use cortex_m_rt::entry;
+use sandbox_stm32f4_rust::{
+    board::{Board, ClockPreset},
+    button::{self, ButtonEvent, Timings},
+    exti,
+};
+use stm32f4xx_hal::{
+    interrupt,
+    prelude::*,
+    stm32::{self, Interrupt},
+};
+
+#[entry]
+fn main() -> ! {
+    if let (Some(dp), Some(cp)) = (
+        stm32::Peripherals::take(),
+        cortex_m::peripheral::Peripherals::take(),
+    ) {
+        let board = Board::new(dp, cp.SYST, cp.ITM, ClockPreset::Hsi16);
+        let mut led = board.led;
+        let mut delay = board.delay;
+        let mut itm = board.itm;
+
+        // Timestamps aren't used here
+        exti::init(board.spare.EXTI, board.spare.SYSCFG, || 0);
+        button::start(
+            board.button,
+            board.spare.TIM4,
+            board.clocks,
+            Timings::default(),
+        )
+        .unwrap();
+        unsafe {
+            cortex_m::peripheral::NVIC::unmask(Interrupt::EXTI15_10);
+            cortex_m::peripheral::NVIC::unmask(Interrupt::TIM4);
+        }
+
+        // Run `monitor tpiu config internal itm.txt uart off 16000000` in gdb to see the output
+        loop {
+            // With interrupts off, an event showing up between checking and sleeping still wakes
+            // up the WFI
+            let event = cortex_m::interrupt::free(|_| {
+                let event = button::next_event();
+                if event.is_none() {
+                    cortex_m::asm::wfi();
+                }
+                event
+            });
+            if let Some(event) = event {
+                iprintln!(&mut itm.stim[0], "{:?}", event);
+                match event {
+                    ButtonEvent::Click => led.toggle().unwrap(),
+                    ButtonEvent::DoubleClick => {
+                        for _ in 0..4 {
+                            led.toggle().unwrap();
+                            delay.delay_ms(100_u32);
+                        }
+                    }
+                    ButtonEvent::LongPress => led.set_low().unwrap(),
+                    ButtonEvent::Press | ButtonEvent::Release => {}
+                }
+            }
+        }
+    }
+
+    loop {}
+}
+
+#[interrupt]
+fn EXTI15_10() {
+    exti::interrupt();
+}
+
+#[interrupt]
+fn TIM4() {
+    button::timer_interrupt();
+}
@@ -0,0 +1,107 @@
+#![no_std]
+#![no_main]
+/// Crash on demand and report the crash over the debug serial port after the resulting reset.
+/// Received bytes are echoed back as usual, except for these:
+/// * `p` panics
+/// * `f` triggers a hard fault
+use crate::hal::{
+    interrupt,
+    stm32::{self, Interrupt},
+};
+use core::{
+    cell::RefCell,
+    fmt::Write,
+    ops::DerefMut,
+    panic::PanicInfo,
+    sync::atomic::{AtomicU32, Ordering},
+};
+use cortex_m::{interrupt::Mutex, peripheral::syst::SystClkSource};
+use cortex_m_rt::{entry, exception, ExceptionFrame};
+use sandbox_stm32f4_rust::{
+    board::{Board, ClockPreset},
+    build_info::BUILD_INFO,
+    crash_dump,
+    uart_driver::{self, UartContext},
+};
+use stm32f4xx_hal as hal;
+
+static UART_CTX: Mutex<RefCell<Option<UartContext>>> = Mutex::new(RefCell::new(None));
+
+#[entry]
+fn main() -> ! {
+    if let (Some(dp), Some(cp)) = (
+        stm32::Peripherals::take(),
+        cortex_m::peripheral::Peripherals::take(),
+    ) {
+        let board = Board::new(dp, cp.SYST, cp.ITM, ClockPreset::Mhz84);
+
+        // 1ms SysTick to keep track of uptime for the crash record. We don't need the blocking
+        // delay, so take SysTick back from it.
+        let mut syst = board.delay.free();
+        syst.set_clock_source(SystClkSource::Core);
+        syst.set_reload(board.clocks.sysclk().0 / 1000 - 1);
+        syst.clear_current();
+        syst.enable_interrupt();
+        syst.enable_counter();
+
+        let mut serial_ctx = board.serial;
+        cortex_m::interrupt::free(|cs| {
+            write!(serial_ctx, "\r\nBoot {}\r\n", BUILD_INFO).ok();
+            // Report whatever killed us last time, then forget about it
+            if let Some(crash) = crash_dump::last_crash() {
+                write!(serial_ctx, "{}", crash).ok();
+                crash_dump::clear();
+            }
+            *UART_CTX.borrow(cs).borrow_mut() = Some(serial_ctx);
+        });
+
+        unsafe {
+            cortex_m::peripheral::NVIC::unmask(Interrupt::USART2);
+        }
+    }
+
+    loop {
+        let rx_byte = cortex_m::interrupt::free(|cs| {
+            let mut cell = UART_CTX.borrow(cs).borrow_mut();
+            cell.deref_mut()
+                .as_mut()
+                .and_then(|serial_ctx| serial_ctx.rx_queue.dequeue())
+        });
+        match rx_byte {
+            Some(b'p') => panic!("user asked for it"),
+            // Reading from an address with nothing behind it causes a bus fault, which escalates
+            // to a hard fault since the BusFault handler isn't enabled
+            Some(b'f') => unsafe {
+                core::ptr::read_volatile(0x3000_0000 as *const u32);
+            },
+            _ => (),
+        }
+    }
+}
+
+#[interrupt]
+fn USART2() {
+    cortex_m::interrupt::free(|cs| {
+        let mut cell = UART_CTX.borrow(cs).borrow_mut();
+        let serial_ctx = cell.deref_mut().as_mut().unwrap();
+        uart_driver::interrupt(serial_ctx);
+    });
+}
+
+#[exception]
+fn SysTick() {
+    static UPTIME_MS: AtomicU32 = AtomicU32::new(0);
+
+    let uptime_ms = UPTIME_MS.fetch_add(1, Ordering::Relaxed) + 1;
+    crash_dump::set_uptime_ms(uptime_ms);
+}
+
+#[exception]
+fn HardFault(ef: &ExceptionFrame) -> ! {
+    crash_dump::record_hard_fault(ef)
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    crash_dump::record_panic(info)
+}
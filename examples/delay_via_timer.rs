@@ -0,0 +1,72 @@
+//! Blinks the LED with a delay on TIM2 that sleeps between ticks instead of spinning with
+//! interrupts off. Every few blinks, it gives you a second to press the user button, timed by TIM3.
+
+#![no_std]
+#![no_main]
+
+use panic_halt as _; // you can put a breakpoint on `rust_begin_unwind` to catch panics
+                     // use panic_abort as _; // requires nightly
+                     // use panic_itm as _; // logs messages over ITM; requires ITM support
+                     // use panic_semihosting as _; // logs messages to the host stderr; requires a debugger
+
+use cortex_m::{iprintln, peripheral::NVIC};
+use cortex_m_rt::entry;
+use sandbox_stm32f4_rust::{
+    board::{Board, ClockPreset},
+    timer_delay::{self, DelayTimer, Timeout, TimeoutError, TimerDelay},
+};
+use stm32f4xx_hal::{
+    interrupt,
+    prelude::*,
+    stm32::{self, TIM2},
+    timer::Timer,
+};
+
+#[entry]
+fn main() -> ! {
+    if let (Some(dp), Some(cp)) = (
+        stm32::Peripherals::take(),
+        cortex_m::peripheral::Peripherals::take(),
+    ) {
+        let board = Board::new(dp, cp.SYST, cp.ITM, ClockPreset::Hsi16);
+        let (mut led, button, mut itm) = (board.led, board.button, board.itm);
+        iprintln!(&mut itm.stim[0], "Boot");
+
+        let mut delay = TimerDelay::new(board.spare.TIM2, board.clocks);
+        unsafe { NVIC::unmask(TIM2::INTERRUPT) };
+        let mut tim3 = Some(Timer::tim3(board.spare.TIM3, 1.hz(), board.clocks));
+
+        loop {
+            for _ in 0..5 {
+                led.set_low().unwrap();
+                delay.delay_ms(500_u32);
+                led.set_high().unwrap();
+                delay.delay_ms(500_u32);
+            }
+
+            iprintln!(&mut itm.stim[0], "Press the button");
+            let mut timeout = Timeout::start(tim3.take().unwrap(), 1.hz());
+            let pressed = nb::block!(timeout.poll(|| {
+                // Active low
+                if button.is_low().unwrap() {
+                    Ok(())
+                } else {
+                    Err(nb::Error::<()>::WouldBlock)
+                }
+            }));
+            match pressed {
+                Ok(()) => iprintln!(&mut itm.stim[0], "Pressed"),
+                Err(TimeoutError::TimedOut) => iprintln!(&mut itm.stim[0], "Too slow"),
+                Err(TimeoutError::Other(())) => {}
+            }
+            tim3 = Some(timeout.free());
+        }
+    }
+
+    loop {}
+}
+
+#[interrupt]
+fn TIM2() {
+    timer_delay::interrupt::<TIM2>();
+}
@@ -10,25 +10,25 @@ use panic_halt as _; // you can put a breakpoint on `rust_begin_unwind` to catch
                      // use panic_semihosting as _; // logs messages to the host stderr; requires a debugger
 
 use crate::hal::{
-    gpio::{gpioa::PA5, Output, PushPull},
     interrupt,
     prelude::*,
-    serial::{config::Config, Serial},
     stm32::{self, Interrupt, TIM2},
     timer::Timer,
 };
 use cmim::{Context, Move};
-use core::{cell::RefCell, iter::IntoIterator, ops::DerefMut};
+use core::{cell::RefCell, fmt::Write, iter::IntoIterator, ops::DerefMut};
 use cortex_m::{interrupt::Mutex, iprintln};
 use cortex_m_rt::entry;
-use sandbox_stm32f4_rust::uart_driver::{self, UartContext};
+use sandbox_stm32f4_rust::{
+    board::{Board, ClockPreset, Led},
+    stack,
+    uart_driver::{self, UartContext},
+};
 use stm32f4xx_hal as hal;
 
-type LedPin = PA5<Output<PushPull>>;
-
 // Data that is "moved" into the interrupt via the cmim crate
 struct LedContext {
-    pin: LedPin,
+    pin: Led,
     timer: Timer<TIM2>,
 }
 
@@ -40,12 +40,14 @@ static UART_CTX: Mutex<RefCell<Option<UartContext>>> = Mutex::new(RefCell::new(N
 
 #[entry]
 fn main() -> ! {
-    if let (Some(dp), Some(mut cp)) = (
+    // Paint the stack before anything else so we can keep an eye on its usage
+    stack::paint();
+
+    if let (Some(dp), Some(cp)) = (
         stm32::Peripherals::take(),
         cortex_m::peripheral::Peripherals::take(),
     ) {
-        let rcc = dp.RCC.constrain();
-        let clocks = rcc.cfgr.sysclk(84.mhz()).freeze();
+        let mut board = Board::new(dp, cp.SYST, cp.ITM, ClockPreset::Mhz84);
 
         // Don't think there's a way to do this without unsafe code
         unsafe {
@@ -54,34 +56,20 @@ fn main() -> ! {
         }
 
         // Print up a bootup message over ITM
-        let stim = &mut cp.ITM.stim[0];
+        let stim = &mut board.itm.stim[0];
         iprintln!(stim, "Hello world!");
 
-        // Set up the LED. On the NUCLEO-F401RE it's connected to pin PA5.
-        // Calling split also powers up the GPIOA peripheral clock
-        let gpioa = dp.GPIOA.split();
-        let led = gpioa.pa5.into_push_pull_output();
-
-        let mut timer = hal::timer::Timer::tim2(dp.TIM2, 1.hz(), clocks);
+        let mut timer = hal::timer::Timer::tim2(board.spare.TIM2, 1.hz(), board.clocks);
         timer.listen(hal::timer::Event::TimeOut);
-        LEDS.try_move(LedContext { pin: led, timer }).ok();
-
-        // ST-Link is connected to USART2
-        // RX: PA3
-        // TX: PA2
-        let tx = gpioa.pa2.into_alternate_af7();
-        let rx = gpioa.pa3.into_alternate_af7();
-        let mut serial = Serial::usart2(
-            dp.USART2,
-            (tx, rx),
-            Config::default().baudrate(115200.bps()),
-            clocks,
-        )
-        .unwrap();
-        serial.listen(hal::serial::Event::Rxne);
+        LEDS.try_move(LedContext {
+            pin: board.led,
+            timer,
+        })
+        .ok();
 
+        let serial_ctx = board.serial;
         cortex_m::interrupt::free(|cs| {
-            *UART_CTX.borrow(cs).borrow_mut() = Some(UartContext::new(serial));
+            *UART_CTX.borrow(cs).borrow_mut() = Some(serial_ctx);
         });
     }
 
@@ -105,6 +93,14 @@ fn serial_write<T: IntoIterator<Item = u8>>(bytes: T) {
     });
 }
 
+fn serial_write_fmt(args: core::fmt::Arguments) {
+    cortex_m::interrupt::free(|cs| {
+        let mut cell = UART_CTX.borrow(cs).borrow_mut();
+        let serial_ctx = cell.deref_mut().as_mut().unwrap();
+        serial_ctx.write_fmt(args).ok();
+    });
+}
+
 #[interrupt]
 fn TIM2() {
     static mut LED_ON: bool = false;
@@ -125,5 +121,5 @@ fn TIM2() {
     } else {
         serial_write("LED off".bytes());
     }
-    serial_write("\r\n".bytes());
+    serial_write_fmt(format_args!(" ({})\r\n", stack::usage()));
 }
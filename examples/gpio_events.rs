@@ -0,0 +1,115 @@
+//! Reports edges on a few Arduino header pins over ITM, with timestamps in CPU cycles. Wire
+//! switches from D2, D4 and D7 to ground. D2 shares an interrupt vector with the user button, and
+//! D4 and D7 share the one for pins 5-9. Watching both A2 (PA4) and D5 (PB4) fails, since they'd
+//! need the same EXTI line.
+
+#![no_std]
+#![no_main]
+
+use panic_halt as _; // you can put a breakpoint on `rust_begin_unwind` to catch panics
+                     // use panic_abort as _; // requires nightly
+                     // use panic_itm as _; // logs messages over ITM; requires ITM support
+                     // use panic_semihosting as _; // logs messages to the host stderr; requires a debugger
+
+use core::cell::RefCell;
+use cortex_m::{
+    interrupt::Mutex,
+    iprintln,
+    peripheral::{DWT, NVIC},
+};
+use cortex_m_rt::entry;
+use heapless::{consts::U16, spsc::Queue};
+use sandbox_stm32f4_rust::{
+    board::{Board, ClockPreset},
+    exti::{self, InputEvent, Port, Trigger},
+};
+use stm32f4xx_hal::{interrupt, stm32};
+
+/// Events on their way from the interrupt handlers to the main loop
+static EVENTS: Mutex<RefCell<Option<Queue<InputEvent, U16>>>> = Mutex::new(RefCell::new(None));
+
+fn record(event: InputEvent) {
+    cortex_m::interrupt::free(|cs| {
+        if let Some(events) = EVENTS.borrow(cs).borrow_mut().as_mut() {
+            events.enqueue(event).ok();
+        }
+    });
+}
+
+#[entry]
+fn main() -> ! {
+    if let (Some(dp), Some(mut cp)) = (
+        stm32::Peripherals::take(),
+        cortex_m::peripheral::Peripherals::take(),
+    ) {
+        cp.DCB.enable_trace();
+        cp.DWT.enable_cycle_counter();
+        let board = Board::new(dp, cp.SYST, cp.ITM, ClockPreset::Hsi16);
+        let pins = board.pins;
+        let mut itm = board.itm;
+        cortex_m::interrupt::free(|cs| EVENTS.borrow(cs).replace(Some(Queue::new())));
+
+        exti::init(board.spare.EXTI, board.spare.SYSCFG, DWT::cycle_count);
+        let _d2 = pins.d2.into_pull_up_input();
+        let _d4 = pins.d4.into_pull_up_input();
+        let _d7 = pins.d7.into_pull_up_input();
+        let _a2 = pins.a2.into_pull_up_input();
+        let _d5 = pins.d5.into_pull_up_input();
+        let watched = [
+            ("D2", Port::A, 10),
+            ("D4", Port::B, 5),
+            ("D7", Port::A, 8),
+            ("A2", Port::A, 4),
+            ("D5", Port::B, 4),
+        ];
+        // Run `monitor tpiu config internal itm.txt uart off 16000000` in gdb to see the output
+        for &(name, port, line) in &watched {
+            match exti::listen(port, line, Trigger::Both, record) {
+                Ok(()) => unsafe { NVIC::unmask(exti::vector(line)) },
+                Err(e) => iprintln!(&mut itm.stim[0], "Can't watch {}: {:?}", name, e),
+            }
+        }
+
+        loop {
+            let event = cortex_m::interrupt::free(|cs| {
+                let event = EVENTS.borrow(cs).borrow_mut().as_mut()?.dequeue();
+                if event.is_none() {
+                    cortex_m::asm::wfi();
+                }
+                event
+            });
+            if let Some(event) = event {
+                iprintln!(
+                    &mut itm.stim[0],
+                    "P{:?}{} {:?} at {}",
+                    event.port,
+                    event.line,
+                    event.edge,
+                    event.timestamp
+                );
+            }
+        }
+    }
+
+    loop {}
+}
+
+#[interrupt]
+fn EXTI3() {
+    exti::interrupt();
+}
+
+#[interrupt]
+fn EXTI4() {
+    exti::interrupt();
+}
+
+#[interrupt]
+fn EXTI9_5() {
+    exti::interrupt();
+}
+
+#[interrupt]
+fn EXTI15_10() {
+    exti::interrupt();
+}
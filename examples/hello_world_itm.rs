@@ -7,15 +7,13 @@ use panic_halt as _; // you can put a breakpoint on `rust_begin_unwind` to catch
                      // use panic_itm as _; // logs messages over ITM; requires ITM support
                      // use panic_semihosting as _; // logs messages to the host stderr; requires a debugger
 
-use crate::hal::{prelude::*, stm32};
 #[allow(unused_extern_crates)]
-use cortex_m::{asm::bkpt, iprint, iprintln, peripheral::ITM};
+use cortex_m::iprintln;
 use cortex_m_rt::entry;
-use stm32f4xx_hal as hal;
+use sandbox_stm32f4_rust::build_info::BUILD_INFO;
 
 #[entry]
 fn main() -> ! {
-    let dp = stm32::Peripherals::take().unwrap();
     let cp = cortex_m::Peripherals::take().unwrap();
 
     // Run `monitor tpiu config internal itm.txt uart off 16000000` in gdb or add it to openocd.gdb
@@ -24,6 +22,7 @@ fn main() -> ! {
         &mut itm.stim[0],
         "PandasPandasPandasPandasPandasPandasPandas 🐼"
     );
+    iprintln!(&mut itm.stim[0], "{}", BUILD_INFO);
 
     loop {}
 }
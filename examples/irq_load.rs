@@ -0,0 +1,125 @@
+//! How much CPU the TIM2 and USART2 handlers take: TIM2 ticks at 1kHz and blinks the LED, USART2
+//! echoes whatever comes in over the serial port at 115200 baud, and the main loop sleeps. Once a
+//! second, the load and TIM2's worst latency go out over ITM. USART2 has no latency figure, since
+//! nothing records when a byte arrived. Paste a big chunk of text into the serial port to give
+//! USART2 something to do.
+
+#![no_std]
+#![no_main]
+
+use panic_halt as _; // you can put a breakpoint on `rust_begin_unwind` to catch panics
+                     // use panic_abort as _; // requires nightly
+                     // use panic_itm as _; // logs messages over ITM; requires ITM support
+                     // use panic_semihosting as _; // logs messages to the host stderr; requires a debugger
+
+use core::{
+    cell::RefCell,
+    sync::atomic::{AtomicBool, AtomicU32, Ordering},
+};
+use cortex_m::{interrupt::Mutex, iprintln};
+use cortex_m_rt::entry;
+use sandbox_stm32f4_rust::{
+    board::{self, Board, ClockPreset, Led},
+    irq_load,
+    uart_driver::{self, UartContext},
+};
+use stm32f4xx_hal::{
+    interrupt,
+    prelude::*,
+    stm32::{self, Interrupt, TIM2},
+    timer::{Event, Timer},
+};
+
+struct Blinky {
+    led: Led,
+    timer: Timer<TIM2>,
+    ticks: u32,
+}
+
+static BLINKY: Mutex<RefCell<Option<Blinky>>> = Mutex::new(RefCell::new(None));
+static SERIAL: Mutex<RefCell<Option<UartContext>>> = Mutex::new(RefCell::new(None));
+/// System clock cycles per TIM2 count
+static CYCLES_PER_COUNT: AtomicU32 = AtomicU32::new(0);
+static REPORT_DUE: AtomicBool = AtomicBool::new(false);
+
+#[entry]
+fn main() -> ! {
+    if let (Some(dp), Some(mut cp)) = (
+        stm32::Peripherals::take(),
+        cortex_m::peripheral::Peripherals::take(),
+    ) {
+        let board = Board::new(dp, cp.SYST, cp.ITM, ClockPreset::Mhz84);
+        let clocks = board.clocks;
+        let mut itm = board.itm;
+        iprintln!(&mut itm.stim[0], "Boot");
+        irq_load::start(
+            &mut cp.DCB,
+            &mut cp.DWT,
+            clocks,
+            &[Interrupt::TIM2, Interrupt::USART2],
+        );
+
+        let mut timer = Timer::tim2(board.spare.TIM2, 1.khz(), clocks);
+        let psc = unsafe { (*TIM2::ptr()).psc.read().bits() };
+        CYCLES_PER_COUNT.store(
+            clocks.sysclk().0 / board::apb1_timer_hz(clocks) * (psc + 1),
+            Ordering::Relaxed,
+        );
+        timer.listen(Event::TimeOut);
+        let serial = board.serial;
+        let led = board.led;
+        cortex_m::interrupt::free(|cs| {
+            BLINKY.borrow(cs).replace(Some(Blinky {
+                led,
+                timer,
+                ticks: 0,
+            }));
+            SERIAL.borrow(cs).replace(Some(serial));
+        });
+        unsafe {
+            cortex_m::peripheral::NVIC::unmask(Interrupt::TIM2);
+            cortex_m::peripheral::NVIC::unmask(Interrupt::USART2);
+        }
+
+        loop {
+            irq_load::idle();
+            if REPORT_DUE.swap(false, Ordering::Relaxed) {
+                irq_load::report_itm(&mut itm.stim[0]);
+            }
+        }
+    }
+
+    loop {}
+}
+
+#[interrupt]
+fn TIM2() {
+    // TIM2 has been counting since the update event that raised this
+    let counts = unsafe { (*TIM2::ptr()).cnt.read().bits() };
+    let _irq = irq_load::enter_late(
+        Interrupt::TIM2,
+        counts * CYCLES_PER_COUNT.load(Ordering::Relaxed),
+    );
+    cortex_m::interrupt::free(|cs| {
+        if let Some(blinky) = BLINKY.borrow(cs).borrow_mut().as_mut() {
+            blinky.timer.clear_interrupt(Event::TimeOut);
+            blinky.ticks += 1;
+            if blinky.ticks.is_multiple_of(500) {
+                blinky.led.toggle().unwrap();
+            }
+            if blinky.ticks.is_multiple_of(1000) {
+                REPORT_DUE.store(true, Ordering::Relaxed);
+            }
+        }
+    });
+}
+
+#[interrupt]
+fn USART2() {
+    let _irq = irq_load::enter(Interrupt::USART2);
+    cortex_m::interrupt::free(|cs| {
+        if let Some(serial) = SERIAL.borrow(cs).borrow_mut().as_mut() {
+            uart_driver::interrupt(serial);
+        }
+    });
+}
@@ -0,0 +1,44 @@
+//! Dims the user LED with PWM: a smooth ramp up to full brightness, then slow breathing that carries
+//! on while the CPU sleeps.
+
+#![no_std]
+#![no_main]
+
+use panic_halt as _; // you can put a breakpoint on `rust_begin_unwind` to catch panics
+                     // use panic_abort as _; // requires nightly
+                     // use panic_itm as _; // logs messages over ITM; requires ITM support
+                     // use panic_semihosting as _; // logs messages to the host stderr; requires a debugger
+
+use cortex_m_rt::entry;
+use sandbox_stm32f4_rust::{
+    board::{Board, ClockPreset},
+    led_pwm::LedPwm,
+};
+use stm32f4xx_hal::{prelude::*, stm32};
+
+#[entry]
+fn main() -> ! {
+    if let (Some(dp), Some(cp)) = (
+        stm32::Peripherals::take(),
+        cortex_m::peripheral::Peripherals::take(),
+    ) {
+        let board = Board::new(dp, cp.SYST, cp.ITM, ClockPreset::Mhz84);
+        let mut delay = board.delay;
+        let mut led = LedPwm::new(board.led, board.spare.TIM2, board.spare.DMA1, board.clocks);
+
+        // Thanks to gamma correction, this looks like an even fade rather than a jump to bright
+        for level in 0..=255 {
+            led.set_brightness(level);
+            delay.delay_ms(8_u32);
+        }
+        delay.delay_ms(1000_u32);
+
+        // From here on the DMA does all the work
+        led.breathe(3000);
+        loop {
+            cortex_m::asm::wfi();
+        }
+    }
+
+    loop {}
+}
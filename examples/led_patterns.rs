@@ -0,0 +1,94 @@
+//! LED patterns played from a timer interrupt. Click the user button to move on to the next one and
+//! hold it down for an SOS in Morse.
+
+#![no_std]
+#![no_main]
+
+use panic_halt as _; // you can put a breakpoint on `rust_begin_unwind` to catch panics
+                     // use panic_abort as _; // requires nightly
+                     // use panic_itm as _; // logs messages over ITM; requires ITM support
+                     // use panic_semihosting as _; // logs messages to the host stderr; requires a debugger
+
+use cortex_m::peripheral::NVIC;
+use cortex_m_rt::entry;
+use sandbox_stm32f4_rust::{
+    board::{Board, ClockPreset},
+    button::{self, ButtonEvent, Timings},
+    exti,
+    led::{self, Pattern},
+};
+use stm32f4xx_hal::{
+    interrupt,
+    stm32::{self, Interrupt},
+};
+
+const PATTERNS: [Pattern; 6] = [
+    led::HEARTBEAT,
+    led::SLOW_BLINK,
+    led::FAST_BLINK,
+    Pattern::BlinkCode(3),
+    Pattern::Morse("hello world"),
+    Pattern::Off,
+];
+
+#[entry]
+fn main() -> ! {
+    if let (Some(dp), Some(cp)) = (
+        stm32::Peripherals::take(),
+        cortex_m::peripheral::Peripherals::take(),
+    ) {
+        let board = Board::new(dp, cp.SYST, cp.ITM, ClockPreset::Hsi16);
+
+        led::start(board.led, board.spare.TIM11, board.clocks, PATTERNS[0]);
+        exti::init(board.spare.EXTI, board.spare.SYSCFG, || 0);
+        // No need to wait around for double-clicks
+        let timings = Timings {
+            double_click_ms: 0,
+            ..Timings::default()
+        };
+        button::start(board.button, board.spare.TIM4, board.clocks, timings).unwrap();
+        unsafe {
+            NVIC::unmask(Interrupt::TIM1_TRG_COM_TIM11);
+            NVIC::unmask(Interrupt::EXTI15_10);
+            NVIC::unmask(Interrupt::TIM4);
+        }
+
+        let mut current = 0;
+        loop {
+            // With interrupts off, an event showing up between checking and sleeping still wakes
+            // up the WFI
+            let event = cortex_m::interrupt::free(|_| {
+                let event = button::next_event();
+                if event.is_none() {
+                    cortex_m::asm::wfi();
+                }
+                event
+            });
+            match event {
+                Some(ButtonEvent::Click) => {
+                    current = (current + 1) % PATTERNS.len();
+                    led::set_pattern(PATTERNS[current]);
+                }
+                Some(ButtonEvent::LongPress) => led::set_pattern(Pattern::Morse("SOS")),
+                _ => {}
+            }
+        }
+    }
+
+    loop {}
+}
+
+#[interrupt]
+fn TIM1_TRG_COM_TIM11() {
+    led::interrupt();
+}
+
+#[interrupt]
+fn EXTI15_10() {
+    exti::interrupt();
+}
+
+#[interrupt]
+fn TIM4() {
+    button::timer_interrupt();
+}
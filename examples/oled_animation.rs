@@ -0,0 +1,95 @@
+//! Sprite animations on top of the usual picture: a spinner looping in one corner and a circle
+//! pulsing in and out in the other, XORed so it shows up on both light and dark parts of the
+//! picture. TIM3 sets the frame rate. Hook up the display as in `oled_blinky`.
+
+#![no_std]
+#![no_main]
+
+use panic_halt as _; // you can put a breakpoint on `rust_begin_unwind` to catch panics
+                     // use panic_abort as _; // requires nightly
+                     // use panic_itm as _; // logs messages over ITM; requires ITM support
+                     // use panic_semihosting as _; // logs messages to the host stderr; requires a debugger
+
+use cortex_m_rt::entry;
+use embedded_graphics::prelude::*;
+use sandbox_stm32f4_rust::{
+    animation::{self, Animation, Blend, Playback, SpriteSheet},
+    assets,
+    board::{Board, ClockPreset},
+    demo,
+    display::{Config, Controller},
+};
+use stm32f4xx_hal::{
+    interrupt,
+    prelude::*,
+    spi,
+    stm32::{self, Interrupt},
+};
+
+// SH1106 Pins
+// SPI1 interface (AF5)
+// SCK: PA5 (D13, normally the user LED)
+// MOSI: PA7 (D11)
+// RST: PA9 (D8)
+// D/C: PC7 (D9)
+
+const FPS: u32 = 15;
+const SPINNER: SpriteSheet = SpriteSheet::new(&assets::SPINNER, 8);
+const PULSE: SpriteSheet = SpriteSheet::new(&assets::PULSE, 7);
+
+#[entry]
+fn main() -> ! {
+    if let (Some(dp), Some(cp)) = (
+        stm32::Peripherals::take(),
+        cortex_m::peripheral::Peripherals::take(),
+    ) {
+        let board = Board::new(dp, cp.SYST, cp.ITM, ClockPreset::Mhz48);
+        let pins = board.pins;
+
+        let mut oled_rst = pins.d8.into_push_pull_output();
+        let oled_dc = pins.d9.into_push_pull_output();
+        let sck = board.led.into_alternate_af5();
+        let mosi = pins.d11.into_alternate_af5();
+        let spi1 = spi::Spi::spi1(
+            board.spare.SPI1,
+            (sck, spi::NoMiso, mosi),
+            spi::Mode {
+                polarity: spi::Polarity::IdleLow,
+                phase: spi::Phase::CaptureOnFirstTransition,
+            },
+            8.mhz().into(),
+            board.clocks,
+        );
+        let mut delay = board.delay;
+
+        let mut disp = Config::new(Controller::Sh1106).spi_without_cs(spi1, oled_dc);
+        disp.reset(&mut oled_rst, &mut delay).unwrap();
+        disp.init().unwrap();
+
+        animation::start_frame_timer(board.spare.TIM3, FPS, board.clocks);
+        unsafe {
+            cortex_m::peripheral::NVIC::unmask(Interrupt::TIM3);
+        }
+
+        let mut spinner = Animation::new(&SPINNER, Playback::Loop);
+        let mut pulse = Animation::new(&PULSE, Playback::PingPong);
+        loop {
+            let frames = animation::wait_for_frame();
+            spinner.advance(frames);
+            pulse.advance(frames);
+
+            // Only the pixels that end up different get sent, so redrawing the background is cheap
+            demo::draw_kes(&mut disp).unwrap();
+            spinner.draw(Point::new(110, 2), Blend::Transparent, &mut disp);
+            pulse.draw(Point::new(2, 46), Blend::Xor, &mut disp);
+            disp.flush().unwrap();
+        }
+    }
+
+    loop {}
+}
+
+#[interrupt]
+fn TIM3() {
+    animation::interrupt();
+}
@@ -10,23 +10,23 @@ use panic_halt as _; // you can put a breakpoint on `rust_begin_unwind` to catch
                      // use panic_semihosting as _; // logs messages to the host stderr; requires a debugger
 
 use cortex_m_rt::entry;
-use embedded_graphics::{
-    image::{Image, ImageRawLE},
-    pixelcolor::BinaryColor,
-    prelude::*,
-    primitives::{Circle, Rectangle, Triangle},
-    style::PrimitiveStyleBuilder,
+use sandbox_stm32f4_rust::{
+    board::{Board, ClockPreset},
+    demo,
+    display::{Config, Controller},
+    framebuffer::Framebuffer,
 };
-use sh1106::{interface::DisplayInterface, prelude::*, Builder};
-use stm32f4xx_hal as hal;
+use sh1106::interface::DisplayInterface;
 use stm32f4xx_hal::{prelude::*, spi, stm32};
 
 // SH1106 Pins
 // SPI1 interface (AF5)
-// SCK: PA5
-// MOSI: PA7
-// RST: PA9
-// D/C: PC7
+// SCK: PA5 (D13, normally the user LED)
+// MOSI: PA7 (D11)
+// RST: PA9 (D8)
+// D/C: PC7 (D9)
+// An SSD1306 on the same pins works too. See `oled_i2c` for I2C modules.
+const CONTROLLER: Controller = Controller::Sh1106;
 
 #[entry]
 fn main() -> ! {
@@ -34,21 +34,18 @@ fn main() -> ! {
         stm32::Peripherals::take(),
         cortex_m::peripheral::Peripherals::take(),
     ) {
-        let rcc = dp.RCC.constrain();
-        let clocks = rcc.cfgr.sysclk(16.mhz()).freeze();
-
-        let gpioa = dp.GPIOA.split();
-        let gpioc = dp.GPIOC.split();
+        let board = Board::new(dp, cp.SYST, cp.ITM, ClockPreset::Hsi16);
+        let pins = board.pins;
 
         // Set up OLED GPIO pins
-        let mut oled_rst = gpioa.pa9.into_push_pull_output();
-        let oled_dc = gpioc.pc7.into_push_pull_output();
+        let mut oled_rst = pins.d8.into_push_pull_output();
+        let oled_dc = pins.d9.into_push_pull_output();
 
-        // Set up SPI1 for OLED
-        let sck = gpioa.pa5.into_alternate_af5();
-        let mosi = gpioa.pa7.into_alternate_af5();
+        // Set up SPI1 for OLED. SCK shares a pin with the LED, so the LED is out of commission.
+        let sck = board.led.into_alternate_af5();
+        let mosi = pins.d11.into_alternate_af5();
         let spi1 = spi::Spi::spi1(
-            dp.SPI1,
+            board.spare.SPI1,
             (sck, spi::NoMiso, mosi),
             spi::Mode {
                 polarity: spi::Polarity::IdleLow,
@@ -56,25 +53,26 @@ fn main() -> ! {
             },
             // Works but seems out of spec of the OLED datasheet ¯\_(ツ)_/¯
             8.mhz().into(),
-            clocks,
+            board.clocks,
         );
 
-        let mut delay = hal::delay::Delay::new(cp.SYST, clocks);
+        let mut delay = board.delay;
 
         // Set up OLED screen
-        let mut disp: GraphicsMode<_> = Builder::new()
-            .connect_spi(spi1, oled_dc, sh1106::builder::NoOutputPin::new())
-            .into();
+        // Only the parts of the screen that changed get sent on each flush
+        let mut disp = Config::new(CONTROLLER).spi_without_cs(spi1, oled_dc);
 
         disp.reset(&mut oled_rst, &mut delay).unwrap();
         disp.init().unwrap();
 
         loop {
-            draw_kes(&mut disp);
+            demo::draw_kes(&mut disp).unwrap();
+            disp.flush().unwrap();
             delay.delay_ms(3000_u32);
             clear(&mut disp);
             delay.delay_ms(250_u32);
-            //draw_shapes(&mut disp);
+            //demo::draw_shapes(&mut disp).unwrap();
+            //disp.flush().unwrap();
             //delay.delay_ms(250_u32);
         }
     }
@@ -83,65 +81,10 @@ fn main() -> ! {
 }
 
 /// Clear screen
-fn clear<T: DisplayInterface>(disp: &mut GraphicsMode<T>)
+fn clear<T: DisplayInterface>(disp: &mut Framebuffer<T>)
 where
     T::Error: core::fmt::Debug,
 {
     disp.clear();
     disp.flush().unwrap()
 }
-
-/// Draw a picture
-fn draw_kes<T: DisplayInterface>(disp: &mut GraphicsMode<T>)
-where
-    T::Error: core::fmt::Debug,
-{
-    let im: ImageRawLE<BinaryColor> = ImageRawLE::new(include_bytes!("./kes.raw"), 128, 64);
-    Image::new(&im, Point::new(0, 0)).draw(disp).unwrap();
-    disp.flush().unwrap()
-}
-
-/// Draw the embedded_graphics equivalent of hello world
-fn draw_shapes<T: DisplayInterface>(disp: &mut GraphicsMode<T>)
-where
-    T::Error: core::fmt::Debug,
-{
-    let yoffset = 20;
-
-    let style = PrimitiveStyleBuilder::new()
-        .stroke_width(1)
-        .stroke_color(BinaryColor::On)
-        .build();
-
-    // screen outline
-    // default display size is 128x64 if you don't pass a _DisplaySize_
-    // enum to the _Builder_ struct
-    Rectangle::new(Point::new(0, 0), Point::new(127, 63))
-        .into_styled(style)
-        .draw(disp)
-        .unwrap();
-
-    // triangle
-    Triangle::new(
-        Point::new(16, 16 + yoffset),
-        Point::new(16 + 16, 16 + yoffset),
-        Point::new(16 + 8, yoffset),
-    )
-    .into_styled(style)
-    .draw(disp)
-    .unwrap();
-
-    // square
-    Rectangle::new(Point::new(52, yoffset), Point::new(52 + 16, 16 + yoffset))
-        .into_styled(style)
-        .draw(disp)
-        .unwrap();
-
-    // circle
-    Circle::new(Point::new(96, yoffset + 8), 8)
-        .into_styled(style)
-        .draw(disp)
-        .unwrap();
-
-    disp.flush().unwrap();
-}
@@ -0,0 +1,127 @@
+//! Mirrors the serial console onto the SH1106 OLED. Whatever is typed into the virtual COM port shows
+//! up on screen, along with a few status lines. Hook up the display as in `oled_blinky`.
+
+#![no_std]
+#![no_main]
+
+use panic_halt as _; // you can put a breakpoint on `rust_begin_unwind` to catch panics
+                     // use panic_abort as _; // requires nightly
+                     // use panic_itm as _; // logs messages over ITM; requires ITM support
+                     // use panic_semihosting as _; // logs messages to the host stderr; requires a debugger
+
+use core::{cell::RefCell, fmt::Write, ops::DerefMut};
+use cortex_m::interrupt::Mutex;
+use cortex_m_rt::entry;
+use heapless::{consts::U32, Vec};
+use sandbox_stm32f4_rust::{
+    board::{Board, ClockPreset},
+    build_info::BUILD_INFO,
+    console::Console,
+    framebuffer::Framebuffer,
+    uart_driver::{self, UartContext},
+};
+use sh1106::Builder;
+use stm32f4xx_hal::{
+    interrupt,
+    prelude::*,
+    spi,
+    stm32::{self, Interrupt},
+};
+
+// SH1106 Pins
+// SPI1 interface (AF5)
+// SCK: PA5 (D13, normally the user LED)
+// MOSI: PA7 (D11)
+// RST: PA9 (D8)
+// D/C: PC7 (D9)
+
+static UART_CTX: Mutex<RefCell<Option<UartContext>>> = Mutex::new(RefCell::new(None));
+
+#[entry]
+fn main() -> ! {
+    if let (Some(dp), Some(cp)) = (
+        stm32::Peripherals::take(),
+        cortex_m::peripheral::Peripherals::take(),
+    ) {
+        let board = Board::new(dp, cp.SYST, cp.ITM, ClockPreset::Mhz48);
+        let pins = board.pins;
+
+        let mut oled_rst = pins.d8.into_push_pull_output();
+        let oled_dc = pins.d9.into_push_pull_output();
+        let sck = board.led.into_alternate_af5();
+        let mosi = pins.d11.into_alternate_af5();
+        let spi1 = spi::Spi::spi1(
+            board.spare.SPI1,
+            (sck, spi::NoMiso, mosi),
+            spi::Mode {
+                polarity: spi::Polarity::IdleLow,
+                phase: spi::Phase::CaptureOnFirstTransition,
+            },
+            8.mhz().into(),
+            board.clocks,
+        );
+        let mut delay = board.delay;
+
+        let mut disp: Framebuffer<_> = Builder::new()
+            .connect_spi(spi1, oled_dc, sh1106::builder::NoOutputPin::new())
+            .into();
+        disp.reset(&mut oled_rst, &mut delay).unwrap();
+        disp.init().unwrap();
+
+        let mut console = Console::new(disp);
+        console.clear().unwrap();
+        write!(
+            console,
+            "v{} {:.8}\n{}\n\x1b[7;1H--- serial below ---\n> ",
+            BUILD_INFO.version(),
+            BUILD_INFO.git_hash(),
+            BUILD_INFO.build_time()
+        )
+        .unwrap();
+
+        let serial_ctx = board.serial;
+        cortex_m::interrupt::free(|cs| {
+            *UART_CTX.borrow(cs).borrow_mut() = Some(serial_ctx);
+        });
+        unsafe {
+            cortex_m::peripheral::NVIC::unmask(Interrupt::USART2);
+        }
+
+        loop {
+            // Grab whatever came in since last time, then draw it outside of the critical section
+            let mut received: Vec<u8, U32> = Vec::new();
+            cortex_m::interrupt::free(|cs| {
+                let mut cell = UART_CTX.borrow(cs).borrow_mut();
+                let serial_ctx = cell.deref_mut().as_mut().unwrap();
+                while let Some(byte) = serial_ctx.rx_queue.dequeue() {
+                    received.push(byte).ok();
+                }
+            });
+            if received.is_empty() {
+                cortex_m::asm::wfi();
+                continue;
+            }
+            for &byte in received.iter() {
+                match byte {
+                    // Terminals send a bare carriage return for enter
+                    b'\r' => console.write_bytes(b"\n> "),
+                    // Delete moves back and blanks the character
+                    0x7f => console.write_bytes(b"\x08\x1b[K"),
+                    _ => console.write_bytes(&[byte]),
+                }
+            }
+            console.flush().unwrap();
+        }
+    }
+
+    loop {}
+}
+
+#[interrupt]
+fn USART2() {
+    cortex_m::interrupt::free(|cs| {
+        let mut cell = UART_CTX.borrow(cs).borrow_mut();
+        let serial_ctx = cell.deref_mut().as_mut().unwrap();
+        uart_driver::interrupt(serial_ctx);
+    });
+}
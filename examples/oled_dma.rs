@@ -0,0 +1,138 @@
+//! Bounces a ball around the SH1106 OLED, flushing each frame via DMA. While a frame goes out, the
+//! main loop counts how many times it could have done something else, and reports that count over
+//! ITM every 100 frames. Hook up the display as in `oled_blinky`.
+
+#![no_std]
+#![no_main]
+
+use panic_halt as _; // you can put a breakpoint on `rust_begin_unwind` to catch panics
+                     // use panic_abort as _; // requires nightly
+                     // use panic_itm as _; // logs messages over ITM; requires ITM support
+                     // use panic_semihosting as _; // logs messages to the host stderr; requires a debugger
+
+use core::sync::atomic::{AtomicU32, Ordering};
+use cortex_m::iprintln;
+use cortex_m_rt::entry;
+use embedded_graphics::{
+    pixelcolor::BinaryColor, prelude::*, primitives::Circle, style::PrimitiveStyle,
+};
+use sandbox_stm32f4_rust::{
+    board::{Board, ClockPreset},
+    framebuffer::Framebuffer,
+    oled_dma::{self, DmaInterface},
+};
+use sh1106::{
+    displayrotation::DisplayRotation, mode::displaymode::DisplayModeTrait, prelude::*,
+    properties::DisplayProperties,
+};
+use stm32f4xx_hal::{
+    interrupt,
+    prelude::*,
+    spi,
+    stm32::{self, Interrupt},
+};
+
+// SH1106 Pins
+// SPI1 interface (AF5)
+// SCK: PA5 (D13, normally the user LED)
+// MOSI: PA7 (D11)
+// RST: PA9 (D8)
+// D/C: PC7 (D9)
+
+const RADIUS: i32 = 6;
+
+/// Times the DMA queue ran dry, counted by the completion callback. That's at least once per frame,
+/// more if the queue catches up with the flush partway through.
+static QUEUE_DRAINED: AtomicU32 = AtomicU32::new(0);
+
+#[entry]
+fn main() -> ! {
+    if let (Some(dp), Some(cp)) = (
+        stm32::Peripherals::take(),
+        cortex_m::peripheral::Peripherals::take(),
+    ) {
+        let board = Board::new(dp, cp.SYST, cp.ITM, ClockPreset::Mhz84);
+        let pins = board.pins;
+        let mut itm = board.itm;
+
+        let mut oled_rst = pins.d8.into_push_pull_output();
+        let oled_dc = pins.d9.into_push_pull_output();
+        let sck = board.led.into_alternate_af5();
+        let mosi = pins.d11.into_alternate_af5();
+        let spi1 = spi::Spi::spi1(
+            board.spare.SPI1,
+            (sck, spi::NoMiso, mosi),
+            spi::Mode {
+                polarity: spi::Polarity::IdleLow,
+                phase: spi::Phase::CaptureOnFirstTransition,
+            },
+            8.mhz().into(),
+            board.clocks,
+        );
+        let mut delay = board.delay;
+
+        let iface = DmaInterface::new(spi1, board.spare.DMA2, oled_dc);
+        oled_dma::set_on_complete(|| {
+            QUEUE_DRAINED.fetch_add(1, Ordering::Relaxed);
+        });
+        unsafe {
+            cortex_m::peripheral::NVIC::unmask(Interrupt::DMA2_STREAM3);
+        }
+
+        let mut disp = Framebuffer::new(DisplayProperties::new(
+            iface,
+            DisplaySize::Display128x64,
+            DisplayRotation::Rotate0,
+        ));
+        disp.reset(&mut oled_rst, &mut delay).unwrap();
+        disp.init().unwrap();
+
+        let (width, height) = (128, 64);
+        let (mut x, mut y, mut dx, mut dy) = (RADIUS, RADIUS, 2, 1);
+        let mut idle_spins: u32 = 0;
+        let mut frames: u32 = 0;
+        let style = PrimitiveStyle::with_fill(BinaryColor::On);
+        loop {
+            disp.clear();
+            Circle::new(Point::new(x, y), RADIUS as u32)
+                .into_styled(style)
+                .draw(&mut disp)
+                .unwrap();
+            // Returns as soon as the changes are queued up
+            disp.flush().unwrap();
+
+            // Free time while the frame goes out
+            while oled_dma::is_busy() {
+                idle_spins += 1;
+            }
+
+            x += dx;
+            y += dy;
+            if x <= RADIUS || x >= width - 1 - RADIUS {
+                dx = -dx;
+            }
+            if y <= RADIUS || y >= height - 1 - RADIUS {
+                dy = -dy;
+            }
+
+            frames += 1;
+            if frames.is_multiple_of(100) {
+                iprintln!(
+                    &mut itm.stim[0],
+                    "{} frames, queue drained {} times, {} idle spins per frame",
+                    frames,
+                    QUEUE_DRAINED.load(Ordering::Relaxed),
+                    idle_spins / 100
+                );
+                idle_spins = 0;
+            }
+        }
+    }
+
+    loop {}
+}
+
+#[interrupt]
+fn DMA2_STREAM3() {
+    oled_dma::interrupt();
+}
@@ -0,0 +1,54 @@
+//! Same picture as `oled_blinky`, on an I2C module instead. Most of these have an SSD1306 and no
+//! reset pin.
+
+#![no_std]
+#![no_main]
+
+use panic_halt as _; // you can put a breakpoint on `rust_begin_unwind` to catch panics
+                     // use panic_abort as _; // requires nightly
+                     // use panic_itm as _; // logs messages over ITM; requires ITM support
+                     // use panic_semihosting as _; // logs messages to the host stderr; requires a debugger
+
+use cortex_m_rt::entry;
+use sandbox_stm32f4_rust::{
+    board::{Board, ClockPreset},
+    demo,
+    display::{Config, Controller, DEFAULT_I2C_ADDRESS},
+};
+use stm32f4xx_hal::{i2c::I2c, prelude::*, stm32};
+
+// OLED Pins
+// I2C1 interface (AF4, open drain). Most modules have pull-ups on board.
+// SCL: PB8 (D15)
+// SDA: PB9 (D14)
+const CONTROLLER: Controller = Controller::Ssd1306;
+
+#[entry]
+fn main() -> ! {
+    if let (Some(dp), Some(cp)) = (
+        stm32::Peripherals::take(),
+        cortex_m::peripheral::Peripherals::take(),
+    ) {
+        let board = Board::new(dp, cp.SYST, cp.ITM, ClockPreset::Hsi16);
+        let pins = board.pins;
+
+        let scl = pins.d15.into_alternate_af4_open_drain();
+        let sda = pins.d14.into_alternate_af4_open_drain();
+        let i2c = I2c::i2c1(board.spare.I2C1, (scl, sda), 400.khz(), board.clocks);
+
+        let mut disp = Config::new(CONTROLLER).i2c(i2c, DEFAULT_I2C_ADDRESS);
+        disp.init().unwrap();
+
+        let mut delay = board.delay;
+        loop {
+            demo::draw_kes(&mut disp).unwrap();
+            disp.flush().unwrap();
+            delay.delay_ms(3000_u32);
+            disp.clear();
+            disp.flush().unwrap();
+            delay.delay_ms(250_u32);
+        }
+    }
+
+    loop {}
+}
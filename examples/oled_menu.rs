@@ -0,0 +1,164 @@
+//! Little settings menu on the SH1106 OLED, driven by the user button. A click moves to the next
+//! item and a long press selects it. Hook up the display as in `oled_blinky`.
+//!
+//! The button is polled through a [Debouncer] rather than the interrupt-driven driver in
+//! `button`, since the main loop wakes up every few milliseconds to redraw anyway.
+
+#![no_std]
+#![no_main]
+
+use panic_halt as _; // you can put a breakpoint on `rust_begin_unwind` to catch panics
+                     // use panic_abort as _; // requires nightly
+                     // use panic_itm as _; // logs messages over ITM; requires ITM support
+                     // use panic_semihosting as _; // logs messages to the host stderr; requires a debugger
+
+use core::fmt::Write;
+use cortex_m_rt::entry;
+use embedded_graphics::{prelude::*, primitives::Rectangle};
+use heapless::{consts::U16, String};
+use sandbox_stm32f4_rust::{
+    board::{Board, ClockPreset},
+    build_info::BUILD_INFO,
+    button::{Debouncer, Timings},
+    framebuffer::Framebuffer,
+    ui::{Event, Menu, ProgressBar, Screen, Spinner, StatusBar, View},
+};
+use sh1106::Builder;
+use stm32f4xx_hal::{prelude::*, spi, stm32};
+
+// SH1106 Pins
+// SPI1 interface (AF5)
+// SCK: PA5 (D13, normally the user LED)
+// MOSI: PA7 (D11)
+// RST: PA9 (D8)
+// D/C: PC7 (D9)
+
+const POLL_MS: u32 = 10;
+const MENU_ITEMS: [&str; 3] = ["Contrast", "Progress demo", "About"];
+
+/// What's on screen below the status bar
+enum Page {
+    Menu,
+    Contrast(Spinner<'static>),
+    Progress(ProgressBar),
+}
+
+#[entry]
+fn main() -> ! {
+    if let (Some(dp), Some(cp)) = (
+        stm32::Peripherals::take(),
+        cortex_m::peripheral::Peripherals::take(),
+    ) {
+        let board = Board::new(dp, cp.SYST, cp.ITM, ClockPreset::Mhz48);
+        let pins = board.pins;
+        let button = board.button;
+
+        let mut oled_rst = pins.d8.into_push_pull_output();
+        let oled_dc = pins.d9.into_push_pull_output();
+        let sck = board.led.into_alternate_af5();
+        let mosi = pins.d11.into_alternate_af5();
+        let spi1 = spi::Spi::spi1(
+            board.spare.SPI1,
+            (sck, spi::NoMiso, mosi),
+            spi::Mode {
+                polarity: spi::Polarity::IdleLow,
+                phase: spi::Phase::CaptureOnFirstTransition,
+            },
+            8.mhz().into(),
+            board.clocks,
+        );
+        let mut delay = board.delay;
+
+        let mut disp: Framebuffer<_> = Builder::new()
+            .connect_spi(spi1, oled_dc, sh1106::builder::NoOutputPin::new())
+            .into();
+        disp.reset(&mut oled_rst, &mut delay).unwrap();
+        disp.init().unwrap();
+
+        let mut menu = Menu::new(&MENU_ITEMS);
+        let mut page = Page::Menu;
+        let mut contrast = 0x80;
+        // Double-clicks aren't used, and turning them off saves waiting to see if a click is one
+        let mut presses = Debouncer::new(Timings {
+            double_click_ms: 0,
+            ..Timings::default()
+        });
+        let mut now_ms: u32 = 0;
+        let mut about: String<U16> = String::new();
+        write!(about, "v{}", BUILD_INFO.version()).ok();
+        let mut about_open = false;
+
+        loop {
+            delay.delay_ms(POLL_MS);
+            now_ms = now_ms.wrapping_add(POLL_MS);
+
+            let mut uptime: String<U16> = String::new();
+            write!(uptime, "{}s", now_ms / 1000).ok();
+            let mut screen = Screen::new(StatusBar {
+                left: "Settings",
+                right: &uptime,
+            });
+            if about_open {
+                screen.show_modal(&about);
+            }
+
+            // The button is active low
+            presses.update(button.is_low().unwrap(), now_ms);
+            let event = presses.next_event().and_then(Event::from_button);
+            let mut back_to_menu = false;
+            match &mut page {
+                Page::Menu => match event.and_then(|event| screen.handle(event, &mut menu)) {
+                    Some(0) => {
+                        page = Page::Contrast(Spinner::new("Contrast", contrast, 0, 255, 32))
+                    }
+                    Some(1) => page = Page::Progress(ProgressBar::new(100)),
+                    Some(_) => screen.show_modal(&about),
+                    None => {}
+                },
+                Page::Contrast(spinner) => {
+                    if let Some(value) = event.and_then(|event| screen.handle(event, spinner)) {
+                        contrast = value;
+                        back_to_menu = true;
+                    }
+                    disp.set_contrast(spinner.value() as u8).unwrap();
+                }
+                Page::Progress(bar) => {
+                    bar.value += 1;
+                    back_to_menu = bar.value > bar.max || event.is_some();
+                }
+            }
+            if back_to_menu {
+                page = Page::Menu;
+            }
+
+            about_open = screen.modal.is_some();
+
+            match &page {
+                Page::Menu => screen.draw(&menu, &mut disp).unwrap(),
+                Page::Contrast(spinner) => screen.draw(spinner, &mut disp).unwrap(),
+                Page::Progress(bar) => screen.draw(&Centered(bar), &mut disp).unwrap(),
+            }
+            disp.flush().unwrap();
+        }
+    }
+
+    loop {}
+}
+
+/// Draws a progress bar in a strip across the middle of its area
+struct Centered<'a>(&'a ProgressBar);
+
+impl View for Centered<'_> {
+    fn draw<D: DrawTarget<embedded_graphics::pixelcolor::BinaryColor>>(
+        &self,
+        area: Rectangle,
+        target: &mut D,
+    ) -> Result<(), D::Error> {
+        let middle = (area.top_left.y + area.bottom_right.y) / 2;
+        let strip = Rectangle::new(
+            Point::new(area.top_left.x + 8, middle - 4),
+            Point::new(area.bottom_right.x - 8, middle + 4),
+        );
+        self.0.draw(strip, target)
+    }
+}
@@ -0,0 +1,112 @@
+//! Live plots on the SH1106 OLED: a strip chart of a made-up signal with a 1s time axis, a
+//! sparkline of how long each frame took to draw and a bar graph of the last few samples. Hook up
+//! the display as in `oled_blinky`.
+
+#![no_std]
+#![no_main]
+
+use panic_halt as _; // you can put a breakpoint on `rust_begin_unwind` to catch panics
+                     // use panic_abort as _; // requires nightly
+                     // use panic_itm as _; // logs messages over ITM; requires ITM support
+                     // use panic_semihosting as _; // logs messages to the host stderr; requires a debugger
+
+use cortex_m::peripheral::DWT;
+use cortex_m_rt::entry;
+use embedded_graphics::{prelude::*, primitives::Rectangle};
+use heapless::consts::{U128, U64};
+use sandbox_stm32f4_rust::{
+    board::{Board, ClockPreset},
+    framebuffer::Framebuffer,
+    plot::{BarGraph, Scale, Series, Sparkline, StripChart, TimeAxis},
+    ui::View,
+};
+use sh1106::Builder;
+use stm32f4xx_hal::{prelude::*, spi, stm32};
+
+// SH1106 Pins
+// SPI1 interface (AF5)
+// SCK: PA5 (D13, normally the user LED)
+// MOSI: PA7 (D11)
+// RST: PA9 (D8)
+// D/C: PC7 (D9)
+
+const SAMPLE_PERIOD_MS: u32 = 50;
+
+#[entry]
+fn main() -> ! {
+    if let (Some(dp), Some(mut cp)) = (
+        stm32::Peripherals::take(),
+        cortex_m::peripheral::Peripherals::take(),
+    ) {
+        cp.DCB.enable_trace();
+        cp.DWT.enable_cycle_counter();
+        let board = Board::new(dp, cp.SYST, cp.ITM, ClockPreset::Mhz84);
+        let pins = board.pins;
+
+        let mut oled_rst = pins.d8.into_push_pull_output();
+        let oled_dc = pins.d9.into_push_pull_output();
+        let sck = board.led.into_alternate_af5();
+        let mosi = pins.d11.into_alternate_af5();
+        let spi1 = spi::Spi::spi1(
+            board.spare.SPI1,
+            (sck, spi::NoMiso, mosi),
+            spi::Mode {
+                polarity: spi::Polarity::IdleLow,
+                phase: spi::Phase::CaptureOnFirstTransition,
+            },
+            8.mhz().into(),
+            board.clocks,
+        );
+        let mut delay = board.delay;
+        let cycles_per_us = board.clocks.sysclk().0 / 1_000_000;
+
+        let mut disp: Framebuffer<_> = Builder::new()
+            .connect_spi(spi1, oled_dc, sh1106::builder::NoOutputPin::new())
+            .into();
+        disp.reset(&mut oled_rst, &mut delay).unwrap();
+        disp.init().unwrap();
+
+        let mut signal: Series<U128> = Series::new();
+        let mut frame_times: Series<U64> = Series::new();
+        let mut phase: i32 = 0;
+        let mut noise: u32 = 1;
+
+        let chart_area = Rectangle::new(Point::zero(), Point::new(127, 45));
+        let sparkline_area = Rectangle::new(Point::new(0, 50), Point::new(63, 63));
+        let bars_area = Rectangle::new(Point::new(68, 50), Point::new(127, 63));
+        loop {
+            let start = DWT::cycle_count();
+
+            // Triangle wave with a bit of noise
+            phase = (phase + 3) % 200;
+            noise = noise.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            let jitter = (noise >> 16) as i32 % 11 - 5;
+            signal.push((phase - 100).abs() - 50 + jitter);
+
+            disp.clear();
+            let mut chart = StripChart::new(&signal);
+            chart.time_axis = Some(TimeAxis {
+                sample_period_ms: SAMPLE_PERIOD_MS,
+                tick_ms: 1000,
+            });
+            chart.draw(chart_area, &mut disp).unwrap();
+            Sparkline::new(&frame_times)
+                .draw(sparkline_area, &mut disp)
+                .unwrap();
+            let mut latest = [0; 6];
+            for (bar, value) in latest.iter_mut().rev().zip(signal.recent(6)) {
+                *bar = value;
+            }
+            let mut bars = BarGraph::new(&latest);
+            bars.scale = Scale::Fixed { min: -60, max: 60 };
+            bars.draw(bars_area, &mut disp).unwrap();
+            disp.flush().unwrap();
+
+            let elapsed_us = DWT::cycle_count().wrapping_sub(start) / cycles_per_us;
+            frame_times.push(elapsed_us as i32);
+            delay.delay_ms(SAMPLE_PERIOD_MS.saturating_sub(elapsed_us / 1000));
+        }
+    }
+
+    loop {}
+}
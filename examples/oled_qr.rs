@@ -0,0 +1,104 @@
+//! Shows the chip's unique ID as a QR code on the SH1106 OLED, with the same text next to it for
+//! anyone without a phone handy. Hook up the display as in `oled_blinky`.
+
+#![no_std]
+#![no_main]
+
+use panic_halt as _; // you can put a breakpoint on `rust_begin_unwind` to catch panics
+                     // use panic_abort as _; // requires nightly
+                     // use panic_itm as _; // logs messages over ITM; requires ITM support
+                     // use panic_semihosting as _; // logs messages to the host stderr; requires a debugger
+
+use core::fmt::Write;
+use cortex_m_rt::entry;
+use embedded_graphics::{
+    fonts::{Font6x8, Text},
+    pixelcolor::BinaryColor,
+    prelude::*,
+    primitives::Rectangle,
+    style::TextStyle,
+};
+use heapless::{consts::U32, String};
+use sandbox_stm32f4_rust::{
+    board::{Board, ClockPreset},
+    framebuffer::Framebuffer,
+    qr::{EccLevel, QrCode},
+    ui::View,
+};
+use sh1106::Builder;
+use stm32f4xx_hal::{prelude::*, signature::Uid, spi, stm32};
+
+// SH1106 Pins
+// SPI1 interface (AF5)
+// SCK: PA5 (D13, normally the user LED)
+// MOSI: PA7 (D11)
+// RST: PA9 (D8)
+// D/C: PC7 (D9)
+
+#[entry]
+fn main() -> ! {
+    if let (Some(dp), Some(cp)) = (
+        stm32::Peripherals::take(),
+        cortex_m::peripheral::Peripherals::take(),
+    ) {
+        let board = Board::new(dp, cp.SYST, cp.ITM, ClockPreset::Mhz48);
+        let pins = board.pins;
+
+        let mut oled_rst = pins.d8.into_push_pull_output();
+        let oled_dc = pins.d9.into_push_pull_output();
+        let sck = board.led.into_alternate_af5();
+        let mosi = pins.d11.into_alternate_af5();
+        let spi1 = spi::Spi::spi1(
+            board.spare.SPI1,
+            (sck, spi::NoMiso, mosi),
+            spi::Mode {
+                polarity: spi::Polarity::IdleLow,
+                phase: spi::Phase::CaptureOnFirstTransition,
+            },
+            8.mhz().into(),
+            board.clocks,
+        );
+        let mut delay = board.delay;
+
+        let mut disp: Framebuffer<_> = Builder::new()
+            .connect_spi(spi1, oled_dc, sh1106::builder::NoOutputPin::new())
+            .into();
+        disp.reset(&mut oled_rst, &mut delay).unwrap();
+        disp.init().unwrap();
+
+        // Lot number, wafer number and position on the wafer
+        let uid = Uid::get();
+        let mut serial: String<U32> = String::new();
+        write!(
+            serial,
+            "{}-{}-{}-{}",
+            uid.lot_num(),
+            uid.waf_num(),
+            uid.x(),
+            uid.y()
+        )
+        .ok();
+
+        let code = QrCode::encode(serial.as_bytes(), EccLevel::M).unwrap();
+        code.draw(Rectangle::new(Point::zero(), Point::new(63, 63)), &mut disp)
+            .unwrap();
+        let style = TextStyle::new(Font6x8, BinaryColor::On);
+        Text::new("Serial", Point::new(68, 16))
+            .into_styled(style)
+            .draw(&mut disp)
+            .unwrap();
+        // Split over lines of up to 10 characters
+        for (i, line) in serial.as_bytes().chunks(10).enumerate() {
+            Text::new(
+                core::str::from_utf8(line).unwrap_or("?"),
+                Point::new(68, 28 + 8 * i as i32),
+            )
+            .into_styled(style)
+            .draw(&mut disp)
+            .unwrap();
+        }
+        disp.flush().unwrap();
+    }
+
+    loop {}
+}
@@ -0,0 +1,82 @@
+//! Proportional text: the shrug from `uart_tx` in the large font, then a paragraph in the small
+//! font wrapped to the screen, cycling through left, centered and right alignment. Hook up the
+//! display as in `oled_blinky`.
+
+#![no_std]
+#![no_main]
+
+use panic_halt as _; // you can put a breakpoint on `rust_begin_unwind` to catch panics
+                     // use panic_abort as _; // requires nightly
+                     // use panic_itm as _; // logs messages over ITM; requires ITM support
+                     // use panic_semihosting as _; // logs messages to the host stderr; requires a debugger
+
+use cortex_m_rt::entry;
+use embedded_graphics::{prelude::*, primitives::Rectangle};
+use sandbox_stm32f4_rust::{
+    board::{Board, ClockPreset},
+    display::{Config, Controller},
+    font::{self, Align, TextBox},
+    ui::{self, View},
+};
+use stm32f4xx_hal::{prelude::*, spi, stm32};
+
+// SH1106 Pins
+// SPI1 interface (AF5)
+// SCK: PA5 (D13, normally the user LED)
+// MOSI: PA7 (D11)
+// RST: PA9 (D8)
+// D/C: PC7 (D9)
+
+const PARAGRAPH: &str = "Each glyph is only as wide as it needs to be, so an iiii takes up a lot \
+                         less room than a WWWW. Long lines wrap at spaces.";
+
+#[entry]
+fn main() -> ! {
+    if let (Some(dp), Some(cp)) = (
+        stm32::Peripherals::take(),
+        cortex_m::peripheral::Peripherals::take(),
+    ) {
+        let board = Board::new(dp, cp.SYST, cp.ITM, ClockPreset::Hsi16);
+        let pins = board.pins;
+
+        let mut oled_rst = pins.d8.into_push_pull_output();
+        let oled_dc = pins.d9.into_push_pull_output();
+        let sck = board.led.into_alternate_af5();
+        let mosi = pins.d11.into_alternate_af5();
+        let spi1 = spi::Spi::spi1(
+            board.spare.SPI1,
+            (sck, spi::NoMiso, mosi),
+            spi::Mode {
+                polarity: spi::Polarity::IdleLow,
+                phase: spi::Phase::CaptureOnFirstTransition,
+            },
+            8.mhz().into(),
+            board.clocks,
+        );
+        let mut delay = board.delay;
+
+        let mut disp = Config::new(Controller::Sh1106).spi_without_cs(spi1, oled_dc);
+        disp.reset(&mut oled_rst, &mut delay).unwrap();
+        disp.init().unwrap();
+
+        let screen = Rectangle::new(Point::zero(), Point::new(127, 63));
+        let (title, body) = ui::split_top(screen, font::LARGE.line_height() as i32 + 2);
+        loop {
+            for &align in &[Align::Left, Align::Center, Align::Right] {
+                disp.clear();
+                TextBox::new(r"¯\_(ツ)_/¯", &font::LARGE)
+                    .with_align(Align::Center)
+                    .draw(title, &mut disp)
+                    .unwrap();
+                TextBox::new(PARAGRAPH, &font::SMALL)
+                    .with_align(align)
+                    .draw(body, &mut disp)
+                    .unwrap();
+                disp.flush().unwrap();
+                delay.delay_ms(2000_u32);
+            }
+        }
+    }
+
+    loop {}
+}
@@ -0,0 +1,97 @@
+//! Times `draw_kes`, the OLED flush and the USART2 interrupt with the DWT cycle counter, and prints
+//! a report over ITM every few frames. Type into the serial port to give the interrupt some work.
+
+#![no_std]
+#![no_main]
+
+use panic_halt as _; // you can put a breakpoint on `rust_begin_unwind` to catch panics
+                     // use panic_abort as _; // requires nightly
+                     // use panic_itm as _; // logs messages over ITM; requires ITM support
+                     // use panic_semihosting as _; // logs messages to the host stderr; requires a debugger
+
+use core::cell::RefCell;
+use cortex_m::{interrupt::Mutex, iprintln};
+use cortex_m_rt::entry;
+use sandbox_stm32f4_rust::{
+    board::{Board, ClockPreset},
+    demo,
+    display::{Config, Controller},
+    profile,
+    uart_driver::{self, UartContext},
+};
+use stm32f4xx_hal::{
+    interrupt,
+    prelude::*,
+    spi,
+    stm32::{self, Interrupt},
+};
+
+// Same wiring as `oled_blinky`
+const CONTROLLER: Controller = Controller::Sh1106;
+
+static SERIAL: Mutex<RefCell<Option<UartContext>>> = Mutex::new(RefCell::new(None));
+
+#[entry]
+fn main() -> ! {
+    if let (Some(dp), Some(mut cp)) = (
+        stm32::Peripherals::take(),
+        cortex_m::peripheral::Peripherals::take(),
+    ) {
+        let board = Board::new(dp, cp.SYST, cp.ITM, ClockPreset::Mhz84);
+        profile::enable(&mut cp.DCB, &mut cp.DWT, board.clocks);
+        let pins = board.pins;
+        let mut itm = board.itm;
+        iprintln!(&mut itm.stim[0], "Boot");
+
+        let serial = board.serial;
+        cortex_m::interrupt::free(|cs| SERIAL.borrow(cs).replace(Some(serial)));
+        unsafe { cortex_m::peripheral::NVIC::unmask(Interrupt::USART2) };
+
+        let mut oled_rst = pins.d8.into_push_pull_output();
+        let oled_dc = pins.d9.into_push_pull_output();
+        // SCK shares a pin with the LED, so the LED is out of commission
+        let sck = board.led.into_alternate_af5();
+        let mosi = pins.d11.into_alternate_af5();
+        let spi1 = spi::Spi::spi1(
+            board.spare.SPI1,
+            (sck, spi::NoMiso, mosi),
+            spi::Mode {
+                polarity: spi::Polarity::IdleLow,
+                phase: spi::Phase::CaptureOnFirstTransition,
+            },
+            8.mhz().into(),
+            board.clocks,
+        );
+        let mut delay = board.delay;
+        let mut disp = Config::new(CONTROLLER).spi_without_cs(spi1, oled_dc);
+        disp.reset(&mut oled_rst, &mut delay).unwrap();
+        disp.init().unwrap();
+
+        let mut frames = 0_u32;
+        loop {
+            profile!("draw_kes", demo::draw_kes(&mut disp)).unwrap();
+            profile!("flush", disp.flush()).unwrap();
+            delay.delay_ms(500_u32);
+            disp.clear();
+            profile!("flush", disp.flush()).unwrap();
+            delay.delay_ms(100_u32);
+
+            frames += 1;
+            if frames.is_multiple_of(10) {
+                // Could just as well go over the serial port, though that'd be timing itself
+                profile::report_itm(&mut itm.stim[0]);
+            }
+        }
+    }
+
+    loop {}
+}
+
+#[interrupt]
+fn USART2() {
+    cortex_m::interrupt::free(|cs| {
+        if let Some(serial) = SERIAL.borrow(cs).borrow_mut().as_mut() {
+            profile!("uart_interrupt", uart_driver::interrupt(serial));
+        }
+    });
+}
@@ -0,0 +1,80 @@
+//! RTIC scheduled tasks on the TIM5 clock: the LED blinks every 500ms and the uptime goes out over
+//! ITM every second, along with how late the report task started.
+//!
+//! RTIC keeps SysTick for its timer queue, so this sets up the clocks and LED itself rather than
+//! going through [Board](sandbox_stm32f4_rust::board::Board).
+
+#![no_std]
+#![no_main]
+
+use panic_halt as _; // you can put a breakpoint on `rust_begin_unwind` to catch panics
+                     // use panic_abort as _; // requires nightly
+                     // use panic_itm as _; // logs messages over ITM; requires ITM support
+                     // use panic_semihosting as _; // logs messages to the host stderr; requires a debugger
+
+use cortex_m::iprintln;
+use rtic::app;
+use sandbox_stm32f4_rust::{
+    board::{ClockPreset, Led},
+    clock::{self, Duration, Instant},
+};
+use stm32f4xx_hal::prelude::*;
+
+const BLINK_PERIOD: Duration = Duration::from_millis(500);
+const REPORT_PERIOD: Duration = Duration::from_secs(1);
+
+#[app(device = stm32f4xx_hal::stm32, peripherals = true, monotonic = sandbox_stm32f4_rust::clock::Clock)]
+const APP: () = {
+    struct Resources {
+        led: Led,
+        itm: cortex_m::peripheral::ITM,
+    }
+
+    #[init(schedule = [blink, report])]
+    fn init(cx: init::Context) -> init::LateResources {
+        let rcc = cx.device.RCC.constrain();
+        let clocks = rcc.cfgr.sysclk(ClockPreset::Mhz84.sysclk()).freeze();
+        let gpioa = cx.device.GPIOA.split();
+        clock::start(cx.device.TIM5, clocks);
+
+        cx.schedule.blink(cx.start + BLINK_PERIOD).unwrap();
+        cx.schedule.report(cx.start + REPORT_PERIOD).unwrap();
+
+        init::LateResources {
+            led: gpioa.pa5.into_push_pull_output(),
+            itm: cx.core.ITM,
+        }
+    }
+
+    #[task(resources = [led], schedule = [blink])]
+    fn blink(cx: blink::Context) {
+        cx.resources.led.toggle().unwrap();
+        cx.schedule.blink(cx.scheduled + BLINK_PERIOD).unwrap();
+    }
+
+    // Run `monitor tpiu config internal itm.txt uart off 84000000` in gdb to see the output
+    #[task(resources = [itm], schedule = [report])]
+    fn report(cx: report::Context) {
+        let now = Instant::now();
+        iprintln!(
+            &mut cx.resources.itm.stim[0],
+            "up {}.{:06}s, {}us late",
+            now.as_micros() / 1_000_000,
+            now.as_micros() % 1_000_000,
+            (now - cx.scheduled).as_micros()
+        );
+        cx.schedule.report(cx.scheduled + REPORT_PERIOD).unwrap();
+    }
+
+    // Keeps the clock going past the first wraparound of TIM5. It only has to run every half hour
+    // or so, but nothing else here is as urgent.
+    #[task(binds = TIM5, priority = 2)]
+    fn tim5(_: tim5::Context) {
+        clock::interrupt();
+    }
+
+    // Software tasks need an unused interrupt to run from
+    extern "C" {
+        fn EXTI0();
+    }
+};
@@ -0,0 +1,87 @@
+//! Several software timers sharing TIM2: the LED blinks, a status line goes out over ITM every
+//! second and, after 10 seconds, a one-shot timer swaps the fast blink for a slow one.
+
+#![no_std]
+#![no_main]
+
+use panic_halt as _; // you can put a breakpoint on `rust_begin_unwind` to catch panics
+                     // use panic_abort as _; // requires nightly
+                     // use panic_itm as _; // logs messages over ITM; requires ITM support
+                     // use panic_semihosting as _; // logs messages to the host stderr; requires a debugger
+
+use core::cell::RefCell;
+use cortex_m::{interrupt::Mutex, iprintln, peripheral::ITM};
+use cortex_m_rt::entry;
+use sandbox_stm32f4_rust::{
+    board::{Board, ClockPreset, Led},
+    timer_service::{self, TimerId},
+};
+use stm32f4xx_hal::{
+    interrupt,
+    prelude::*,
+    stm32::{self, Interrupt},
+};
+
+static LED: Mutex<RefCell<Option<Led>>> = Mutex::new(RefCell::new(None));
+static ITM_PORT: Mutex<RefCell<Option<ITM>>> = Mutex::new(RefCell::new(None));
+static BLINK: Mutex<RefCell<Option<TimerId>>> = Mutex::new(RefCell::new(None));
+
+#[entry]
+fn main() -> ! {
+    if let (Some(dp), Some(cp)) = (
+        stm32::Peripherals::take(),
+        cortex_m::peripheral::Peripherals::take(),
+    ) {
+        let board = Board::new(dp, cp.SYST, cp.ITM, ClockPreset::Mhz84);
+        let (led, itm) = (board.led, board.itm);
+        cortex_m::interrupt::free(|cs| {
+            LED.borrow(cs).replace(Some(led));
+            ITM_PORT.borrow(cs).replace(Some(itm));
+        });
+
+        timer_service::start(board.spare.TIM2, board.clocks);
+        unsafe { cortex_m::peripheral::NVIC::unmask(Interrupt::TIM2) };
+
+        let blink = timer_service::every(100_000, toggle_led).unwrap();
+        cortex_m::interrupt::free(|cs| BLINK.borrow(cs).replace(Some(blink)));
+        timer_service::every(1_000_000, report).unwrap();
+        timer_service::after(10_000_000, slow_down).unwrap();
+    }
+
+    // Everything happens in the TIM2 interrupt
+    loop {
+        cortex_m::asm::wfi();
+    }
+}
+
+fn toggle_led() {
+    cortex_m::interrupt::free(|cs| {
+        if let Some(led) = LED.borrow(cs).borrow_mut().as_mut() {
+            led.toggle().unwrap();
+        }
+    });
+}
+
+// Run `monitor tpiu config internal itm.txt uart off 84000000` in gdb to see the output
+fn report() {
+    let now = timer_service::now();
+    cortex_m::interrupt::free(|cs| {
+        if let Some(itm) = ITM_PORT.borrow(cs).borrow_mut().as_mut() {
+            iprintln!(&mut itm.stim[0], "{} us since start", now);
+        }
+    });
+}
+
+fn slow_down() {
+    let blink = cortex_m::interrupt::free(|cs| BLINK.borrow(cs).borrow_mut().take());
+    if let Some(blink) = blink {
+        timer_service::cancel(blink);
+    }
+    let blink = timer_service::every(500_000, toggle_led).unwrap();
+    cortex_m::interrupt::free(|cs| BLINK.borrow(cs).replace(Some(blink)));
+}
+
+#[interrupt]
+fn TIM2() {
+    timer_service::interrupt();
+}
@@ -9,12 +9,13 @@ use panic_halt as _; // you can put a breakpoint on `rust_begin_unwind` to catch
 
 use crate::hal::{
     prelude::*,
-    serial::{config::Config, Pins, Serial},
+    serial::{Pins, Serial},
     stm32::{self, usart1, USART2},
 };
 use core::fmt::{self, Write};
 #[allow(unused_extern_crates)]
 use cortex_m_rt::entry;
+use sandbox_stm32f4_rust::board::{Board, ClockPreset};
 use stm32f4xx_hal as hal;
 
 #[allow(unused_macros)]
@@ -58,27 +59,10 @@ fn main() -> ! {
         stm32::Peripherals::take(),
         cortex_m::peripheral::Peripherals::take(),
     ) {
-        let rcc = dp.RCC.constrain();
-        let clocks = rcc.cfgr.sysclk(84.mhz()).freeze();
-
-        // Set up the LED. On the NUCLEO-F401RE it's connected to pin PA5.
-        // Calling split also powers up the GPIOA peripheral clock
-        let gpioa = dp.GPIOA.split();
-        let mut led = gpioa.pa5.into_push_pull_output();
-
-        // ST-Link is connected to USART2
-        // RX: PA3
-        // TX: PA2
-        let tx = gpioa.pa2.into_alternate_af7();
-        let rx = gpioa.pa3.into_alternate_af7();
-        let mut serial = Serial::usart2(
-            dp.USART2,
-            (tx, rx),
-            Config::default().baudrate(115200.bps()),
-            clocks,
-        )
-        .unwrap();
-        let mut delay = hal::delay::Delay::new(cp.SYST, clocks);
+        let board = Board::new(dp, cp.SYST, cp.ITM, ClockPreset::Mhz84);
+        let mut led = board.led;
+        let mut serial = board.serial.handle;
+        let mut delay = board.delay;
         let usart2 = unsafe { &mut *(USART2::ptr() as *mut usart1::RegisterBlock) };
 
         // Use peripheral access crate (PAC) write a character to the serial port's transmission data register
@@ -0,0 +1,256 @@
+//! Sprite animations on the OLED.
+//!
+//! A [SpriteSheet] is an [Asset] with its frames stacked top to bottom, so `assets/spinner.png`
+//! with eight 16x16 frames is a 16x128 image. An [Animation] keeps track of which frame to show,
+//! playing once, looping or going back and forth. Sprites [Blend] onto anything that implements
+//! [BlendTarget], e.g. a [Framebuffer]. With [Blend::Xor], drawing the same frame again erases it,
+//! so a sprite can move over a background without redrawing the background.
+//!
+//! The frame rate comes from TIM3. Call [start_frame_timer], unmask TIM3 in the NVIC and call
+//! [interrupt] from its handler. Then each time through the main loop:
+//!
+//! ```ignore
+//! let frames = animation::wait_for_frame();
+//! spinner.advance(frames);
+//! spinner.draw(top_left, Blend::Transparent, &mut disp);
+//! disp.flush()?;
+//! ```
+use crate::{assets::Asset, framebuffer::Framebuffer};
+use core::{
+    cell::RefCell,
+    sync::atomic::{AtomicU32, Ordering},
+};
+use cortex_m::interrupt::Mutex;
+use embedded_graphics::prelude::Point;
+use sh1106::interface::DisplayInterface;
+use stm32f4xx_hal::{
+    prelude::*,
+    rcc::Clocks,
+    stm32::TIM3,
+    timer::{Event, Timer},
+};
+
+/// How a sprite's pixels combine with what's already there
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Blend {
+    /// Copy the sprite over, unlit pixels and all
+    Opaque,
+    /// Only draw lit pixels, so the background shows through unlit ones
+    Transparent,
+    /// Flip whatever is under lit pixels
+    Xor,
+}
+
+/// Somewhere sprites can be drawn. A `DrawTarget` isn't enough, since XOR needs to read pixels back.
+pub trait BlendTarget {
+    /// Out of bounds pixels are off
+    fn get_pixel(&self, x: u32, y: u32) -> bool;
+    /// Out of bounds pixels are ignored
+    fn set_pixel(&mut self, x: u32, y: u32, on: bool);
+}
+
+impl<DI: DisplayInterface> BlendTarget for Framebuffer<DI> {
+    fn get_pixel(&self, x: u32, y: u32) -> bool {
+        Framebuffer::get_pixel(self, x, y)
+    }
+
+    fn set_pixel(&mut self, x: u32, y: u32, on: bool) {
+        Framebuffer::set_pixel(self, x, y, on)
+    }
+}
+
+/// Equally sized frames, stacked top to bottom in one bitmap
+#[derive(Clone, Copy)]
+pub struct SpriteSheet {
+    data: &'static [u8],
+    width: u32,
+    height: u32,
+    frames: u32,
+}
+
+impl SpriteSheet {
+    /// Split `asset` into `frames` frames. Panics if its height isn't a multiple of that.
+    pub const fn new(asset: &Asset, frames: u32) -> Self {
+        assert!(frames > 0 && asset.height.is_multiple_of(frames));
+        Self {
+            data: asset.data,
+            width: asset.width,
+            height: asset.height / frames,
+            frames,
+        }
+    }
+
+    pub fn frames(&self) -> u32 {
+        self.frames
+    }
+
+    /// Width and height of one frame
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Whether a pixel of a frame is lit. Rows are padded to a whole byte, most significant bit
+    /// first, same as the assets.
+    pub fn is_lit(&self, frame: u32, x: u32, y: u32) -> bool {
+        let row_bytes = (self.width as usize).div_ceil(8);
+        let row = (frame * self.height + y) as usize;
+        self.data[row * row_bytes + x as usize / 8] & (0x80 >> (x % 8)) != 0
+    }
+
+    /// Draw a frame with its top left corner at `top_left`. Anything off the edge is skipped.
+    pub fn draw<T: BlendTarget + ?Sized>(
+        &self,
+        frame: u32,
+        top_left: Point,
+        blend: Blend,
+        target: &mut T,
+    ) {
+        let frame = frame.min(self.frames - 1);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let (target_x, target_y) = (top_left.x + x as i32, top_left.y + y as i32);
+                if target_x < 0 || target_y < 0 {
+                    continue;
+                }
+                let (target_x, target_y) = (target_x as u32, target_y as u32);
+                let lit = self.is_lit(frame, x, y);
+                match blend {
+                    Blend::Opaque => target.set_pixel(target_x, target_y, lit),
+                    Blend::Transparent if lit => target.set_pixel(target_x, target_y, true),
+                    Blend::Xor if lit => {
+                        let on = target.get_pixel(target_x, target_y);
+                        target.set_pixel(target_x, target_y, !on);
+                    }
+                    Blend::Transparent | Blend::Xor => {}
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Playback {
+    /// Stop on the last frame
+    Once,
+    /// Start over after the last frame
+    Loop,
+    /// Go back and forth between the first and last frames
+    PingPong,
+}
+
+/// Plays a [SpriteSheet] one frame per tick
+pub struct Animation<'a> {
+    pub sheet: &'a SpriteSheet,
+    pub playback: Playback,
+    frame: u32,
+    reversing: bool,
+}
+
+impl<'a> Animation<'a> {
+    pub fn new(sheet: &'a SpriteSheet, playback: Playback) -> Self {
+        Self {
+            sheet,
+            playback,
+            frame: 0,
+            reversing: false,
+        }
+    }
+
+    /// Current frame
+    pub fn frame(&self) -> u32 {
+        self.frame
+    }
+
+    /// Only ever true for [Playback::Once]
+    pub fn is_finished(&self) -> bool {
+        self.playback == Playback::Once && self.frame == self.sheet.frames() - 1
+    }
+
+    /// Back to the first frame
+    pub fn rewind(&mut self) {
+        self.frame = 0;
+        self.reversing = false;
+    }
+
+    /// Move on by `ticks` frames, e.g. the number [wait_for_frame] returned. If the main loop falls
+    /// behind, this skips frames rather than slowing down.
+    pub fn advance(&mut self, ticks: u32) {
+        let frames = self.sheet.frames();
+        // Skip whole cycles, which end up back where they started
+        let ticks = match self.playback {
+            Playback::Once => ticks.min(frames),
+            Playback::Loop => ticks % frames,
+            Playback::PingPong => ticks % (2 * frames - 2).max(1),
+        };
+        for _ in 0..ticks {
+            self.step(frames - 1);
+        }
+    }
+
+    fn step(&mut self, last: u32) {
+        match self.playback {
+            Playback::Once => self.frame = (self.frame + 1).min(last),
+            Playback::Loop => self.frame = (self.frame + 1) % (last + 1),
+            Playback::PingPong if last == 0 => {}
+            Playback::PingPong => {
+                if self.reversing {
+                    self.frame -= 1;
+                    self.reversing = self.frame != 0;
+                } else {
+                    self.frame += 1;
+                    self.reversing = self.frame == last;
+                }
+            }
+        }
+    }
+
+    /// Draw the current frame
+    pub fn draw<T: BlendTarget + ?Sized>(&self, top_left: Point, blend: Blend, target: &mut T) {
+        self.sheet.draw(self.frame, top_left, blend, target)
+    }
+}
+
+/// Frames that came due and haven't been picked up yet
+static PENDING_FRAMES: AtomicU32 = AtomicU32::new(0);
+static TIMER: Mutex<RefCell<Option<Timer<TIM3>>>> = Mutex::new(RefCell::new(None));
+
+/// Have TIM3 tick `fps` times a second. Unmask TIM3 in the NVIC afterwards.
+pub fn start_frame_timer(tim: TIM3, fps: u32, clocks: Clocks) {
+    let mut timer = Timer::tim3(tim, fps.hz(), clocks);
+    timer.listen(Event::TimeOut);
+    PENDING_FRAMES.store(0, Ordering::Relaxed);
+    cortex_m::interrupt::free(|cs| TIMER.borrow(cs).replace(Some(timer)));
+}
+
+/// Frames that came due since the last call. 0 if it's not time for the next one yet.
+pub fn take_frames() -> u32 {
+    PENDING_FRAMES.swap(0, Ordering::Relaxed)
+}
+
+/// Sleep until it's time for the next frame. Returns the number of frames that came due, which is
+/// more than 1 if the caller fell behind.
+pub fn wait_for_frame() -> u32 {
+    loop {
+        // With interrupts off, a tick between checking and sleeping still wakes up the WFI
+        let frames = cortex_m::interrupt::free(|_| {
+            let frames = take_frames();
+            if frames == 0 {
+                cortex_m::asm::wfi();
+            }
+            frames
+        });
+        if frames > 0 {
+            return frames;
+        }
+    }
+}
+
+/// Call this from the TIM3 interrupt handler
+pub fn interrupt() {
+    cortex_m::interrupt::free(|cs| {
+        if let Some(timer) = TIMER.borrow(cs).borrow_mut().as_mut() {
+            timer.clear_interrupt(Event::TimeOut);
+            PENDING_FRAMES.fetch_add(1, Ordering::Relaxed);
+        }
+    });
+}
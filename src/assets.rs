@@ -0,0 +1,19 @@
+//! Images converted from the PNGs in the `assets/` folder at build time. Drop a PNG in there and it
+//! shows up here as a constant named after the file, e.g. `assets/kes.png` becomes [KES]. See
+//! `build/assets.rs` for the conversion options.
+use embedded_graphics::{image::ImageRawLE, pixelcolor::BinaryColor};
+
+/// A 1bpp bitmap
+pub struct Asset {
+    pub data: &'static [u8],
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Asset {
+    pub fn image(&self) -> ImageRawLE<'static, BinaryColor> {
+        ImageRawLE::new(self.data, self.width, self.height)
+    }
+}
+
+include!(concat!(env!("OUT_DIR"), "/assets.rs"));
@@ -0,0 +1,394 @@
+//! Board support for the NUCLEO-F401RE. The other Nucleo-64 boards (e.g. NUCLEO-F411RE,
+//! NUCLEO-F446RE) share its pinout, so this works for them too. [Board::new] does the setup every
+//! example needs and hands back the on-board hardware ready to go:
+//! * User LED (LD2) on PA5
+//! * User button (B1) on PC13. It's active low and has an external pull-up.
+//! * Virtual COM port via the ST-Link on USART2 (TX: PA2, RX: PA3) at 115200 baud, wrapped in a
+//!   [UartContext]
+//! * SysTick-based delay
+//! * ITM
+//! * The Arduino header pins that aren't already spoken for
+//!
+//! Pins not on the Arduino header aren't handed back. Neither are the debug pins.
+use crate::uart_driver::UartContext;
+use cortex_m::peripheral::{ITM, SYST};
+use stm32f4xx_hal as hal;
+use stm32f4xx_hal::{
+    delay::Delay,
+    gpio::{
+        gpioa::{PA0, PA1, PA10, PA4, PA5, PA6, PA7, PA8, PA9},
+        gpiob::{PB0, PB10, PB4, PB5, PB6, PB8, PB9},
+        gpioc::{PC0, PC1, PC13, PC7},
+        Floating, Input, Output, PushPull,
+    },
+    prelude::*,
+    rcc::Clocks,
+    serial::{config::Config, Serial},
+    stm32,
+    time::Hertz,
+};
+
+pub type Led = PA5<Output<PushPull>>;
+pub type Button = PC13<Input<Floating>>;
+
+/// System clock configurations. All of them run off the internal oscillator.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ClockPreset {
+    /// 16MHz straight from the internal oscillator. No PLL.
+    Hsi16,
+    /// 48MHz
+    Mhz48,
+    /// 84MHz, the fastest the STM32F401 goes
+    Mhz84,
+    /// The fastest the selected chip goes
+    Max,
+}
+
+#[cfg(feature = "stm32f401")]
+const MAX_SYSCLK_MHZ: u32 = 84;
+#[cfg(feature = "stm32f411")]
+const MAX_SYSCLK_MHZ: u32 = 100;
+#[cfg(any(feature = "stm32f405", feature = "stm32f407"))]
+const MAX_SYSCLK_MHZ: u32 = 168;
+// 180MHz needs over-drive mode, which the HAL doesn't turn on
+#[cfg(feature = "stm32f446")]
+const MAX_SYSCLK_MHZ: u32 = 168;
+
+impl ClockPreset {
+    pub fn sysclk(self) -> Hertz {
+        match self {
+            ClockPreset::Hsi16 => 16.mhz().into(),
+            ClockPreset::Mhz48 => 48.mhz().into(),
+            ClockPreset::Mhz84 => 84.mhz().into(),
+            ClockPreset::Max => MAX_SYSCLK_MHZ.mhz().into(),
+        }
+    }
+}
+
+/// What the timers on APB1 (TIM2-TIM5) count at, which is twice the APB1 clock whenever that's
+/// divided down from the system clock
+pub fn apb1_timer_hz(clocks: Clocks) -> u32 {
+    clocks.pclk1().0 * if clocks.ppre1() == 1 { 1 } else { 2 }
+}
+
+/// Same for the timers on APB2 (TIM1 and TIM9-TIM11)
+pub fn apb2_timer_hz(clocks: Clocks) -> u32 {
+    clocks.pclk2().0 * if clocks.ppre2() == 1 { 1 } else { 2 }
+}
+
+/// The 32-bit timers, which can count for a long time at a fine resolution before wrapping around
+pub trait FreeRunningTimer {
+    /// Power the timer up fresh from a reset and set it to count from 0 to `u32::MAX` at `tick_hz`,
+    /// with no flags set. Starting it is up to the caller, once everything else is in place.
+    ///
+    /// The prescaler is 16 bits, so `tick_hz` has to be at least 1/65536 of [apb1_timer_hz], e.g.
+    /// 1282Hz at 84MHz. Panics otherwise.
+    fn setup_free_running(&self, clocks: Clocks, tick_hz: u32);
+}
+
+macro_rules! free_running_timers {
+    ($($TIM:ident: ($en:ident, $rst:ident),)+) => {
+        $(
+            impl FreeRunningTimer for stm32::$TIM {
+                fn setup_free_running(&self, clocks: Clocks, tick_hz: u32) {
+                    let rcc = unsafe { &*stm32::RCC::ptr() };
+                    rcc.apb1enr.modify(|_, w| w.$en().set_bit());
+                    rcc.apb1rstr.modify(|_, w| w.$rst().set_bit());
+                    rcc.apb1rstr.modify(|_, w| w.$rst().clear_bit());
+
+                    let psc = apb1_timer_hz(clocks) / tick_hz - 1;
+                    assert!(psc <= u32::from(u16::MAX), "tick rate too slow for the prescaler");
+                    // The prescaler is 16 bits but the reload value is a full 32
+                    unsafe {
+                        self.psc.write(|w| w.bits(psc));
+                        self.arr.write(|w| w.bits(u32::MAX));
+                    }
+                    // Load the prescaler now rather than at the first wraparound
+                    self.egr.write(|w| w.ug().set_bit());
+                    self.sr.write(|w| unsafe { w.bits(0) });
+                }
+            }
+        )+
+    };
+}
+
+free_running_timers! {
+    TIM2: (tim2en, tim2rst),
+    TIM5: (tim5en, tim5rst),
+}
+
+/// Arduino header pins, named after their silkscreen labels. D0 and D1 are wired to the virtual COM
+/// port and D13 to the user LED, so those are missing here. Use [Board::led] if you need D13 for
+/// something else, e.g. as SPI1 SCK. D3 is missing too, since it's PB3, the SWO debug pin that ITM
+/// output goes out on.
+pub struct ArduinoPins {
+    pub d2: PA10<Input<Floating>>,
+    pub d4: PB5<Input<Floating>>,
+    pub d5: PB4<Input<Floating>>,
+    pub d6: PB10<Input<Floating>>,
+    pub d7: PA8<Input<Floating>>,
+    pub d8: PA9<Input<Floating>>,
+    pub d9: PC7<Input<Floating>>,
+    pub d10: PB6<Input<Floating>>,
+    pub d11: PA7<Input<Floating>>,
+    pub d12: PA6<Input<Floating>>,
+    pub d14: PB9<Input<Floating>>,
+    pub d15: PB8<Input<Floating>>,
+    pub a0: PA0<Input<Floating>>,
+    pub a1: PA1<Input<Floating>>,
+    pub a2: PA4<Input<Floating>>,
+    pub a3: PB0<Input<Floating>>,
+    pub a4: PC1<Input<Floating>>,
+    pub a5: PC0<Input<Floating>>,
+}
+
+/// Declares [SparePeripherals] and `split`, which sorts the device peripherals into the ones the
+/// board sets up and the spares. Every peripheral a chip's PAC has needs to be listed, or `split`
+/// won't compile for that chip.
+macro_rules! spare_peripherals {
+    ($($(#[$attr:meta])* $name:ident,)+) => {
+        /// Device peripherals the board doesn't use, passed through untouched for the application.
+        /// Which ones there are depends on the chip.
+        #[allow(non_snake_case)]
+        pub struct SparePeripherals {
+            $($(#[$attr])* pub $name: stm32::$name,)+
+        }
+
+        #[allow(non_snake_case)]
+        fn split(dp: stm32::Peripherals) -> (BoardPeripherals, SparePeripherals) {
+            let stm32::Peripherals {
+                RCC,
+                GPIOA,
+                GPIOB,
+                GPIOC,
+                USART2,
+                $($(#[$attr])* $name,)+
+            } = dp;
+            let board = BoardPeripherals {
+                RCC,
+                GPIOA,
+                GPIOB,
+                GPIOC,
+                USART2,
+            };
+            (board, SparePeripherals { $($(#[$attr])* $name,)+ })
+        }
+    };
+}
+
+spare_peripherals! {
+    ADC1,
+    ADC_COMMON,
+    CRC,
+    DBGMCU,
+    DMA1,
+    DMA2,
+    EXTI,
+    FLASH,
+    FPU,
+    FPU_CPACR,
+    GPIOD,
+    GPIOE,
+    GPIOH,
+    I2C1,
+    I2C2,
+    I2C3,
+    IWDG,
+    NVIC_STIR,
+    OTG_FS_DEVICE,
+    OTG_FS_GLOBAL,
+    OTG_FS_HOST,
+    OTG_FS_PWRCLK,
+    PWR,
+    RTC,
+    SCB_ACTRL,
+    SPI1,
+    SPI2,
+    SPI3,
+    SPI4,
+    STK,
+    SYSCFG,
+    TIM1,
+    TIM10,
+    TIM11,
+    TIM2,
+    TIM3,
+    TIM4,
+    TIM5,
+    TIM8,
+    TIM9,
+    USART1,
+    USART6,
+    WWDG,
+    #[cfg(any(feature = "stm32f405", feature = "stm32f407", feature = "stm32f446"))]
+    ADC2,
+    #[cfg(any(feature = "stm32f405", feature = "stm32f407", feature = "stm32f446"))]
+    ADC3,
+    #[cfg(any(feature = "stm32f405", feature = "stm32f407", feature = "stm32f446"))]
+    DAC,
+    #[cfg(any(feature = "stm32f405", feature = "stm32f407", feature = "stm32f446"))]
+    DCMI,
+    #[cfg(any(feature = "stm32f405", feature = "stm32f407", feature = "stm32f446"))]
+    OTG_HS_DEVICE,
+    #[cfg(any(feature = "stm32f405", feature = "stm32f407", feature = "stm32f446"))]
+    OTG_HS_GLOBAL,
+    #[cfg(any(feature = "stm32f405", feature = "stm32f407", feature = "stm32f446"))]
+    OTG_HS_HOST,
+    #[cfg(any(feature = "stm32f405", feature = "stm32f407", feature = "stm32f446"))]
+    OTG_HS_PWRCLK,
+    #[cfg(any(feature = "stm32f405", feature = "stm32f407", feature = "stm32f446"))]
+    SAI1,
+    #[cfg(any(feature = "stm32f405", feature = "stm32f407", feature = "stm32f446"))]
+    UART4,
+    #[cfg(any(feature = "stm32f405", feature = "stm32f407", feature = "stm32f446"))]
+    UART5,
+    #[cfg(any(feature = "stm32f405", feature = "stm32f407", feature = "stm32f446"))]
+    CAN1,
+    #[cfg(any(feature = "stm32f405", feature = "stm32f407", feature = "stm32f446"))]
+    CAN2,
+    #[cfg(any(feature = "stm32f405", feature = "stm32f407", feature = "stm32f446"))]
+    GPIOF,
+    #[cfg(any(feature = "stm32f405", feature = "stm32f407", feature = "stm32f446"))]
+    GPIOG,
+    #[cfg(any(feature = "stm32f405", feature = "stm32f407", feature = "stm32f446"))]
+    TIM12,
+    #[cfg(any(feature = "stm32f405", feature = "stm32f407", feature = "stm32f446"))]
+    TIM13,
+    #[cfg(any(feature = "stm32f405", feature = "stm32f407", feature = "stm32f446"))]
+    TIM14,
+    #[cfg(any(feature = "stm32f405", feature = "stm32f407", feature = "stm32f446"))]
+    TIM6,
+    #[cfg(any(feature = "stm32f405", feature = "stm32f407", feature = "stm32f446"))]
+    TIM7,
+    #[cfg(any(feature = "stm32f405", feature = "stm32f407", feature = "stm32f446"))]
+    USART3,
+    #[cfg(any(feature = "stm32f405", feature = "stm32f407"))]
+    CRYP,
+    #[cfg(any(feature = "stm32f405", feature = "stm32f407"))]
+    ETHERNET_DMA,
+    #[cfg(any(feature = "stm32f405", feature = "stm32f407"))]
+    ETHERNET_MAC,
+    #[cfg(any(feature = "stm32f405", feature = "stm32f407"))]
+    ETHERNET_MMC,
+    #[cfg(any(feature = "stm32f405", feature = "stm32f407"))]
+    ETHERNET_PTP,
+    #[cfg(any(feature = "stm32f405", feature = "stm32f407"))]
+    GPIOI,
+    #[cfg(any(feature = "stm32f405", feature = "stm32f407"))]
+    GPIOJ,
+    #[cfg(any(feature = "stm32f405", feature = "stm32f407"))]
+    GPIOK,
+    #[cfg(any(feature = "stm32f405", feature = "stm32f407"))]
+    HASH,
+    #[cfg(any(feature = "stm32f405", feature = "stm32f407"))]
+    LTDC,
+    #[cfg(any(feature = "stm32f405", feature = "stm32f407"))]
+    SPI6,
+    #[cfg(any(feature = "stm32f405", feature = "stm32f407"))]
+    UART7,
+    #[cfg(any(feature = "stm32f405", feature = "stm32f407"))]
+    UART8,
+    #[cfg(feature = "stm32f446")]
+    FMC,
+    #[cfg(feature = "stm32f446")]
+    HDMI_CEC,
+    #[cfg(feature = "stm32f446")]
+    SAI2,
+    #[cfg(feature = "stm32f446")]
+    SDMMC,
+    #[cfg(feature = "stm32f446")]
+    SPDIFRX,
+    #[cfg(any(feature = "stm32f405", feature = "stm32f407"))]
+    FSMC,
+    #[cfg(any(feature = "stm32f405", feature = "stm32f407"))]
+    RNG,
+    #[cfg(not(feature = "stm32f446"))]
+    I2S2EXT,
+    #[cfg(not(feature = "stm32f446"))]
+    I2S3EXT,
+    #[cfg(not(feature = "stm32f446"))]
+    SDIO,
+    #[cfg(feature = "stm32f446")]
+    QUADSPI,
+    #[cfg(any(feature = "stm32f405", feature = "stm32f407", feature = "stm32f411"))]
+    SPI5,
+}
+
+/// What [Board::new] sets up
+#[allow(non_snake_case)]
+struct BoardPeripherals {
+    RCC: stm32::RCC,
+    GPIOA: stm32::GPIOA,
+    GPIOB: stm32::GPIOB,
+    GPIOC: stm32::GPIOC,
+    USART2: stm32::USART2,
+}
+
+pub struct Board {
+    pub led: Led,
+    pub button: Button,
+    /// Virtual COM port. RXNE interrupts are enabled, but USART2 still has to be unmasked in the
+    /// NVIC for [crate::uart_driver::interrupt] to get called.
+    pub serial: UartContext,
+    pub delay: Delay,
+    pub itm: ITM,
+    pub pins: ArduinoPins,
+    pub clocks: Clocks,
+    pub spare: SparePeripherals,
+}
+
+impl Board {
+    /// Set up the clocks and on-board hardware. SysTick and ITM are the only core peripherals the
+    /// board needs, so the rest stay with the caller.
+    pub fn new(dp: stm32::Peripherals, syst: SYST, itm: ITM, clock: ClockPreset) -> Self {
+        let (dp, spare) = split(dp);
+        let rcc = dp.RCC.constrain();
+        let clocks = rcc.cfgr.sysclk(clock.sysclk()).freeze();
+
+        // Calling split also powers up the GPIO peripheral clocks
+        let gpioa = dp.GPIOA.split();
+        let gpiob = dp.GPIOB.split();
+        let gpioc = dp.GPIOC.split();
+
+        let tx = gpioa.pa2.into_alternate_af7();
+        let rx = gpioa.pa3.into_alternate_af7();
+        let mut serial = Serial::usart2(
+            dp.USART2,
+            (tx, rx),
+            Config::default().baudrate(115200.bps()),
+            clocks,
+        )
+        .unwrap();
+        serial.listen(hal::serial::Event::Rxne);
+
+        Self {
+            led: gpioa.pa5.into_push_pull_output(),
+            button: gpioc.pc13.into_floating_input(),
+            serial: UartContext::new(serial),
+            delay: Delay::new(syst, clocks),
+            itm,
+            pins: ArduinoPins {
+                d2: gpioa.pa10,
+                d4: gpiob.pb5,
+                d5: gpiob.pb4,
+                d6: gpiob.pb10,
+                d7: gpioa.pa8,
+                d8: gpioa.pa9,
+                d9: gpioc.pc7,
+                d10: gpiob.pb6,
+                d11: gpioa.pa7,
+                d12: gpioa.pa6,
+                d14: gpiob.pb9,
+                d15: gpiob.pb8,
+                a0: gpioa.pa0,
+                a1: gpioa.pa1,
+                a2: gpioa.pa4,
+                a3: gpiob.pb0,
+                a4: gpioc.pc1,
+                a5: gpioc.pc0,
+            },
+            clocks,
+            spare,
+        }
+    }
+}
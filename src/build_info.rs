@@ -0,0 +1,86 @@
+//! Metadata about the build, captured by `build.rs` and embedded in the firmware image.
+//!
+//! [BUILD_INFO] sits at a fixed offset in flash (0x200, right after the vector table), so a debugger
+//! or flashing tool can identify the firmware on a board without the ELF file. Look for [MAGIC].
+use core::fmt;
+
+/// First word of the build info in flash
+pub const MAGIC: u32 = 0xB01D_1AF0;
+
+/// Strings are NUL-padded ASCII so the layout is fixed and easy to parse from the outside
+#[repr(C)]
+pub struct BuildInfo {
+    magic: u32,
+    dirty: u32,
+    /// Unix timestamp of the build
+    pub timestamp: u64,
+    git_hash: [u8; 40],
+    build_time: [u8; 20],
+    version: [u8; 16],
+    profile: [u8; 16],
+    features: [u8; 96],
+}
+
+#[link_section = ".build_info"]
+#[used]
+pub static BUILD_INFO: BuildInfo = include!(concat!(env!("OUT_DIR"), "/build_info.rs"));
+
+fn padded_str(bytes: &[u8]) -> &str {
+    let len = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    core::str::from_utf8(&bytes[..len]).unwrap_or("?")
+}
+
+impl BuildInfo {
+    /// Full hash of the commit that was built, or "unknown" outside of a git checkout
+    pub fn git_hash(&self) -> &str {
+        padded_str(&self.git_hash)
+    }
+
+    /// Whether the working tree had uncommitted changes
+    pub fn is_dirty(&self) -> bool {
+        self.dirty != 0
+    }
+
+    /// Build time in ISO 8601 format, UTC
+    pub fn build_time(&self) -> &str {
+        padded_str(&self.build_time)
+    }
+
+    /// Crate version
+    pub fn version(&self) -> &str {
+        padded_str(&self.version)
+    }
+
+    /// Cargo profile, e.g. "debug" or "release"
+    pub fn profile(&self) -> &str {
+        padded_str(&self.profile)
+    }
+
+    /// Enabled cargo features, comma-separated
+    pub fn features(&self) -> &str {
+        padded_str(&self.features)
+    }
+}
+
+/// e.g. `v0.1.0 2174cbe7-dirty (release) built 2021-01-04T13:37:00Z [default,stm32f401]`
+impl fmt::Display for BuildInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let git_hash = self.git_hash();
+        write!(
+            f,
+            "v{} {}",
+            self.version(),
+            &git_hash[..git_hash.len().min(8)]
+        )?;
+        if self.is_dirty() {
+            f.write_str("-dirty")?;
+        }
+        write!(
+            f,
+            " ({}) built {} [{}]",
+            self.profile(),
+            self.build_time(),
+            self.features()
+        )
+    }
+}
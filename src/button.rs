@@ -0,0 +1,255 @@
+//! Debounced user button (B1) with click, double-click and long press events.
+//!
+//! A [Debouncer] turns raw button readings with timestamps into [ButtonEvent]s, whoever takes the
+//! readings.
+//!
+//! The driver around it wakes up on an [exti] edge interrupt whenever PC13 changes and then samples
+//! the button every [TICK_MS] on TIM4 until things settle down, so nothing runs while the button is
+//! left alone. Set up [exti] and call [start], unmask EXTI15_10 and TIM4 in the NVIC and call
+//! [exti::interrupt] and [timer_interrupt] from their handlers. Then pick up events with
+//! [next_event].
+use crate::{
+    board::Button,
+    exti::{self, InputEvent, Port, Trigger},
+};
+use core::cell::RefCell;
+use cortex_m::interrupt::Mutex;
+use heapless::{consts::U8, spsc::Queue};
+use stm32f4xx_hal::{
+    prelude::*,
+    rcc::Clocks,
+    stm32::TIM4,
+    timer::{Event, Timer},
+};
+
+/// How often the button is sampled while something is going on
+pub const TICK_MS: u32 = 5;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ButtonEvent {
+    Press,
+    Release,
+    /// Pressed and released once, and not pressed again soon enough for a double-click
+    Click,
+    DoubleClick,
+    /// Held for [Timings::long_press_ms]. Reported while still held, and instead of a click.
+    LongPress,
+}
+
+/// All in milliseconds
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Timings {
+    /// How long the button has to stay put before a change counts
+    pub debounce_ms: u32,
+    /// Longest gap between releasing the button and pressing it again for a double-click. 0 turns
+    /// double-clicks off, which makes clicks show up without waiting.
+    pub double_click_ms: u32,
+    pub long_press_ms: u32,
+}
+
+impl Default for Timings {
+    fn default() -> Self {
+        Self {
+            debounce_ms: 20,
+            double_click_ms: 250,
+            long_press_ms: 600,
+        }
+    }
+}
+
+/// Debouncing and click detection. Feed it raw button readings with [Debouncer::update] whenever
+/// they change and every so often while [Debouncer::is_idle] says it's waiting for something.
+pub struct Debouncer {
+    pub timings: Timings,
+    /// Last reading and when it changed to that
+    raw: bool,
+    raw_since: u32,
+    /// Debounced state
+    pressed: bool,
+    pressed_at: u32,
+    long_press_reported: bool,
+    /// When the button was released after a click that could still become a double-click
+    pending_click: Option<u32>,
+    /// The current press is the second half of a double-click
+    second_press: bool,
+    events: Queue<ButtonEvent, U8>,
+}
+
+impl Debouncer {
+    /// Starts out released
+    pub fn new(timings: Timings) -> Self {
+        Self {
+            timings,
+            raw: false,
+            raw_since: 0,
+            pressed: false,
+            pressed_at: 0,
+            long_press_reported: false,
+            pending_click: None,
+            second_press: false,
+            events: Queue::new(),
+        }
+    }
+
+    /// `now_ms` is any free-running millisecond count. Wrapping around is fine.
+    pub fn update(&mut self, pressed: bool, now_ms: u32) {
+        if pressed != self.raw {
+            self.raw = pressed;
+            self.raw_since = now_ms;
+        }
+        if self.raw != self.pressed
+            && now_ms.wrapping_sub(self.raw_since) >= self.timings.debounce_ms
+        {
+            // Timings count from when the button actually moved, not from when it settled
+            let at = self.raw_since;
+            self.pressed = self.raw;
+            if self.pressed {
+                self.on_press(at);
+            } else {
+                self.on_release(at);
+            }
+        }
+
+        if self.pressed
+            && !self.long_press_reported
+            && now_ms.wrapping_sub(self.pressed_at) >= self.timings.long_press_ms
+        {
+            self.long_press_reported = true;
+            if self.second_press {
+                // The first press was a click after all
+                self.second_press = false;
+                self.push(ButtonEvent::Click);
+            }
+            self.push(ButtonEvent::LongPress);
+        }
+
+        if let Some(released_at) = self.pending_click {
+            if now_ms.wrapping_sub(released_at) >= self.timings.double_click_ms {
+                self.pending_click = None;
+                self.push(ButtonEvent::Click);
+            }
+        }
+    }
+
+    fn on_press(&mut self, at: u32) {
+        if let Some(released_at) = self.pending_click.take() {
+            if at.wrapping_sub(released_at) < self.timings.double_click_ms {
+                self.second_press = true;
+            } else {
+                // Nobody called update in time to report it
+                self.push(ButtonEvent::Click);
+            }
+        }
+        self.pressed_at = at;
+        self.long_press_reported = false;
+        self.push(ButtonEvent::Press);
+    }
+
+    fn on_release(&mut self, at: u32) {
+        self.push(ButtonEvent::Release);
+        if self.long_press_reported {
+            return;
+        }
+        if self.second_press {
+            self.second_press = false;
+            self.push(ButtonEvent::DoubleClick);
+        } else if self.timings.double_click_ms == 0 {
+            self.push(ButtonEvent::Click);
+        } else {
+            self.pending_click = Some(at);
+        }
+    }
+
+    /// Events that don't fit in the queue are dropped
+    fn push(&mut self, event: ButtonEvent) {
+        self.events.enqueue(event).ok();
+    }
+
+    /// Debounced state
+    pub fn is_pressed(&self) -> bool {
+        self.pressed
+    }
+
+    /// Nothing more will happen until the button moves, so there's no need to keep calling
+    /// [Debouncer::update]
+    pub fn is_idle(&self) -> bool {
+        self.raw == self.pressed
+            && self.pending_click.is_none()
+            && (!self.pressed || self.long_press_reported)
+    }
+
+    /// Oldest event that hasn't been picked up yet
+    pub fn next_event(&mut self) -> Option<ButtonEvent> {
+        self.events.dequeue()
+    }
+}
+
+struct Driver {
+    pin: Button,
+    timer: Timer<TIM4>,
+    debouncer: Debouncer,
+    /// Only advances while the timer is running, which is all the debouncer needs
+    now_ms: u32,
+}
+
+impl Driver {
+    fn poll(&mut self) {
+        // The button is active low
+        let pressed = self.pin.is_low().unwrap();
+        self.debouncer.update(pressed, self.now_ms);
+        if self.debouncer.is_idle() {
+            self.timer.unlisten(Event::TimeOut);
+        } else {
+            self.timer.listen(Event::TimeOut);
+        }
+    }
+}
+
+static DRIVER: Mutex<RefCell<Option<Driver>>> = Mutex::new(RefCell::new(None));
+
+/// Start watching the button. Fails if something else already has its EXTI line. Unmask
+/// EXTI15_10 and TIM4 in the NVIC afterwards.
+pub fn start(pin: Button, tim: TIM4, clocks: Clocks, timings: Timings) -> Result<(), exti::Error> {
+    exti::listen(Port::C, 13, Trigger::Both, on_edge)?;
+    let timer = Timer::tim4(tim, (1000 / TICK_MS).hz(), clocks);
+    let mut driver = Driver {
+        pin,
+        timer,
+        debouncer: Debouncer::new(timings),
+        now_ms: 0,
+    };
+    // In case the button is already held down
+    driver.poll();
+    cortex_m::interrupt::free(|cs| DRIVER.borrow(cs).replace(Some(driver)));
+    Ok(())
+}
+
+/// Oldest event that hasn't been picked up yet. Up to 8 are kept.
+pub fn next_event() -> Option<ButtonEvent> {
+    cortex_m::interrupt::free(|cs| {
+        DRIVER
+            .borrow(cs)
+            .borrow_mut()
+            .as_mut()
+            .and_then(|driver| driver.debouncer.next_event())
+    })
+}
+
+fn on_edge(_: InputEvent) {
+    cortex_m::interrupt::free(|cs| {
+        if let Some(driver) = DRIVER.borrow(cs).borrow_mut().as_mut() {
+            driver.poll();
+        }
+    });
+}
+
+/// Call this from the TIM4 interrupt handler
+pub fn timer_interrupt() {
+    cortex_m::interrupt::free(|cs| {
+        if let Some(driver) = DRIVER.borrow(cs).borrow_mut().as_mut() {
+            driver.timer.clear_interrupt(Event::TimeOut);
+            driver.now_ms = driver.now_ms.wrapping_add(TICK_MS);
+            driver.poll();
+        }
+    });
+}
@@ -0,0 +1,256 @@
+//! Uptime in microseconds, good for half a million years.
+//!
+//! TIM5 counts microseconds in 32 bits, which wrap around roughly every 71 minutes. The TIM5
+//! interrupt extends that to 64 bits by bumping a count of half-periods twice per wrap: once when
+//! the counter passes halfway and once when it wraps around. The count's parity says which half
+//! the counter was in, so a reading is right even if the counter wrapped after the count was read
+//! or the interrupt hasn't been handled yet. No critical section needed, so [Instant::now] works
+//! from any priority, as long as the TIM5 interrupt gets to run once in a while.
+//!
+//! Call [start], unmask TIM5 in the NVIC and call [interrupt] from its handler. [Clock] is also an
+//! RTIC monotonic timer, for scheduling tasks:
+//!
+//! ```ignore
+//! #[app(device = stm32f4xx_hal::stm32, monotonic = sandbox_stm32f4_rust::clock::Clock)]
+//! const APP: () = {
+//!     #[init(schedule = [blink])]
+//!     fn init(cx: init::Context) {
+//!         clock::start(cx.device.TIM5, clocks);
+//!         cx.schedule.blink(cx.start + Duration::from_millis(500)).unwrap();
+//!     }
+//!
+//!     #[task(binds = TIM5)]
+//!     fn tim5(_: tim5::Context) {
+//!         clock::interrupt();
+//!     }
+//! }
+//! ```
+use crate::board::FreeRunningTimer;
+use core::{
+    convert::TryFrom,
+    num::TryFromIntError,
+    ops::{Add, AddAssign, Sub, SubAssign},
+    sync::atomic::{compiler_fence, AtomicU32, Ordering},
+};
+use rtic::{Fraction, Monotonic};
+use stm32f4xx_hal::{rcc::Clocks, stm32::TIM5};
+
+/// How fast TIM5 counts
+pub const TICK_HZ: u32 = 1_000_000;
+
+/// Half-periods of the counter since [start]
+static PERIODS: AtomicU32 = AtomicU32::new(0);
+/// For [Clock::ratio]
+static SYSCLK_HZ: AtomicU32 = AtomicU32::new(0);
+
+/// Microseconds since [start], given the count of half-periods and the counter. The counter can
+/// be up to one half-period ahead of the count.
+pub fn extend(periods: u32, counter: u32) -> u64 {
+    (u64::from(periods) << 31) + u64::from(counter ^ ((periods & 1) << 31))
+}
+
+/// A point in time, in microseconds since [start]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Instant(u64);
+
+impl Instant {
+    pub const fn from_micros(micros: u64) -> Self {
+        Self(micros)
+    }
+
+    /// 0 before [start]
+    pub fn now() -> Self {
+        let tim = unsafe { &*TIM5::ptr() };
+        let periods = PERIODS.load(Ordering::Acquire);
+        // The count has to be read first
+        compiler_fence(Ordering::SeqCst);
+        let counter = tim.cnt.read().bits();
+        Self(extend(periods, counter))
+    }
+
+    pub fn as_micros(self) -> u64 {
+        self.0
+    }
+
+    /// How long since `earlier`, or 0 if it's actually later
+    pub fn duration_since(self, earlier: Instant) -> Duration {
+        Duration(self.0.saturating_sub(earlier.0))
+    }
+
+    pub fn elapsed(self) -> Duration {
+        Instant::now().duration_since(self)
+    }
+}
+
+/// A span of time in microseconds
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Duration(u64);
+
+impl Duration {
+    pub const fn from_micros(micros: u64) -> Self {
+        Self(micros)
+    }
+
+    pub const fn from_millis(millis: u64) -> Self {
+        Self(millis * 1000)
+    }
+
+    pub const fn from_secs(secs: u64) -> Self {
+        Self(secs * 1_000_000)
+    }
+
+    pub fn as_micros(self) -> u64 {
+        self.0
+    }
+
+    pub fn as_millis(self) -> u64 {
+        self.0 / 1000
+    }
+
+    pub fn as_secs(self) -> u64 {
+        self.0 / 1_000_000
+    }
+}
+
+impl Add<Duration> for Instant {
+    type Output = Instant;
+
+    fn add(self, duration: Duration) -> Instant {
+        Instant(self.0 + duration.0)
+    }
+}
+
+impl AddAssign<Duration> for Instant {
+    fn add_assign(&mut self, duration: Duration) {
+        self.0 += duration.0;
+    }
+}
+
+impl Sub<Duration> for Instant {
+    type Output = Instant;
+
+    fn sub(self, duration: Duration) -> Instant {
+        Instant(self.0 - duration.0)
+    }
+}
+
+impl SubAssign<Duration> for Instant {
+    fn sub_assign(&mut self, duration: Duration) {
+        self.0 -= duration.0;
+    }
+}
+
+impl Sub<Instant> for Instant {
+    type Output = Duration;
+
+    /// Saturates at 0, like [Instant::duration_since]
+    fn sub(self, earlier: Instant) -> Duration {
+        self.duration_since(earlier)
+    }
+}
+
+impl Add for Duration {
+    type Output = Duration;
+
+    fn add(self, other: Duration) -> Duration {
+        Duration(self.0 + other.0)
+    }
+}
+
+impl AddAssign for Duration {
+    fn add_assign(&mut self, other: Duration) {
+        self.0 += other.0;
+    }
+}
+
+impl Sub for Duration {
+    type Output = Duration;
+
+    fn sub(self, other: Duration) -> Duration {
+        Duration(self.0 - other.0)
+    }
+}
+
+impl SubAssign for Duration {
+    fn sub_assign(&mut self, other: Duration) {
+        self.0 -= other.0;
+    }
+}
+
+/// In microseconds, which RTIC needs for its timer queue
+impl TryFrom<Duration> for u32 {
+    type Error = TryFromIntError;
+
+    fn try_from(duration: Duration) -> Result<u32, TryFromIntError> {
+        u32::try_from(duration.0)
+    }
+}
+
+/// TIM5 as an RTIC monotonic timer
+pub struct Clock;
+
+impl Monotonic for Clock {
+    type Instant = Instant;
+
+    /// SysTick runs at the system clock
+    fn ratio() -> Fraction {
+        let sysclk_hz = SYSCLK_HZ.load(Ordering::Relaxed);
+        let divisor = gcd(sysclk_hz, TICK_HZ).max(1);
+        Fraction {
+            numerator: sysclk_hz / divisor,
+            denominator: TICK_HZ / divisor,
+        }
+    }
+
+    fn now() -> Instant {
+        Instant::now()
+    }
+
+    /// Only for RTIC to call, right after `#[init]`, which needs to have called [start]
+    unsafe fn reset() {
+        let tim = &*TIM5::ptr();
+        tim.cnt.write(|w| w.bits(0));
+        tim.sr.write(|w| w.bits(0));
+        PERIODS.store(0, Ordering::Release);
+    }
+
+    fn zero() -> Instant {
+        Instant(0)
+    }
+}
+
+fn gcd(mut a: u32, mut b: u32) -> u32 {
+    while b != 0 {
+        let r = a % b;
+        a = b;
+        b = r;
+    }
+    a
+}
+
+/// Take over TIM5 and start counting from 0. Unmask TIM5 in the NVIC afterwards.
+pub fn start(tim: TIM5, clocks: Clocks) {
+    SYSCLK_HZ.store(clocks.sysclk().0, Ordering::Relaxed);
+    tim.setup_free_running(clocks, TICK_HZ);
+    // Halfway. Any 32-bit value is a valid compare value.
+    tim.ccr1.write(|w| unsafe { w.bits(1 << 31) });
+    PERIODS.store(0, Ordering::Release);
+    tim.dier.write(|w| w.uie().set_bit().cc1ie().set_bit());
+    tim.cr1.modify(|_, w| w.cen().set_bit());
+}
+
+/// Call this from the TIM5 interrupt handler at least once every half hour or so, which is every
+/// time it goes off unless it's kept waiting
+pub fn interrupt() {
+    let tim = unsafe { &*TIM5::ptr() };
+    let sr = tim.sr.read();
+    // Only the bits written as 0 get cleared
+    if sr.uif().bit_is_set() {
+        tim.sr.write(|w| unsafe { w.bits(!0).uif().clear_bit() });
+        PERIODS.fetch_add(1, Ordering::Release);
+    }
+    if sr.cc1if().bit_is_set() {
+        tim.sr.write(|w| unsafe { w.bits(!0).cc1if().clear_bit() });
+        PERIODS.fetch_add(1, Ordering::Release);
+    }
+}
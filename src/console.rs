@@ -0,0 +1,213 @@
+//! Scrolling text console on the SH1106 OLED, for mirroring log output or serial data on screen.
+//!
+//! Text goes on a 21x8 character grid in the 6x8 font, which fills the 128x64 display. Writing past
+//! the last line scrolls everything up. Besides printable ASCII, the console understands:
+//! * `\n`: go to the start of the next line
+//! * `\r`: go to the start of the current line
+//! * Backspace: move back one character
+//! * `ESC[2J`: clear the screen. The cursor stays put, as in a real terminal.
+//! * `ESC[K`: clear the rest of the current line
+//! * `ESC[H` or `ESC[<row>;<col>H`: move the cursor. Rows and columns start at 1.
+//!
+//! Other escape sequences are swallowed. Anything outside of ASCII shows up as `?`.
+//!
+//! The console implements [core::fmt::Write], and each `write_str` call redraws and flushes the
+//! display. Only the parts of the screen that changed get sent, but batch up output with `write!`
+//! rather than writing a character at a time if speed matters.
+use crate::framebuffer::Framebuffer;
+use core::fmt;
+use embedded_graphics::{
+    fonts::{Font6x8, Text},
+    pixelcolor::BinaryColor,
+    prelude::*,
+    style::TextStyle,
+};
+use sh1106::interface::DisplayInterface;
+
+pub const COLUMNS: usize = 21;
+pub const ROWS: usize = 8;
+const CHAR_HEIGHT: i32 = 8;
+
+const ESC: u8 = 0x1b;
+const BACKSPACE: u8 = 0x08;
+
+/// Where we are in parsing an escape sequence
+#[derive(Clone, Copy)]
+enum Escape {
+    None,
+    /// Got ESC
+    Start,
+    /// Got `ESC[` plus the parameters so far
+    Csi {
+        params: [u16; 2],
+        count: usize,
+    },
+}
+
+pub struct Console<DI: DisplayInterface> {
+    disp: Framebuffer<DI>,
+    text: [[u8; COLUMNS]; ROWS],
+    row: usize,
+    column: usize,
+    escape: Escape,
+}
+
+impl<DI: DisplayInterface> Console<DI> {
+    /// Take over an initialized display. The text starts out blank, but the screen isn't touched
+    /// until the first flush.
+    pub fn new(disp: Framebuffer<DI>) -> Self {
+        Self {
+            disp,
+            text: [[b' '; COLUMNS]; ROWS],
+            row: 0,
+            column: 0,
+            escape: Escape::None,
+        }
+    }
+
+    /// Give back the display
+    pub fn release(self) -> Framebuffer<DI> {
+        self.disp
+    }
+
+    /// Cursor position as (row, column), starting from 0
+    pub fn cursor(&self) -> (usize, usize) {
+        (self.row, self.column)
+    }
+
+    /// Text on row `row`, starting from 0, padded out to [COLUMNS] with spaces
+    pub fn line(&self, row: usize) -> &str {
+        // Only ASCII ever makes it into the buffer
+        core::str::from_utf8(&self.text[row]).unwrap_or("")
+    }
+
+    /// Clear the screen and move the cursor to the top left
+    pub fn clear(&mut self) -> Result<(), DI::Error> {
+        self.clear_text();
+        self.row = 0;
+        self.column = 0;
+        self.flush()
+    }
+
+    /// Update the text without touching the display. Call [Console::flush] afterwards.
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.write_byte(byte);
+        }
+    }
+
+    /// Redraw the display from the text buffer
+    pub fn flush(&mut self) -> Result<(), DI::Error> {
+        self.disp.clear();
+        let style = TextStyle::new(Font6x8, BinaryColor::On);
+        for (row, line) in self.text.iter().enumerate() {
+            // Only ASCII ever makes it into the buffer
+            let line = core::str::from_utf8(line).unwrap_or("");
+            Text::new(line, Point::new(0, row as i32 * CHAR_HEIGHT))
+                .into_styled(style)
+                .draw(&mut self.disp)
+                .ok();
+        }
+        self.disp.flush()
+    }
+
+    fn write_byte(&mut self, byte: u8) {
+        match self.escape {
+            Escape::None => self.write_plain(byte),
+            Escape::Start => {
+                self.escape = if byte == b'[' {
+                    Escape::Csi {
+                        params: [0; 2],
+                        count: 0,
+                    }
+                } else {
+                    Escape::None
+                }
+            }
+            Escape::Csi {
+                mut params,
+                mut count,
+            } => match byte {
+                b'0'..=b'9' => {
+                    let index = count.min(params.len() - 1);
+                    params[index] = params[index]
+                        .saturating_mul(10)
+                        .saturating_add(u16::from(byte - b'0'));
+                    count = count.max(1);
+                    self.escape = Escape::Csi { params, count };
+                }
+                b';' => {
+                    self.escape = Escape::Csi {
+                        params,
+                        count: count.max(1) + 1,
+                    };
+                }
+                // Final byte of the sequence
+                0x40..=0x7e => {
+                    self.escape = Escape::None;
+                    self.run_csi(byte, params);
+                }
+                // Intermediate bytes and private parameter markers
+                _ => {}
+            },
+        }
+    }
+
+    fn write_plain(&mut self, byte: u8) {
+        match byte {
+            b'\n' => self.new_line(),
+            b'\r' => self.column = 0,
+            BACKSPACE => self.column = self.column.saturating_sub(1),
+            ESC => self.escape = Escape::Start,
+            // Other control characters, and UTF-8 continuation bytes so that each non-ASCII
+            // character only takes up one spot
+            0..=0x1f | 0x7f..=0xbf => {}
+            _ => {
+                if self.column == COLUMNS {
+                    self.new_line();
+                }
+                self.text[self.row][self.column] = if byte.is_ascii() { byte } else { b'?' };
+                self.column += 1;
+            }
+        }
+    }
+
+    fn run_csi(&mut self, command: u8, params: [u16; 2]) {
+        match command {
+            b'J' if params[0] == 2 => self.clear_text(),
+            b'K' => {
+                if let Some(rest) = self.text[self.row].get_mut(self.column..) {
+                    rest.fill(b' ');
+                }
+            }
+            b'H' | b'f' => {
+                // 0 and missing parameters both mean 1
+                let to_index = |param: u16, len: usize| (param.max(1) as usize - 1).min(len - 1);
+                self.row = to_index(params[0], ROWS);
+                self.column = to_index(params[1], COLUMNS);
+            }
+            _ => {}
+        }
+    }
+
+    fn new_line(&mut self) {
+        self.column = 0;
+        if self.row + 1 < ROWS {
+            self.row += 1;
+        } else {
+            self.text.copy_within(1.., 0);
+            self.text[ROWS - 1] = [b' '; COLUMNS];
+        }
+    }
+
+    fn clear_text(&mut self) {
+        self.text = [[b' '; COLUMNS]; ROWS];
+    }
+}
+
+impl<DI: DisplayInterface> fmt::Write for Console<DI> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.write_bytes(s.as_bytes());
+        self.flush().map_err(|_| fmt::Error)
+    }
+}
@@ -0,0 +1,258 @@
+//! Crash record that survives a software reset.
+//!
+//! The record lives in the `.uninit` section, which cortex-m-rt doesn't zero or initialize at
+//! startup. Whatever the panic or HardFault handler wrote right before resetting the chip is still
+//! there on the next boot, where the application can report it via [last_crash] and then
+//! [clear] it. A power cycle wipes it, of course.
+//!
+//! The application has to route its handlers here, e.g.
+//!
+//! ```ignore
+//! #[panic_handler]
+//! fn panic(info: &core::panic::PanicInfo) -> ! {
+//!     crash_dump::record_panic(info)
+//! }
+//!
+//! #[exception]
+//! fn HardFault(ef: &cortex_m_rt::ExceptionFrame) -> ! {
+//!     crash_dump::record_hard_fault(ef)
+//! }
+//! ```
+use core::{
+    fmt::{self, Write},
+    mem::MaybeUninit,
+    panic::PanicInfo,
+    ptr,
+    sync::atomic::{AtomicU32, Ordering},
+};
+use cortex_m::peripheral::SCB;
+use cortex_m_rt::ExceptionFrame;
+
+/// Marks a valid record. RAM contents are random after a power cycle, so anything else means there
+/// was no crash.
+const MAGIC: u32 = 0xDEAD_C0DE;
+const MESSAGE_CAPACITY: usize = 128;
+
+#[link_section = ".uninit.CRASH_RECORD"]
+static mut CRASH_RECORD: MaybeUninit<CrashRecord> = MaybeUninit::uninit();
+
+static UPTIME_MS: AtomicU32 = AtomicU32::new(0);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CrashKind {
+    Panic,
+    HardFault,
+}
+
+/// Everything we manage to save about a crash. Every field is a plain integer so that any bit
+/// pattern left over in RAM is a valid (if meaningless) value.
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct CrashRecord {
+    magic: u32,
+    kind: u32,
+    /// Program counter. For panics this points into the panic handler, not at the offending code;
+    /// the source location is part of the message instead.
+    pub pc: u32,
+    /// Link register
+    pub lr: u32,
+    /// Last uptime reported via [set_uptime_ms] before the crash
+    pub uptime_ms: u32,
+    /// Stacked exception frame (R0-R3, R12, LR, PC, xPSR). Only filled in for hard faults.
+    pub frame: [u32; 8],
+    /// Configurable fault status register
+    pub cfsr: u32,
+    /// HardFault status register
+    pub hfsr: u32,
+    /// MemManage fault address register
+    pub mmfar: u32,
+    /// BusFault address register
+    pub bfar: u32,
+    message_len: u32,
+    message: [u8; MESSAGE_CAPACITY],
+}
+
+impl CrashRecord {
+    const fn empty(kind: CrashKind) -> Self {
+        Self {
+            magic: MAGIC,
+            kind: kind as u32,
+            pc: 0,
+            lr: 0,
+            uptime_ms: 0,
+            frame: [0; 8],
+            cfsr: 0,
+            hfsr: 0,
+            mmfar: 0,
+            bfar: 0,
+            message_len: 0,
+            message: [0; MESSAGE_CAPACITY],
+        }
+    }
+
+    fn is_valid(&self) -> bool {
+        self.magic == MAGIC
+            && self.message_len as usize <= MESSAGE_CAPACITY
+            && (self.kind == CrashKind::Panic as u32 || self.kind == CrashKind::HardFault as u32)
+    }
+
+    pub fn kind(&self) -> CrashKind {
+        if self.kind == CrashKind::Panic as u32 {
+            CrashKind::Panic
+        } else {
+            CrashKind::HardFault
+        }
+    }
+
+    /// Panic message, possibly truncated. Empty for hard faults.
+    pub fn message(&self) -> &str {
+        let bytes = &self.message[..self.message_len as usize];
+        match core::str::from_utf8(bytes) {
+            Ok(message) => message,
+            // Truncation may have split a multi-byte character
+            Err(e) => core::str::from_utf8(&bytes[..e.valid_up_to()]).unwrap_or(""),
+        }
+    }
+
+    fn save_fault_registers(&mut self) {
+        let scb = unsafe { &*SCB::ptr() };
+        self.cfsr = scb.cfsr.read();
+        self.hfsr = scb.hfsr.read();
+        self.mmfar = scb.mmfar.read();
+        self.bfar = scb.bfar.read();
+    }
+}
+
+impl fmt::Display for CrashRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self.kind() {
+            CrashKind::Panic => "panic",
+            CrashKind::HardFault => "hard fault",
+        };
+        write!(f, "*** Crashed ({}) at {} ms\r\n", kind, self.uptime_ms)?;
+        write!(f, "PC: {:#010x} LR: {:#010x}\r\n", self.pc, self.lr)?;
+        match self.kind() {
+            CrashKind::Panic => write!(f, "{}\r\n", self.message()),
+            CrashKind::HardFault => {
+                let [r0, r1, r2, r3, r12, _, _, xpsr] = self.frame;
+                write!(
+                    f,
+                    "R0: {:#010x} R1: {:#010x} R2: {:#010x} R3: {:#010x}\r\n",
+                    r0, r1, r2, r3
+                )?;
+                write!(f, "R12: {:#010x} xPSR: {:#010x}\r\n", r12, xpsr)?;
+                write!(
+                    f,
+                    "CFSR: {:#010x} HFSR: {:#010x} MMFAR: {:#010x} BFAR: {:#010x}\r\n",
+                    self.cfsr, self.hfsr, self.mmfar, self.bfar
+                )
+            }
+        }
+    }
+}
+
+/// Truncating writer into the message buffer. Never fails so that formatting carries on as far as
+/// it can.
+struct MessageWriter<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl Write for MessageWriter<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let count = s.len().min(self.buf.len() - self.len);
+        self.buf[self.len..self.len + count].copy_from_slice(&s.as_bytes()[..count]);
+        self.len += count;
+        Ok(())
+    }
+}
+
+/// Let the crash handlers know how long we've been up. Call this periodically, e.g. from a timer
+/// interrupt.
+pub fn set_uptime_ms(uptime_ms: u32) {
+    UPTIME_MS.store(uptime_ms, Ordering::Relaxed);
+}
+
+/// Save a panic to the crash record and reset the chip. Call this from the `#[panic_handler]`.
+#[inline(always)]
+pub fn record_panic(info: &PanicInfo) -> ! {
+    cortex_m::interrupt::disable();
+    let (pc, lr) = pc_and_lr();
+
+    let mut record = CrashRecord::empty(CrashKind::Panic);
+    record.pc = pc;
+    record.lr = lr;
+    record.uptime_ms = UPTIME_MS.load(Ordering::Relaxed);
+    record.save_fault_registers();
+    let mut writer = MessageWriter {
+        buf: &mut record.message,
+        len: 0,
+    };
+    write!(writer, "{}", info).ok();
+    record.message_len = writer.len as u32;
+
+    save_and_reset(&record)
+}
+
+/// Save a hard fault to the crash record and reset the chip. Call this from the `HardFault`
+/// exception handler.
+pub fn record_hard_fault(ef: &ExceptionFrame) -> ! {
+    cortex_m::interrupt::disable();
+
+    let mut record = CrashRecord::empty(CrashKind::HardFault);
+    record.pc = ef.pc;
+    record.lr = ef.lr;
+    record.uptime_ms = UPTIME_MS.load(Ordering::Relaxed);
+    record.frame = [ef.r0, ef.r1, ef.r2, ef.r3, ef.r12, ef.lr, ef.pc, ef.xpsr];
+    record.save_fault_registers();
+
+    save_and_reset(&record)
+}
+
+/// The crash saved before the last reset, if there was one
+pub fn last_crash() -> Option<CrashRecord> {
+    // Every field is an integer, so reading whatever happens to be in RAM is fine
+    let record = unsafe { ptr::read_volatile(ptr::addr_of!(CRASH_RECORD)).assume_init() };
+    if record.is_valid() {
+        Some(record)
+    } else {
+        None
+    }
+}
+
+/// Forget the saved crash so that it's only reported once
+pub fn clear() {
+    unsafe {
+        let record = ptr::addr_of_mut!(CRASH_RECORD) as *mut CrashRecord;
+        ptr::write_volatile(ptr::addr_of_mut!((*record).magic), 0);
+    }
+}
+
+fn save_and_reset(record: &CrashRecord) -> ! {
+    unsafe {
+        ptr::write_volatile(ptr::addr_of_mut!(CRASH_RECORD), MaybeUninit::new(*record));
+    }
+    SCB::sys_reset()
+}
+
+#[inline(always)]
+fn pc_and_lr() -> (u32, u32) {
+    #[cfg(target_arch = "arm")]
+    {
+        let (pc, lr): (u32, u32);
+        unsafe {
+            core::arch::asm!(
+                "mov {0}, pc",
+                "mov {1}, lr",
+                out(reg) pc,
+                out(reg) lr,
+                options(nomem, nostack, preserves_flags)
+            );
+        }
+        (pc, lr)
+    }
+    #[cfg(not(target_arch = "arm"))]
+    {
+        (0, 0)
+    }
+}
@@ -0,0 +1,50 @@
+//! Drawings the OLED examples show off. They live here rather than in the examples so the snapshot
+//! tests can check them without hardware. None of them flush; that's up to the caller.
+use crate::assets;
+use embedded_graphics::{
+    image::Image,
+    pixelcolor::BinaryColor,
+    prelude::*,
+    primitives::{Circle, Rectangle, Triangle},
+    style::PrimitiveStyleBuilder,
+};
+
+/// Picture of Kes, filling a 128x64 display
+pub fn draw_kes<D: DrawTarget<BinaryColor>>(target: &mut D) -> Result<(), D::Error> {
+    let im = assets::KES.image();
+    Image::new(&im, Point::new(0, 0)).draw(target)
+}
+
+/// The embedded_graphics equivalent of hello world: a triangle, square and circle in a frame
+pub fn draw_shapes<D: DrawTarget<BinaryColor>>(target: &mut D) -> Result<(), D::Error> {
+    let yoffset = 20;
+
+    let style = PrimitiveStyleBuilder::new()
+        .stroke_width(1)
+        .stroke_color(BinaryColor::On)
+        .build();
+
+    // screen outline
+    Rectangle::new(Point::new(0, 0), Point::new(127, 63))
+        .into_styled(style)
+        .draw(target)?;
+
+    // triangle
+    Triangle::new(
+        Point::new(16, 16 + yoffset),
+        Point::new(16 + 16, 16 + yoffset),
+        Point::new(16 + 8, yoffset),
+    )
+    .into_styled(style)
+    .draw(target)?;
+
+    // square
+    Rectangle::new(Point::new(52, yoffset), Point::new(52 + 16, 16 + yoffset))
+        .into_styled(style)
+        .draw(target)?;
+
+    // circle
+    Circle::new(Point::new(96, yoffset + 8), 8)
+        .into_styled(style)
+        .draw(target)
+}
@@ -0,0 +1,208 @@
+//! One display type for SH1106 and SSD1306 panels, over SPI or I2C, so UI code doesn't care which
+//! one is attached.
+//!
+//! Pick the controller in a [Config] and the bus by calling [Config::spi], [Config::i2c] or, for
+//! anything else that implements sh1106's `DisplayInterface` (e.g.
+//! [crate::oled_dma::DmaInterface]), [Config::connect]. Either way you get back a [Display]: a
+//! [Framebuffer] with `DrawTarget`, `flush`, `reset`, `set_contrast` and `set_rotation`.
+//!
+//! ```ignore
+//! let mut disp = Config::new(Controller::Ssd1306).i2c(i2c, DEFAULT_I2C_ADDRESS);
+//! disp.reset(&mut rst, &mut delay)?;
+//! disp.init()?;
+//! ```
+//!
+//! The two controllers share nearly all of their command set, so this reuses the sh1106 crate and
+//! patches up the difference on the way out. That also means display sizes come from sh1106's
+//! `DisplaySize`, which bakes in the SH1106's 2 column offset for 128x32 panels. SSD1306 panels need
+//! to be 128x64 for now.
+use crate::framebuffer::Framebuffer;
+use embedded_hal::{
+    blocking::{i2c, spi},
+    digital::v2::OutputPin,
+};
+use sh1106::{
+    builder::NoOutputPin,
+    displayrotation::DisplayRotation,
+    interface::{DisplayInterface, SpiInterface},
+    mode::displaymode::DisplayModeTrait,
+    prelude::DisplaySize,
+    properties::DisplayProperties,
+};
+
+/// Address of most modules. Some have a solder jumper to switch to 0x3D.
+pub const DEFAULT_I2C_ADDRESS: u8 = 0x3C;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Controller {
+    /// 132 columns of RAM, with 128x64 panels centered in them
+    Sh1106,
+    /// 128 columns of RAM, and a charge pump instead of the SH1106's DC-DC converter
+    Ssd1306,
+}
+
+/// Which panel is attached and how it's mounted
+#[derive(Clone, Copy)]
+pub struct Config {
+    pub controller: Controller,
+    pub size: DisplaySize,
+    pub rotation: DisplayRotation,
+}
+
+/// Framebuffer for whatever panel [Config] says is attached
+pub type Display<DI> = Framebuffer<PanelInterface<DI>>;
+
+impl Config {
+    /// 128x64 panel, not rotated
+    pub fn new(controller: Controller) -> Self {
+        Self {
+            controller,
+            size: match controller {
+                Controller::Sh1106 => DisplaySize::Display128x64,
+                Controller::Ssd1306 => DisplaySize::Display128x64NoOffset,
+            },
+            rotation: DisplayRotation::Rotate0,
+        }
+    }
+
+    pub fn with_size(self, size: DisplaySize) -> Self {
+        Self { size, ..self }
+    }
+
+    pub fn with_rotation(self, rotation: DisplayRotation) -> Self {
+        Self { rotation, ..self }
+    }
+
+    /// 4-wire SPI with a data/command pin. Chip select is optional; pass `NoOutputPin` if it's tied
+    /// low.
+    pub fn spi<SPI, DC, CS, CommE, PinE>(
+        self,
+        spi: SPI,
+        dc: DC,
+        cs: CS,
+    ) -> Display<SpiInterface<SPI, DC, CS>>
+    where
+        SPI: spi::Write<u8, Error = CommE>,
+        DC: OutputPin<Error = PinE>,
+        CS: OutputPin<Error = PinE>,
+    {
+        self.connect(SpiInterface::new(spi, dc, cs))
+    }
+
+    /// Same as [Config::spi], for modules without a chip select pin
+    pub fn spi_without_cs<SPI, DC, CommE, PinE>(
+        self,
+        spi: SPI,
+        dc: DC,
+    ) -> Display<SpiInterface<SPI, DC, NoOutputPin<PinE>>>
+    where
+        SPI: spi::Write<u8, Error = CommE>,
+        DC: OutputPin<Error = PinE>,
+    {
+        self.spi(spi, dc, NoOutputPin::new())
+    }
+
+    pub fn i2c<I2C: i2c::Write>(self, i2c: I2C, address: u8) -> Display<I2cInterface<I2C>> {
+        self.connect(I2cInterface { i2c, address })
+    }
+
+    pub fn connect<DI: DisplayInterface>(self, iface: DI) -> Display<DI> {
+        let iface = PanelInterface {
+            iface,
+            controller: self.controller,
+        };
+        Framebuffer::new(DisplayProperties::new(iface, self.size, self.rotation))
+    }
+}
+
+/// Passes everything through to the bus, except for the few commands the SSD1306 does differently
+pub struct PanelInterface<DI> {
+    iface: DI,
+    controller: Controller,
+}
+
+impl<DI> PanelInterface<DI> {
+    pub fn controller(&self) -> Controller {
+        self.controller
+    }
+
+    pub fn release(self) -> DI {
+        self.iface
+    }
+}
+
+impl<DI: DisplayInterface> DisplayInterface for PanelInterface<DI> {
+    type Error = DI::Error;
+
+    fn init(&mut self) -> Result<(), Self::Error> {
+        self.iface.init()
+    }
+
+    fn send_commands(&mut self, commands: &[u8]) -> Result<(), Self::Error> {
+        match (self.controller, commands) {
+            // sh1106 turns on the DC-DC converter while setting up, which is where the SSD1306 wants
+            // its charge pump turned on instead. Also make sure it's in page addressing mode, which
+            // is what sh1106 assumes.
+            (Controller::Ssd1306, &[0xAD, setting]) => {
+                let charge_pump = if setting & 1 != 0 { 0x14 } else { 0x10 };
+                self.iface.send_commands(&[0x8D, charge_pump, 0x20, 0x02])
+            }
+            _ => self.iface.send_commands(commands),
+        }
+    }
+
+    fn send_data(&mut self, data: &[u8]) -> Result<(), Self::Error> {
+        self.iface.send_data(data)
+    }
+}
+
+/// Bytes per I2C write, not counting the control byte. Enough for a whole page of a 132 column
+/// display.
+const I2C_CHUNK: usize = 132;
+
+/// I2C interface for either controller. Use this rather than sh1106's, which always starts writing
+/// data at the top left corner and so can't handle the [Framebuffer]'s partial updates.
+pub struct I2cInterface<I2C> {
+    i2c: I2C,
+    address: u8,
+}
+
+impl<I2C> I2cInterface<I2C> {
+    pub fn release(self) -> I2C {
+        self.i2c
+    }
+
+    /// Send `bytes` in as few writes as possible, each one starting with `control`
+    fn write<E>(&mut self, control: u8, bytes: &[u8]) -> Result<(), E>
+    where
+        I2C: i2c::Write<Error = E>,
+    {
+        let mut buffer = [control; I2C_CHUNK + 1];
+        for chunk in bytes.chunks(I2C_CHUNK) {
+            buffer[1..=chunk.len()].copy_from_slice(chunk);
+            self.i2c.write(self.address, &buffer[..=chunk.len()])?;
+        }
+        Ok(())
+    }
+}
+
+impl<I2C, E> DisplayInterface for I2cInterface<I2C>
+where
+    I2C: i2c::Write<Error = E>,
+{
+    type Error = E;
+
+    fn init(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn send_commands(&mut self, commands: &[u8]) -> Result<(), Self::Error> {
+        // Control byte: Co = 0, D/C# = 0, i.e. commands until the end of the write
+        self.write(0x00, commands)
+    }
+
+    fn send_data(&mut self, data: &[u8]) -> Result<(), Self::Error> {
+        // Co = 0, D/C# = 1
+        self.write(0x40, data)
+    }
+}
@@ -0,0 +1,281 @@
+//! Edge interrupts for any GPIO input pin.
+//!
+//! Each of the 16 EXTI lines can watch pin N of one port, so PA4 and PB4 can't both be watched.
+//! [listen] refuses the second one with [Error::LineTaken] rather than quietly moving the line over.
+//! Lines 5-9 and 10-15 share the EXTI9_5 and EXTI15_10 vectors. [interrupt] works out which lines
+//! fired and calls their handlers with an [InputEvent], so every EXTI vector in use can just call
+//! it.
+//!
+//! ```ignore
+//! exti::init(board.spare.EXTI, board.spare.SYSCFG, DWT::cycle_count);
+//! let _switch = board.pins.d2.into_pull_up_input();
+//! exti::listen(Port::A, 10, Trigger::Both, on_switch)?;
+//! unsafe { NVIC::unmask(exti::vector(10)) };
+//!
+//! #[interrupt]
+//! fn EXTI15_10() {
+//!     exti::interrupt();
+//! }
+//! ```
+use core::cell::RefCell;
+use cortex_m::interrupt::Mutex;
+use stm32f4xx_hal::stm32::{self, Interrupt, EXTI, RCC, SYSCFG};
+
+/// Number of EXTI lines for GPIO pins
+pub const LINES: usize = 16;
+
+/// GPIO ports on every supported chip
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Port {
+    A,
+    B,
+    C,
+    D,
+    E,
+    H,
+}
+
+impl Port {
+    /// Value for the SYSCFG_EXTICR registers
+    fn code(self) -> u32 {
+        match self {
+            Port::A => 0,
+            Port::B => 1,
+            Port::C => 2,
+            Port::D => 3,
+            Port::E => 4,
+            Port::H => 7,
+        }
+    }
+
+    fn input_data(self) -> u32 {
+        // Only reads a register
+        unsafe {
+            match self {
+                Port::A => (*stm32::GPIOA::ptr()).idr.read().bits(),
+                Port::B => (*stm32::GPIOB::ptr()).idr.read().bits(),
+                Port::C => (*stm32::GPIOC::ptr()).idr.read().bits(),
+                Port::D => (*stm32::GPIOD::ptr()).idr.read().bits(),
+                Port::E => (*stm32::GPIOE::ptr()).idr.read().bits(),
+                Port::H => (*stm32::GPIOH::ptr()).idr.read().bits(),
+            }
+        }
+    }
+}
+
+/// Which edges to report
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Trigger {
+    Rising,
+    Falling,
+    Both,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Edge {
+    Rising,
+    Falling,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InputEvent {
+    pub port: Port,
+    pub line: u8,
+    pub edge: Edge,
+    /// From the clock passed to [init], read as soon as the interrupt is handled
+    pub timestamp: u32,
+}
+
+/// Called from interrupt context
+pub type Handler = fn(InputEvent);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// Lines only go up to 15
+    BadLine,
+    /// The line is already watching a pin on `port`
+    LineTaken { port: Port },
+    /// [init] hasn't been called
+    NotInitialized,
+}
+
+/// Who has each line
+#[derive(Clone, Copy)]
+pub struct Registration {
+    pub port: Port,
+    pub trigger: Trigger,
+    pub handler: Handler,
+}
+
+/// Bookkeeping for which pin each line belongs to. [listen] and [release] keep one of these in
+/// step with the hardware.
+#[derive(Default)]
+pub struct LineTable {
+    lines: [Option<Registration>; LINES],
+}
+
+impl LineTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fails if the line is out of range or already taken, even by the same pin
+    pub fn claim(&mut self, line: u8, registration: Registration) -> Result<(), Error> {
+        let slot = self
+            .lines
+            .get_mut(usize::from(line))
+            .ok_or(Error::BadLine)?;
+        match slot {
+            Some(existing) => Err(Error::LineTaken {
+                port: existing.port,
+            }),
+            None => {
+                *slot = Some(registration);
+                Ok(())
+            }
+        }
+    }
+
+    /// The registration that was there, if any
+    pub fn release(&mut self, line: u8) -> Option<Registration> {
+        self.lines.get_mut(usize::from(line))?.take()
+    }
+
+    pub fn get(&self, line: u8) -> Option<&Registration> {
+        self.lines.get(usize::from(line))?.as_ref()
+    }
+}
+
+/// The interrupt vector for a line
+pub fn vector(line: u8) -> Interrupt {
+    match line {
+        0 => Interrupt::EXTI0,
+        1 => Interrupt::EXTI1,
+        2 => Interrupt::EXTI2,
+        3 => Interrupt::EXTI3,
+        4 => Interrupt::EXTI4,
+        5..=9 => Interrupt::EXTI9_5,
+        _ => Interrupt::EXTI15_10,
+    }
+}
+
+struct Manager {
+    exti: EXTI,
+    syscfg: SYSCFG,
+    clock: fn() -> u32,
+    table: LineTable,
+}
+
+static MANAGER: Mutex<RefCell<Option<Manager>>> = Mutex::new(RefCell::new(None));
+
+/// Take over EXTI and SYSCFG. Event timestamps come from `clock`, e.g. `DWT::cycle_count` or a
+/// millisecond tick counter.
+pub fn init(exti: EXTI, syscfg: SYSCFG, clock: fn() -> u32) {
+    // SYSCFG holds the pin to line mapping, but the HAL doesn't turn its clock on
+    unsafe { (*RCC::ptr()).apb2enr.modify(|_, w| w.syscfgen().enabled()) };
+    cortex_m::interrupt::free(|cs| {
+        MANAGER.borrow(cs).replace(Some(Manager {
+            exti,
+            syscfg,
+            clock,
+            table: LineTable::new(),
+        }))
+    });
+}
+
+/// Call `handler` on edges of pin `line` of `port`. The pin should already be set up as an input.
+/// Unmask [vector]`(line)` in the NVIC afterwards.
+pub fn listen(port: Port, line: u8, trigger: Trigger, handler: Handler) -> Result<(), Error> {
+    cortex_m::interrupt::free(|cs| {
+        let mut manager = MANAGER.borrow(cs).borrow_mut();
+        let manager = manager.as_mut().ok_or(Error::NotInitialized)?;
+        manager.table.claim(
+            line,
+            Registration {
+                port,
+                trigger,
+                handler,
+            },
+        )?;
+
+        let offset = 4 * (line % 4);
+        let select = |bits: u32| (bits & !(0xf << offset)) | (port.code() << offset);
+        let syscfg = &manager.syscfg;
+        // Any 4-bit value is a valid port selection
+        unsafe {
+            match line / 4 {
+                0 => syscfg.exticr1.modify(|r, w| w.bits(select(r.bits()))),
+                1 => syscfg.exticr2.modify(|r, w| w.bits(select(r.bits()))),
+                2 => syscfg.exticr3.modify(|r, w| w.bits(select(r.bits()))),
+                _ => syscfg.exticr4.modify(|r, w| w.bits(select(r.bits()))),
+            }
+        }
+
+        let mask = 1 << line;
+        let set = |bits: u32, on: bool| if on { bits | mask } else { bits & !mask };
+        let exti = &manager.exti;
+        // Every bit below 16 is a GPIO line
+        unsafe {
+            exti.rtsr
+                .modify(|r, w| w.bits(set(r.bits(), trigger != Trigger::Falling)));
+            exti.ftsr
+                .modify(|r, w| w.bits(set(r.bits(), trigger != Trigger::Rising)));
+            // Don't report an edge from before now
+            exti.pr.write(|w| w.bits(mask));
+            exti.imr.modify(|r, w| w.bits(r.bits() | mask));
+        }
+        Ok(())
+    })
+}
+
+/// Stop watching a line and free it up for another pin
+pub fn release(line: u8) {
+    cortex_m::interrupt::free(|cs| {
+        if let Some(manager) = MANAGER.borrow(cs).borrow_mut().as_mut() {
+            if manager.table.release(line).is_some() {
+                let mask = 1 << line;
+                unsafe { manager.exti.imr.modify(|r, w| w.bits(r.bits() & !mask)) };
+            }
+        }
+    });
+}
+
+/// Call this from every EXTI interrupt handler in use. Handlers run with interrupts enabled, so
+/// they can call [listen] and [release].
+pub fn interrupt() {
+    let mut fired = [None; LINES];
+    cortex_m::interrupt::free(|cs| {
+        if let Some(manager) = MANAGER.borrow(cs).borrow_mut().as_mut() {
+            let timestamp = (manager.clock)();
+            let pending = manager.exti.pr.read().bits() & manager.exti.imr.read().bits();
+            // Writing 1s clears them
+            unsafe { manager.exti.pr.write(|w| w.bits(pending & 0xffff)) };
+            for (line, fired) in fired.iter_mut().enumerate() {
+                let line = line as u8;
+                let registration = match manager.table.get(line) {
+                    Some(registration) if pending & (1 << line) != 0 => registration,
+                    _ => continue,
+                };
+                let edge = match registration.trigger {
+                    Trigger::Rising => Edge::Rising,
+                    Trigger::Falling => Edge::Falling,
+                    // Going by the level now, which a bouncing contact may have changed again
+                    Trigger::Both if registration.port.input_data() & (1 << line) != 0 => {
+                        Edge::Rising
+                    }
+                    Trigger::Both => Edge::Falling,
+                };
+                let event = InputEvent {
+                    port: registration.port,
+                    line,
+                    edge,
+                    timestamp,
+                };
+                *fired = Some((registration.handler, event));
+            }
+        }
+    });
+    for (handler, event) in fired.iter().flatten() {
+        handler(*event);
+    }
+}
@@ -0,0 +1,226 @@
+//! Proportional fonts packed from the BDF files in `assets/fonts/` at build time. Drop a BDF in
+//! there and it shows up here as a constant named after the file, e.g. `assets/fonts/small.bdf`
+//! becomes [SMALL]. See `build/fonts.rs` for picking a subset of the characters.
+//!
+//! The bundled fonts are the embedded-graphics 6x8 ([SMALL]) and 8x16 ([LARGE]) fonts cropped to
+//! the width of each glyph. Both cover Latin-1 and, as the most important glyph outside it, ツ.
+//! Characters a font doesn't have are drawn as its replacement character, usually `?`.
+//!
+//! [TextBox] wraps and aligns text within an area.
+use crate::ui::{height, width, View};
+use embedded_graphics::{pixelcolor::BinaryColor, prelude::*, primitives::Rectangle};
+
+/// One character of a [Font]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Glyph {
+    pub c: char,
+    /// Where its bitmap starts in [Font::bitmaps]
+    pub offset: u32,
+    /// How far to move right after drawing it
+    pub advance: u8,
+    pub width: u8,
+    pub height: u8,
+    /// Left edge of the bitmap relative to the pen position
+    pub x: i8,
+    /// Top edge of the bitmap relative to the top of the line
+    pub top: i8,
+}
+
+/// A bitmap font with a glyph per character, each as wide as it needs to be
+pub struct Font {
+    /// Pixels above the baseline
+    pub ascent: u32,
+    /// Pixels below the baseline
+    pub descent: u32,
+    /// Drawn in place of characters the font doesn't have
+    pub replacement: char,
+    /// Glyph bitmaps, back to back. Rows are padded to a whole byte, most significant bit first.
+    pub bitmaps: &'static [u8],
+    /// Sorted by character
+    pub glyphs: &'static [Glyph],
+}
+
+impl Font {
+    pub fn line_height(&self) -> u32 {
+        self.ascent + self.descent
+    }
+
+    /// `None` if the font doesn't have `c`
+    pub fn glyph(&self, c: char) -> Option<&Glyph> {
+        self.glyphs
+            .binary_search_by_key(&c, |glyph| glyph.c)
+            .ok()
+            .map(|i| &self.glyphs[i])
+    }
+
+    /// The glyph `c` is drawn with
+    fn glyph_or_replacement(&self, c: char) -> &Glyph {
+        self.glyph(c)
+            .or_else(|| self.glyph(self.replacement))
+            .unwrap_or(&self.glyphs[0])
+    }
+
+    pub fn char_width(&self, c: char) -> u32 {
+        self.glyph_or_replacement(c).advance.into()
+    }
+
+    pub fn text_width(&self, text: &str) -> u32 {
+        text.chars().map(|c| self.char_width(c)).sum()
+    }
+
+    fn is_lit(&self, glyph: &Glyph, x: u32, y: u32) -> bool {
+        let row_bytes = (glyph.width as usize).div_ceil(8);
+        let byte = glyph.offset as usize + y as usize * row_bytes + x as usize / 8;
+        self.bitmaps[byte] & (0x80 >> (x % 8)) != 0
+    }
+
+    /// Draw one line of text with the top of the line at `top_left`. Only lit pixels are drawn, in
+    /// `color`. Pixels outside `clip` are skipped. Returns where the next character would go.
+    pub fn draw_line<D: DrawTarget<BinaryColor>>(
+        &self,
+        text: &str,
+        top_left: Point,
+        color: BinaryColor,
+        clip: &Rectangle,
+        target: &mut D,
+    ) -> Result<Point, D::Error> {
+        let mut pen = top_left;
+        for c in text.chars() {
+            let glyph = self.glyph_or_replacement(c);
+            let origin = pen + Point::new(glyph.x.into(), glyph.top.into());
+            let pixels = (0..u32::from(glyph.height))
+                .flat_map(|y| (0..u32::from(glyph.width)).map(move |x| (x, y)))
+                .filter(|&(x, y)| self.is_lit(glyph, x, y))
+                .map(|(x, y)| origin + Point::new(x as i32, y as i32))
+                .filter(|point| contains(clip, *point))
+                .map(|point| Pixel(point, color));
+            target.draw_iter(pixels)?;
+            pen.x += i32::from(glyph.advance);
+        }
+        Ok(pen)
+    }
+
+    /// Split `text` into lines no wider than `max_width`, breaking at spaces where possible and
+    /// always at `\n`. Words too long for a line of their own are broken wherever they overflow.
+    pub fn wrap<'a>(&'a self, text: &'a str, max_width: u32) -> Wrap<'a> {
+        Wrap {
+            font: self,
+            rest: Some(text),
+            max_width,
+        }
+    }
+}
+
+fn contains(area: &Rectangle, point: Point) -> bool {
+    point.x >= area.top_left.x
+        && point.y >= area.top_left.y
+        && point.x <= area.bottom_right.x
+        && point.y <= area.bottom_right.y
+}
+
+/// Lines of text from [Font::wrap]. Spaces at the ends of wrapped lines are dropped.
+pub struct Wrap<'a> {
+    font: &'a Font,
+    rest: Option<&'a str>,
+    max_width: u32,
+}
+
+impl<'a> Iterator for Wrap<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        let text = self.rest?;
+        let mut line_width = 0;
+        // Where the line ends if it's broken at a space
+        let mut last_space = None;
+        for (i, c) in text.char_indices() {
+            if c == '\n' {
+                // A final newline ends the last line rather than starting an empty one
+                self.rest = Some(&text[i + 1..]).filter(|rest| !rest.is_empty());
+                return Some(text[..i].trim_end_matches(' '));
+            }
+            let char_width = self.font.char_width(c);
+            if c == ' ' {
+                // The first space after a word is where to break. Spaces can hang past the edge.
+                if i > 0 && !text[..i].ends_with(' ') {
+                    last_space = Some(i);
+                }
+            } else if line_width + char_width > self.max_width && i > 0 {
+                let (line, rest) = match last_space {
+                    Some(end) => (&text[..end], text[end..].trim_start_matches(' ')),
+                    None => text.split_at(i),
+                };
+                self.rest = Some(rest);
+                return Some(line);
+            }
+            line_width += char_width;
+        }
+        self.rest = None;
+        Some(text.trim_end_matches(' '))
+    }
+}
+
+/// Where lines go between the sides of a [TextBox]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+/// Text wrapped to fit the area it's drawn in, starting at the top. Lines that don't fit are cut
+/// off.
+pub struct TextBox<'a> {
+    pub text: &'a str,
+    pub font: &'a Font,
+    pub align: Align,
+}
+
+impl<'a> TextBox<'a> {
+    pub fn new(text: &'a str, font: &'a Font) -> Self {
+        Self {
+            text,
+            font,
+            align: Align::Left,
+        }
+    }
+
+    pub fn with_align(self, align: Align) -> Self {
+        Self { align, ..self }
+    }
+
+    /// How tall the text ends up when wrapped to `max_width`
+    pub fn height(&self, max_width: u32) -> u32 {
+        self.font.wrap(self.text, max_width).count() as u32 * self.font.line_height()
+    }
+}
+
+impl View for TextBox<'_> {
+    fn draw<D: DrawTarget<BinaryColor>>(
+        &self,
+        area: Rectangle,
+        target: &mut D,
+    ) -> Result<(), D::Error> {
+        let area_width = width(&area).max(0) as u32;
+        let line_height = self.font.line_height() as i32;
+        let lines = self.font.wrap(self.text, area_width);
+        for (row, line) in lines.enumerate() {
+            let y = area.top_left.y + row as i32 * line_height;
+            if y + line_height > area.top_left.y + height(&area) {
+                break;
+            }
+            let slack = area_width.saturating_sub(self.font.text_width(line)) as i32;
+            let x = area.top_left.x
+                + match self.align {
+                    Align::Left => 0,
+                    Align::Center => slack / 2,
+                    Align::Right => slack,
+                };
+            self.font
+                .draw_line(line, Point::new(x, y), BinaryColor::On, &area, target)?;
+        }
+        Ok(())
+    }
+}
+
+include!(concat!(env!("OUT_DIR"), "/fonts.rs"));
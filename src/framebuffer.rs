@@ -0,0 +1,220 @@
+//! Drop-in replacement for sh1106's `GraphicsMode` that only sends what changed.
+//!
+//! `GraphicsMode::flush` pushes the whole 1 KiB framebuffer to the display every time, even for a
+//! one-pixel change. [Framebuffer] keeps track of which columns changed in each 8-pixel-high page
+//! and [Framebuffer::flush] only sends those, one segment per page. Drawing the same thing twice
+//! costs nothing, since a byte only counts as changed if its value actually changes.
+//!
+//! Create one the same way as a `GraphicsMode`:
+//!
+//! ```ignore
+//! let mut disp: Framebuffer<_> = sh1106::Builder::new().connect_spi(spi, dc, cs).into();
+//! disp.init()?;
+//! ```
+//!
+//! or use [crate::display] to pick between SH1106 and SSD1306 panels.
+use embedded_graphics::{drawable::Pixel, geometry::Size, pixelcolor::BinaryColor, DrawTarget};
+use embedded_hal::{blocking::delay::DelayMs, digital::v2::OutputPin};
+use sh1106::{
+    displayrotation::DisplayRotation, interface::DisplayInterface,
+    mode::displaymode::DisplayModeTrait, properties::DisplayProperties,
+};
+
+/// Enough for the biggest display the SH1106 driver supports, 132x64
+const MAX_WIDTH: usize = 132;
+pub const PAGES: usize = 8;
+
+/// Changed columns in a page, inclusive
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DirtySpan {
+    pub start: u8,
+    pub end: u8,
+}
+
+impl DirtySpan {
+    /// Number of columns
+    pub fn width(self) -> usize {
+        usize::from(self.end - self.start) + 1
+    }
+
+    fn extend(span: &mut Option<DirtySpan>, column: u8) {
+        *span = Some(match *span {
+            None => DirtySpan {
+                start: column,
+                end: column,
+            },
+            Some(DirtySpan { start, end }) => DirtySpan {
+                start: start.min(column),
+                end: end.max(column),
+            },
+        });
+    }
+}
+
+pub struct Framebuffer<DI> {
+    properties: DisplayProperties<DI>,
+    buffer: [[u8; MAX_WIDTH]; PAGES],
+    dirty: [Option<DirtySpan>; PAGES],
+}
+
+impl<DI: DisplayInterface> DisplayModeTrait<DI> for Framebuffer<DI> {
+    fn new(properties: DisplayProperties<DI>) -> Self {
+        Self {
+            properties,
+            buffer: [[0; MAX_WIDTH]; PAGES],
+            dirty: [None; PAGES],
+        }
+    }
+
+    fn release(self) -> DisplayProperties<DI> {
+        self.properties
+    }
+}
+
+impl<DI: DisplayInterface> Framebuffer<DI> {
+    /// Same as `GraphicsMode::reset`
+    pub fn reset<RST, DELAY, PinE>(&mut self, rst: &mut RST, delay: &mut DELAY) -> Result<(), PinE>
+    where
+        RST: OutputPin<Error = PinE>,
+        DELAY: DelayMs<u8>,
+    {
+        rst.set_high()?;
+        delay.delay_ms(1);
+        rst.set_low()?;
+        delay.delay_ms(10);
+        rst.set_high()
+    }
+
+    /// Set up the display. The display's RAM could hold anything at this point, so the next flush
+    /// sends everything.
+    pub fn init(&mut self) -> Result<(), DI::Error> {
+        self.properties.init_column_mode()?;
+        self.invalidate();
+        Ok(())
+    }
+
+    /// Width and height of the panel in pixels, not taking rotation into account
+    fn panel_size(&self) -> (u8, u8) {
+        self.properties.get_size().dimensions()
+    }
+
+    /// Display dimensions, taking into account the current rotation of the display
+    pub fn get_dimensions(&self) -> (u8, u8) {
+        self.properties.get_dimensions()
+    }
+
+    /// Changing the rotation doesn't move what's already on the screen, so redraw everything
+    /// afterwards.
+    pub fn set_rotation(&mut self, rotation: DisplayRotation) -> Result<(), DI::Error> {
+        self.properties.set_rotation(rotation)
+    }
+
+    pub fn set_contrast(&mut self, contrast: u8) -> Result<(), DI::Error> {
+        self.properties.set_contrast(contrast)
+    }
+
+    /// Turn off all pixels. Like drawing, this only takes effect on the next flush.
+    pub fn clear(&mut self) {
+        let (width, height) = self.panel_size();
+        for page in 0..usize::from(height) / 8 {
+            for column in 0..width {
+                self.set_byte(page, column, 0);
+            }
+        }
+    }
+
+    /// Page, column and bit within the byte for a pixel, or `None` if it's out of bounds
+    fn locate(&self, x: u32, y: u32) -> Option<(usize, u8, u8)> {
+        let (width, height) = self.panel_size();
+        // The panel is always addressed in its native orientation
+        let (column, row) = match self.properties.get_rotation() {
+            DisplayRotation::Rotate0 | DisplayRotation::Rotate180 => (x, y),
+            DisplayRotation::Rotate90 | DisplayRotation::Rotate270 => (y, x),
+        };
+        if column >= u32::from(width) || row >= u32::from(height) {
+            return None;
+        }
+        Some((row as usize / 8, column as u8, 1 << (row % 8)))
+    }
+
+    /// Turn a pixel on or off. Out of bounds pixels are ignored.
+    pub fn set_pixel(&mut self, x: u32, y: u32, on: bool) {
+        if let Some((page, column, bit)) = self.locate(x, y) {
+            let byte = self.buffer[page][usize::from(column)];
+            self.set_byte(page, column, if on { byte | bit } else { byte & !bit });
+        }
+    }
+
+    /// Whether a pixel is on, as of the last drawing rather than the last flush. Out of bounds
+    /// pixels are off.
+    pub fn get_pixel(&self, x: u32, y: u32) -> bool {
+        self.locate(x, y)
+            .is_some_and(|(page, column, bit)| self.buffer[page][usize::from(column)] & bit != 0)
+    }
+
+    fn set_byte(&mut self, page: usize, column: u8, value: u8) {
+        let byte = &mut self.buffer[page][usize::from(column)];
+        if *byte != value {
+            *byte = value;
+            DirtySpan::extend(&mut self.dirty[page], column);
+        }
+    }
+
+    /// Mark the whole screen as changed so that the next flush sends everything
+    pub fn invalidate(&mut self) {
+        let (width, height) = self.panel_size();
+        for span in self.dirty[..usize::from(height) / 8].iter_mut() {
+            *span = Some(DirtySpan {
+                start: 0,
+                end: width - 1,
+            });
+        }
+    }
+
+    /// Changed columns in each page since the last flush
+    pub fn dirty(&self) -> &[Option<DirtySpan>; PAGES] {
+        &self.dirty
+    }
+
+    /// Number of bytes the next flush will send, not counting commands
+    pub fn dirty_len(&self) -> usize {
+        self.dirty.iter().flatten().map(|span| span.width()).sum()
+    }
+
+    /// Send the changed parts of each page to the display
+    pub fn flush(&mut self) -> Result<(), DI::Error> {
+        let column_offset = self.properties.get_size().column_offset();
+        for page in 0..PAGES {
+            let span = match self.dirty[page] {
+                Some(span) => span,
+                None => continue,
+            };
+            let (start, end) = (span.start + column_offset, span.end + column_offset + 1);
+            let row = page as u8 * 8;
+            self.properties
+                .set_draw_area((start, row), (end, row + 8))?;
+            self.properties
+                .draw(&self.buffer[page][usize::from(span.start)..=usize::from(span.end)])?;
+            // Only mark the page clean once it's made it to the display, so a failed flush can be
+            // retried
+            self.dirty[page] = None;
+        }
+        Ok(())
+    }
+}
+
+impl<DI: DisplayInterface> DrawTarget<BinaryColor> for Framebuffer<DI> {
+    type Error = core::convert::Infallible;
+
+    fn draw_pixel(&mut self, Pixel(pos, color): Pixel<BinaryColor>) -> Result<(), Self::Error> {
+        if pos.x >= 0 && pos.y >= 0 {
+            self.set_pixel(pos.x as u32, pos.y as u32, color.is_on());
+        }
+        Ok(())
+    }
+
+    fn size(&self) -> Size {
+        let (width, height) = self.get_dimensions();
+        Size::new(width.into(), height.into())
+    }
+}
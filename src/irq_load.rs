@@ -0,0 +1,362 @@
+//! How much of the CPU each interrupt handler takes, how late they get going and how much time is
+//! left over, from DWT cycle counts.
+//!
+//! Call [start] with the interrupts to watch. Each of their handlers calls [enter] first thing and
+//! holds on to the guard until it returns, and the main loop (or RTIC's `idle`) sleeps with [idle]
+//! instead of a bare `WFI`. Whatever isn't idle or a watched handler counts as thread mode. A
+//! handler that gets interrupted is only charged for its own time, and so is idle when an interrupt
+//! wakes it. Handlers that don't call [enter] at all count towards whatever they interrupt.
+//!
+//! Latency is how long after its event a handler starts. There's no telling in general, so it's
+//! only tracked for handlers that work it out and pass it to [enter_late], e.g. from how far a
+//! timer has counted since its update event. A UART, for one, doesn't record when a byte came in,
+//! so its receive handler gets no latency figure and shows up as `-` in the report.
+//!
+//! ```ignore
+//! irq_load::start(&mut cp.DCB, &mut cp.DWT, board.clocks, &[Interrupt::USART2]);
+//! loop {
+//!     irq_load::idle();
+//! }
+//!
+//! #[interrupt]
+//! fn USART2() {
+//!     let _irq = irq_load::enter(Interrupt::USART2);
+//!     // ...
+//! }
+//! ```
+//!
+//! [report] prints the numbers since the last report. Cycle counts wrap around every 2^32 cycles
+//! (51 seconds at 84MHz), so a handler or sleep that long comes out wrong, and so does going that
+//! long without entering or leaving either.
+use crate::profile::ItmWriter;
+use core::{cell::RefCell, fmt};
+use cortex_m::{
+    interrupt::{Mutex, Nr},
+    peripheral::{itm::Stim, DCB, DWT},
+};
+use heapless::{
+    consts::{U17, U8},
+    Vec,
+};
+use stm32f4xx_hal::{rcc::Clocks, stm32::Interrupt};
+
+/// How many interrupts can be watched
+pub const CAPACITY: usize = 8;
+
+/// One interrupt's numbers, in cycles
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct IrqStats {
+    /// How many times the handler finished
+    pub count: u32,
+    /// Time in the handler itself, not counting anything that interrupted it
+    pub busy: u64,
+    /// Longest single run, same as [IrqStats::busy]
+    pub longest: u32,
+    /// Worst latency, if the handler reports any
+    pub worst_latency: Option<u32>,
+}
+
+#[derive(Clone, Copy)]
+enum Activity {
+    Idle,
+    /// Index into the watched interrupts, if it's one of them
+    Irq(Option<usize>),
+}
+
+#[derive(Clone, Copy)]
+struct Frame {
+    activity: Activity,
+    start: u32,
+    /// Time taken by whatever interrupted this
+    nested: u32,
+}
+
+/// The bookkeeping behind the module. Every event comes with the cycle count it happened at, so
+/// the caller decides where time comes from.
+#[derive(Clone)]
+pub struct LoadMonitor<I> {
+    irqs: Vec<(I, IrqStats), U8>,
+    idle: u64,
+    /// Since the last reset
+    window: u64,
+    last: u32,
+    /// Idle plus one frame for each of the 16 priority levels
+    stack: Vec<Frame, U17>,
+    /// Entered but didn't fit on the stack, so that exits still match up
+    lost: u32,
+}
+
+impl<I: Nr + Copy> LoadMonitor<I> {
+    pub fn new(now: u32) -> Self {
+        Self {
+            irqs: Vec::new(),
+            idle: 0,
+            window: 0,
+            last: now,
+            stack: Vec::new(),
+            lost: 0,
+        }
+    }
+
+    /// Returns false if [CAPACITY] interrupts are already being watched
+    pub fn watch(&mut self, irq: I) -> bool {
+        self.index(irq).is_some() || self.irqs.push((irq, IrqStats::default())).is_ok()
+    }
+
+    fn index(&self, irq: I) -> Option<usize> {
+        self.irqs
+            .iter()
+            .position(|(other, _)| other.nr() == irq.nr())
+    }
+
+    fn tick(&mut self, now: u32) {
+        self.window += u64::from(now.wrapping_sub(self.last));
+        self.last = now;
+    }
+
+    fn push(&mut self, activity: Activity, now: u32) {
+        self.tick(now);
+        let frame = Frame {
+            activity,
+            start: now,
+            nested: 0,
+        };
+        if self.stack.push(frame).is_err() {
+            self.lost += 1;
+        }
+    }
+
+    /// A handler started, `latency` cycles after its event if it knows
+    pub fn enter(&mut self, irq: I, now: u32, latency: Option<u32>) {
+        let index = self.index(irq);
+        if let (Some(index), Some(latency)) = (index, latency) {
+            let worst = &mut self.irqs[index].1.worst_latency;
+            *worst = Some(worst.map_or(latency, |worst| worst.max(latency)));
+        }
+        self.push(Activity::Irq(index), now);
+    }
+
+    pub fn enter_idle(&mut self, now: u32) {
+        self.push(Activity::Idle, now);
+    }
+
+    /// Whatever was entered last is done
+    pub fn exit(&mut self, now: u32) {
+        self.tick(now);
+        if self.lost > 0 {
+            self.lost -= 1;
+            return;
+        }
+        let frame = match self.stack.pop() {
+            Some(frame) => frame,
+            None => return,
+        };
+        let total = now.wrapping_sub(frame.start);
+        let own = total.saturating_sub(frame.nested);
+        match frame.activity {
+            Activity::Idle => self.idle += u64::from(own),
+            Activity::Irq(Some(index)) => {
+                let stats = &mut self.irqs[index].1;
+                stats.count = stats.count.saturating_add(1);
+                stats.busy += u64::from(own);
+                stats.longest = stats.longest.max(own);
+            }
+            Activity::Irq(None) => {}
+        }
+        if let Some(parent) = self.stack.last_mut() {
+            parent.nested = parent.nested.saturating_add(total);
+        }
+    }
+
+    pub fn irq(&self, irq: I) -> Option<IrqStats> {
+        self.index(irq).map(|index| self.irqs[index].1)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (I, IrqStats)> + '_ {
+        self.irqs.iter().copied()
+    }
+
+    /// Idle time that's over and done with
+    pub fn idle_cycles(&self) -> u64 {
+        self.idle
+    }
+
+    /// Time since the last reset
+    pub fn window_cycles(&self, now: u32) -> u64 {
+        self.window + u64::from(now.wrapping_sub(self.last))
+    }
+
+    /// Neither idle nor a watched handler
+    pub fn thread_cycles(&self, now: u32) -> u64 {
+        let busy: u64 = self.iter().map(|(_, stats)| stats.busy).sum();
+        self.window_cycles(now)
+            .saturating_sub(self.idle)
+            .saturating_sub(busy)
+    }
+
+    /// Start a new window. Anything in progress only counts from `now` on.
+    pub fn reset(&mut self, now: u32) {
+        for (_, stats) in self.irqs.iter_mut() {
+            *stats = IrqStats::default();
+        }
+        for frame in self.stack.iter_mut() {
+            frame.start = now;
+            frame.nested = 0;
+        }
+        self.idle = 0;
+        self.window = 0;
+        self.last = now;
+    }
+}
+
+impl<I: Nr + Copy + fmt::Debug> LoadMonitor<I> {
+    /// Idle and thread mode shares, then a line per interrupt, with times in microseconds at
+    /// `sysclk_hz`. Interrupts without a latency get a `-` and a note at the end saying why.
+    pub fn write_report<W: fmt::Write>(&self, w: &mut W, now: u32, sysclk_hz: u32) -> fmt::Result {
+        let window = self.window_cycles(now);
+        let us = |cycles: u32| u64::from(cycles) * 1_000_000 / u64::from(sysclk_hz.max(1));
+        write!(
+            w,
+            "{} ms: idle {}, thread {}\r\n",
+            window * 1000 / u64::from(sysclk_hz.max(1)),
+            Percent(self.idle, window),
+            Percent(self.thread_cycles(now), window)
+        )?;
+        write!(
+            w,
+            "{:<16} {:>8} {:>6} {:>11} {:>11}\r\n",
+            "irq", "count", "load", "longest us", "latency us"
+        )?;
+        for (irq, stats) in self.iter() {
+            write!(
+                w,
+                "{:<16} {:>8} {:>6} {:>11} ",
+                Padded(irq),
+                stats.count,
+                Percent(stats.busy, window),
+                us(stats.longest)
+            )?;
+            match stats.worst_latency {
+                Some(latency) => write!(w, "{:>11}\r\n", us(latency))?,
+                None => write!(w, "{:>11}\r\n", "-")?,
+            }
+        }
+        if self.iter().any(|(_, stats)| stats.worst_latency.is_none()) {
+            write!(
+                w,
+                "- latency is only known for handlers that use enter_late\r\n"
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// `part` out of `whole`, to a tenth of a percent
+struct Percent(u64, u64);
+
+impl fmt::Display for Percent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let tenths = self.0 * 1000 / self.1.max(1);
+        let mut buf = heapless::String::<U8>::new();
+        fmt::write(&mut buf, format_args!("{}.{}%", tenths / 10, tenths % 10))?;
+        f.pad(&buf)
+    }
+}
+
+/// `Debug` output that respects width, which derived `Debug` on fieldless enums doesn't
+struct Padded<T>(T);
+
+impl<T: fmt::Debug> fmt::Display for Padded<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut buf = heapless::String::<heapless::consts::U32>::new();
+        fmt::write(&mut buf, format_args!("{:?}", self.0))?;
+        f.pad(&buf)
+    }
+}
+
+struct Monitor {
+    load: LoadMonitor<Interrupt>,
+    sysclk_hz: u32,
+}
+
+static MONITOR: Mutex<RefCell<Option<Monitor>>> = Mutex::new(RefCell::new(None));
+
+/// Run `f` on the monitor, if it's been started, with the time taken inside the critical section
+/// so that the order of events is the order of timestamps
+fn with_monitor<R: Default>(f: impl FnOnce(&mut Monitor, u32) -> R) -> R {
+    cortex_m::interrupt::free(|cs| {
+        let now = DWT::cycle_count();
+        MONITOR
+            .borrow(cs)
+            .borrow_mut()
+            .as_mut()
+            .map(|monitor| f(monitor, now))
+            .unwrap_or_default()
+    })
+}
+
+/// Start the cycle counter and watch `irqs`, up to [CAPACITY] of them. Unmask them in the NVIC
+/// afterwards.
+pub fn start(dcb: &mut DCB, dwt: &mut DWT, clocks: Clocks, irqs: &[Interrupt]) {
+    dcb.enable_trace();
+    dwt.enable_cycle_counter();
+    let mut load = LoadMonitor::new(DWT::cycle_count());
+    for &irq in irqs {
+        load.watch(irq);
+    }
+    cortex_m::interrupt::free(|cs| {
+        MONITOR.borrow(cs).replace(Some(Monitor {
+            load,
+            sysclk_hz: clocks.sysclk().0,
+        }))
+    });
+}
+
+/// Until it's dropped, the handler for `irq` is running
+#[must_use = "the handler counts as done as soon as this is dropped"]
+pub struct Handler(());
+
+impl Drop for Handler {
+    fn drop(&mut self) {
+        with_monitor(|monitor, now| monitor.load.exit(now));
+    }
+}
+
+/// Call this first thing in `irq`'s handler
+pub fn enter(irq: Interrupt) -> Handler {
+    with_monitor(|monitor, now| monitor.load.enter(irq, now, None));
+    Handler(())
+}
+
+/// [enter] for a handler that knows it started `latency` cycles after its event
+pub fn enter_late(irq: Interrupt, latency: u32) -> Handler {
+    with_monitor(|monitor, now| monitor.load.enter(irq, now, Some(latency)));
+    Handler(())
+}
+
+/// Sleep until an interrupt, counting the time as idle. Not from inside a critical section, or it
+/// never wakes up.
+pub fn idle() {
+    with_monitor(|monitor, now| monitor.load.enter_idle(now));
+    cortex_m::asm::wfi();
+    with_monitor(|monitor, now| monitor.load.exit(now));
+}
+
+/// Print the numbers since the last report, or since [start], to anything that implements
+/// [fmt::Write], then start over. The time spent printing counts towards the next report.
+pub fn report<W: fmt::Write>(w: &mut W) -> fmt::Result {
+    let copy = with_monitor(|monitor, now| {
+        let copy = (monitor.load.clone(), now, monitor.sysclk_hz);
+        monitor.load.reset(now);
+        Some(copy)
+    });
+    match copy {
+        Some((load, now, sysclk_hz)) => load.write_report(w, now, sysclk_hz),
+        None => Ok(()),
+    }
+}
+
+/// [report] over an ITM stimulus port
+pub fn report_itm(stim: &mut Stim) {
+    report(&mut ItmWriter(stim)).ok();
+}
@@ -0,0 +1,272 @@
+//! Blink patterns for the user LED: heartbeat, blink codes, Morse and anything else that's a list
+//! of on and off times.
+//!
+//! A [Player] works out whether the LED should be on as it's told how much time has passed. The
+//! driver around it plays a [Pattern] on the LED from TIM11, ticking every [TICK_MS]. Call [start],
+//! unmask `TIM1_TRG_COM_TIM11` in the NVIC and call [interrupt] from its handler. [set_pattern]
+//! switches patterns from anywhere, interrupt handlers included.
+use crate::board::Led;
+use core::cell::RefCell;
+use cortex_m::interrupt::Mutex;
+use stm32f4xx_hal::{
+    prelude::*,
+    rcc::Clocks,
+    stm32::TIM11,
+    timer::{Event, Timer},
+};
+
+/// How often the driver updates the LED, which is as precise as pattern timings get
+pub const TICK_MS: u32 = 10;
+/// Length of a dot in Morse code. Dashes and the gap between letters are 3 dots, and the gap
+/// between words is 7.
+pub const MORSE_UNIT_MS: u32 = 100;
+/// On and off time of each blink in a blink code
+pub const BLINK_CODE_MS: u32 = 200;
+/// Off time between repeats of a blink code, so the blinks can be counted
+pub const BLINK_CODE_PAUSE_MS: u32 = 1200;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Pattern {
+    Off,
+    On,
+    /// Alternating on and off times in milliseconds, starting with on, repeated forever. Use an
+    /// even number of times so that every repeat starts with the LED on.
+    Sequence(&'static [u16]),
+    /// This many blinks, then a pause, repeated forever
+    BlinkCode(u8),
+    /// A message in Morse code, repeated forever. Letters and digits only. Anything else is
+    /// treated as a space.
+    Morse(&'static str),
+}
+
+/// A quick double beat about once a second
+pub const HEARTBEAT: Pattern = Pattern::Sequence(&[60, 140, 60, 740]);
+pub const SLOW_BLINK: Pattern = Pattern::Sequence(&[500, 500]);
+pub const FAST_BLINK: Pattern = Pattern::Sequence(&[100, 100]);
+
+/// Dots and dashes for A-Z, then 0-9
+const MORSE: [&str; 36] = [
+    ".-", "-...", "-.-.", "-..", ".", "..-.", "--.", "....", "..", ".---", "-.-", ".-..", "--",
+    "-.", "---", ".--.", "--.-", ".-.", "...", "-", "..-", "...-", ".--", "-..-", "-.--", "--..",
+    "-----", ".----", "..---", "...--", "....-", ".....", "-....", "--...", "---..", "----.",
+];
+
+fn morse_code(c: char) -> Option<&'static str> {
+    match c.to_ascii_uppercase() {
+        c @ 'A'..='Z' => Some(MORSE[c as usize - 'A' as usize]),
+        c @ '0'..='9' => Some(MORSE[26 + c as usize - '0' as usize]),
+        _ => None,
+    }
+}
+
+/// A stretch of time with the LED on or off
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Segment {
+    pub on: bool,
+    pub ms: u32,
+}
+
+impl Pattern {
+    /// The `index`th stretch of on or off time. `None` past the end of one repeat.
+    pub fn segment(&self, index: usize) -> Option<Segment> {
+        let segment = |on, ms| Some(Segment { on, ms });
+        match *self {
+            Pattern::Off | Pattern::On => (index == 0).then_some(Segment {
+                on: *self == Pattern::On,
+                ms: u32::MAX,
+            }),
+            Pattern::Sequence(times) => times
+                .get(index)
+                .and_then(|&ms| segment(index.is_multiple_of(2), ms.into())),
+            Pattern::BlinkCode(count) => {
+                let count = usize::from(count);
+                if index + 1 < 2 * count {
+                    segment(index.is_multiple_of(2), BLINK_CODE_MS)
+                } else if index + 1 == 2 * count {
+                    segment(false, BLINK_CODE_PAUSE_MS)
+                } else {
+                    None
+                }
+            }
+            Pattern::Morse(text) => morse_segment(text, index),
+        }
+    }
+}
+
+/// Each dot or dash is an on segment followed by an off one. The off one after the last dot or
+/// dash of a letter is the gap to the next letter or word.
+fn morse_segment(text: &str, index: usize) -> Option<Segment> {
+    let mut letters = text.chars().map(morse_code).peekable();
+    let mut skipped = 0;
+    while let Some(letter) = letters.next() {
+        let code = match letter {
+            Some(code) => code,
+            None => continue,
+        };
+        let element = (index - skipped) / 2;
+        if element >= code.len() {
+            skipped += 2 * code.len();
+            continue;
+        }
+        if (index - skipped).is_multiple_of(2) {
+            let units = if code.as_bytes()[element] == b'-' {
+                3
+            } else {
+                1
+            };
+            return Some(Segment {
+                on: true,
+                ms: units * MORSE_UNIT_MS,
+            });
+        }
+        let units = if element + 1 < code.len() {
+            1
+        } else {
+            // Anything that isn't a letter or digit separates words, and so does starting over
+            let mut word_break = false;
+            while let Some(None) = letters.peek() {
+                letters.next();
+                word_break = true;
+            }
+            if word_break || letters.peek().is_none() {
+                7
+            } else {
+                3
+            }
+        };
+        return Some(Segment {
+            on: false,
+            ms: units * MORSE_UNIT_MS,
+        });
+    }
+    None
+}
+
+/// Plays a [Pattern], keeping track of where it's up to
+pub struct Player {
+    pattern: Pattern,
+    index: usize,
+    on: bool,
+    /// Left of the current segment
+    remaining_ms: u32,
+}
+
+impl Player {
+    pub fn new(pattern: Pattern) -> Self {
+        let mut player = Self {
+            pattern,
+            index: 0,
+            on: false,
+            remaining_ms: 0,
+        };
+        player.set(pattern);
+        player
+    }
+
+    /// Switch to `pattern`, starting from the beginning
+    pub fn set(&mut self, pattern: Pattern) {
+        self.pattern = pattern;
+        self.index = 0;
+        let first = self.current();
+        self.on = first.on;
+        self.remaining_ms = first.ms.max(1);
+    }
+
+    pub fn pattern(&self) -> Pattern {
+        self.pattern
+    }
+
+    /// Whether the LED should be on right now
+    pub fn is_on(&self) -> bool {
+        self.on
+    }
+
+    /// The current segment. A pattern with nothing in it is off.
+    fn current(&self) -> Segment {
+        self.pattern.segment(self.index).unwrap_or(Segment {
+            on: false,
+            ms: u32::MAX,
+        })
+    }
+
+    /// Move time forward. Returns whether the LED should be on.
+    pub fn advance(&mut self, elapsed_ms: u32) -> bool {
+        let mut elapsed_ms = elapsed_ms;
+        while elapsed_ms >= self.remaining_ms {
+            elapsed_ms -= self.remaining_ms;
+            self.index = match self.pattern.segment(self.index + 1) {
+                Some(_) => self.index + 1,
+                None => 0,
+            };
+            let next = self.current();
+            self.on = next.on;
+            // Zero-length segments would keep this loop going forever
+            self.remaining_ms = next.ms.max(1);
+        }
+        self.remaining_ms -= elapsed_ms;
+        self.on
+    }
+}
+
+struct Driver {
+    led: Led,
+    timer: Timer<TIM11>,
+    player: Player,
+}
+
+static DRIVER: Mutex<RefCell<Option<Driver>>> = Mutex::new(RefCell::new(None));
+
+/// Start playing `pattern` on the LED. Unmask `TIM1_TRG_COM_TIM11` in the NVIC afterwards.
+pub fn start(led: Led, tim: TIM11, clocks: Clocks, pattern: Pattern) {
+    let mut timer = Timer::tim11(tim, (1000 / TICK_MS).hz(), clocks);
+    timer.listen(Event::TimeOut);
+    let mut driver = Driver {
+        led,
+        timer,
+        player: Player::new(pattern),
+    };
+    driver.show();
+    cortex_m::interrupt::free(|cs| DRIVER.borrow(cs).replace(Some(driver)));
+}
+
+impl Driver {
+    fn show(&mut self) {
+        if self.player.is_on() {
+            self.led.set_high().unwrap();
+        } else {
+            self.led.set_low().unwrap();
+        }
+    }
+}
+
+/// Switch to another pattern, starting from the beginning. Safe to call from any context.
+pub fn set_pattern(pattern: Pattern) {
+    cortex_m::interrupt::free(|cs| {
+        if let Some(driver) = DRIVER.borrow(cs).borrow_mut().as_mut() {
+            driver.player.set(pattern);
+            driver.show();
+        }
+    });
+}
+
+/// What's playing now. `None` before [start].
+pub fn pattern() -> Option<Pattern> {
+    cortex_m::interrupt::free(|cs| {
+        DRIVER
+            .borrow(cs)
+            .borrow()
+            .as_ref()
+            .map(|driver| driver.player.pattern())
+    })
+}
+
+/// Call this from the `TIM1_TRG_COM_TIM11` interrupt handler
+pub fn interrupt() {
+    cortex_m::interrupt::free(|cs| {
+        if let Some(driver) = DRIVER.borrow(cs).borrow_mut().as_mut() {
+            driver.timer.clear_interrupt(Event::TimeOut);
+            driver.player.advance(TICK_MS);
+            driver.show();
+        }
+    });
+}
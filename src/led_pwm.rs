@@ -0,0 +1,218 @@
+//! Dimmable user LED (LD2). Instead of toggling PA5 as a GPIO, TIM2 channel 1 drives it (AF1) with
+//! PWM at about [PWM_HZ].
+//!
+//! Brightness levels go from 0 to 255 and are [gamma] corrected, so equal steps look equal.
+//! [LedPwm::breathe] fades the LED up and down forever without any help from the CPU: DMA1 stream 1
+//! (channel 3 is TIM2_UP) copies the next duty cycle out of a table into CCR1 at the end of every
+//! PWM period, going round and round the table.
+//!
+//! ```ignore
+//! let mut led = LedPwm::new(board.led, board.spare.TIM2, board.spare.DMA1, board.clocks);
+//! led.set_brightness(64);
+//! led.breathe(3000);
+//! ```
+use crate::board::{self, Led};
+use core::ptr;
+use stm32f4xx_hal::{
+    gpio::{gpioa::PA5, Alternate, AF1},
+    rcc::Clocks,
+    stm32::{DMA1, RCC, TIM2},
+};
+
+/// Duty cycle for full brightness. Each PWM period is this many timer ticks.
+pub const FULL: u16 = 4096;
+/// Roughly how fast the PWM runs at a steady brightness. Fast enough not to flicker.
+pub const PWM_HZ: u32 = 1000;
+/// Most PWM periods one breath can be spread over. Longer breaths slow the PWM down below
+/// [PWM_HZ] instead.
+pub const MAX_BREATH_STEPS: usize = 2048;
+
+const STREAM: usize = 1;
+const TIM2_UP_CHANNEL: u8 = 3;
+
+/// CIE 1931 lightness, from 0 to 1, to how much light that takes, from 0 to 1
+const fn luminance(lightness: f32) -> f32 {
+    let l = lightness * 100.0;
+    if l <= 8.0 {
+        l / 903.3
+    } else {
+        let t = (l + 16.0) / 116.0;
+        t * t * t
+    }
+}
+
+const fn duty(lightness: f32) -> u16 {
+    (luminance(lightness) * FULL as f32 + 0.5) as u16
+}
+
+const GAMMA: [u16; 256] = {
+    let mut table = [0; 256];
+    let mut level = 0;
+    while level < 256 {
+        table[level] = duty(level as f32 / 255.0);
+        level += 1;
+    }
+    table
+};
+
+/// Duty cycle for a brightness level, from 0 to [FULL]
+pub fn gamma(level: u8) -> u16 {
+    GAMMA[usize::from(level)]
+}
+
+/// Duty cycle for `step` of a breath `steps` long. It starts and ends dark and is brightest halfway
+/// through, easing in and out at both ends.
+pub fn breath_duty(step: u32, steps: u32) -> u16 {
+    let position = 2.0 * step as f32 / steps as f32;
+    let x = if position <= 1.0 {
+        position
+    } else {
+        2.0 - position
+    };
+    duty(x * x * (3.0 - 2.0 * x))
+}
+
+/// Prescaler for a PWM period of [FULL] ticks at about `pwm_hz`, given the timer clock
+fn prescaler(timer_hz: u32, pwm_hz: u32) -> u32 {
+    let ticks = u64::from(pwm_hz) * u64::from(FULL);
+    let prescaler = (u64::from(timer_hz) + ticks / 2) / ticks;
+    prescaler.clamp(1, 0x1_0000) as u32 - 1
+}
+
+/// Prescaler and number of steps for a breath lasting `period_ms`, given the timer clock. The
+/// steps come out as close to [PWM_HZ] as [MAX_BREATH_STEPS] allows.
+pub fn breath_timing(timer_hz: u32, period_ms: u32) -> (u32, usize) {
+    let breath_ticks = u64::from(timer_hz) * u64::from(period_ms) / 1000;
+    let longest = (MAX_BREATH_STEPS * FULL as usize) as u64;
+    let divider = (u64::from(prescaler(timer_hz, PWM_HZ)) + 1)
+        .max(breath_ticks.div_ceil(longest))
+        .min(0x1_0000);
+    let steps = (breath_ticks / (divider * u64::from(FULL))).clamp(2, MAX_BREATH_STEPS as u64);
+    (divider as u32 - 1, steps as usize)
+}
+
+/// Duty cycles for [LedPwm::breathe]. Only written while the DMA is off. Words, since CCR1 is a
+/// 32-bit register and the bus would copy a half-word into both halves of it.
+static mut BREATH: [u32; MAX_BREATH_STEPS] = [0; MAX_BREATH_STEPS];
+
+/// The user LED on PWM. Only one can exist, since it takes over TIM2 and DMA1.
+pub struct LedPwm {
+    // Held on to so that nothing else can touch them
+    _pin: PA5<Alternate<AF1>>,
+    tim: TIM2,
+    dma: DMA1,
+    /// What TIM2 counts at
+    timer_hz: u32,
+}
+
+impl LedPwm {
+    /// Take over the LED, TIM2 and DMA1. Starts off.
+    pub fn new(led: Led, tim: TIM2, dma: DMA1, clocks: Clocks) -> Self {
+        let rcc = unsafe { &*RCC::ptr() };
+        rcc.apb1enr.modify(|_, w| w.tim2en().set_bit());
+        rcc.apb1rstr.modify(|_, w| w.tim2rst().set_bit());
+        rcc.apb1rstr.modify(|_, w| w.tim2rst().clear_bit());
+        rcc.ahb1enr.modify(|_, w| w.dma1en().set_bit());
+
+        let timer_hz = board::apb1_timer_hz(clocks);
+
+        // Duty cycles only change at the end of a period, so there are no glitches
+        tim.ccmr1_output()
+            .modify(|_, w| w.oc1pe().set_bit().oc1m().pwm_mode1());
+        tim.ccer.modify(|_, w| w.cc1e().set_bit());
+        // Any 32-bit value is a valid reload value, duty cycle and prescaler
+        unsafe {
+            tim.arr.write(|w| w.bits(u32::from(FULL) - 1));
+            tim.ccr1.write(|w| w.bits(0));
+            tim.psc.write(|w| w.bits(prescaler(timer_hz, PWM_HZ)));
+        }
+        // Load the registers now rather than at the end of the first period
+        tim.egr.write(|w| w.ug().set_bit());
+        tim.cr1.modify(|_, w| w.cen().set_bit());
+
+        let stream = &dma.st[STREAM];
+        stream.cr.modify(|_, w| w.en().disabled());
+        while stream.cr.read().en().is_enabled() {}
+        stream
+            .par
+            .write(|w| w.pa().bits(&tim.ccr1 as *const _ as u32));
+        stream.cr.write(|w| {
+            w.chsel()
+                .bits(TIM2_UP_CHANNEL)
+                .pl()
+                .low()
+                .msize()
+                .bits32()
+                .psize()
+                .bits32()
+                .minc()
+                .incremented()
+                .circ()
+                .enabled()
+                .dir()
+                .memory_to_peripheral()
+        });
+
+        Self {
+            _pin: led.into_alternate_af1(),
+            tim,
+            dma,
+            timer_hz,
+        }
+    }
+
+    /// Stay at `level`, from 0 (off) to 255, and stop breathing
+    pub fn set_brightness(&mut self, level: u8) {
+        self.stop_breathing();
+        unsafe {
+            self.tim
+                .psc
+                .write(|w| w.bits(prescaler(self.timer_hz, PWM_HZ)));
+            self.tim.ccr1.write(|w| w.bits(gamma(level).into()));
+        }
+    }
+
+    /// Fade in and out, taking `period_ms` for each breath, until told otherwise. Rounded to a
+    /// whole number of PWM periods.
+    pub fn breathe(&mut self, period_ms: u32) {
+        self.stop_breathing();
+        let (prescaler, steps) = breath_timing(self.timer_hz, period_ms);
+        // The DMA is off, so nothing else is reading the table
+        let table = unsafe { &mut *ptr::addr_of_mut!(BREATH) };
+        for (step, duty) in table[..steps].iter_mut().enumerate() {
+            *duty = breath_duty(step as u32, steps as u32).into();
+        }
+
+        let stream = &self.dma.st[STREAM];
+        stream
+            .m0ar
+            .write(|w| w.m0a().bits(ptr::addr_of!(BREATH) as u32));
+        stream.ndtr.write(|w| w.ndt().bits(steps as u16));
+        self.dma.lifcr.write(|w| {
+            w.ctcif1()
+                .set_bit()
+                .chtif1()
+                .set_bit()
+                .cteif1()
+                .set_bit()
+                .cdmeif1()
+                .set_bit()
+                .cfeif1()
+                .set_bit()
+        });
+        stream.cr.modify(|_, w| w.en().enabled());
+        unsafe { self.tim.psc.write(|w| w.bits(prescaler)) };
+        self.tim.dier.modify(|_, w| w.ude().set_bit());
+    }
+
+    pub fn is_breathing(&self) -> bool {
+        self.tim.dier.read().ude().bit_is_set()
+    }
+
+    fn stop_breathing(&mut self) {
+        self.tim.dier.modify(|_, w| w.ude().clear_bit());
+        let stream = &self.dma.st[STREAM];
+        stream.cr.modify(|_, w| w.en().disabled());
+        while stream.cr.read().en().is_enabled() {}
+    }
+}
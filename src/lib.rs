@@ -1,4 +1,28 @@
 #![no_std]
 //! Check out the examples folder!
 
+pub mod animation;
+pub mod assets;
+pub mod board;
+pub mod build_info;
+pub mod button;
+pub mod clock;
+pub mod console;
+pub mod crash_dump;
+pub mod demo;
+pub mod display;
+pub mod exti;
+pub mod font;
+pub mod framebuffer;
+pub mod irq_load;
+pub mod led;
+pub mod led_pwm;
+pub mod oled_dma;
+pub mod plot;
+pub mod profile;
+pub mod qr;
+pub mod stack;
+pub mod timer_delay;
+pub mod timer_service;
 pub mod uart_driver;
+pub mod ui;
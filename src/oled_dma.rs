@@ -0,0 +1,294 @@
+//! Non-blocking display interface for the SH1106 OLED on SPI1, using DMA2 stream 3 (channel 3 is
+//! SPI1_TX).
+//!
+//! [DmaInterface] implements sh1106's `DisplayInterface`, so it slots in under a
+//! [crate::framebuffer::Framebuffer] in place of the blocking `SpiInterface`. Instead of waiting for
+//! each write to go out, commands and data are copied into a queue and the DMA interrupt works
+//! through it in the background, toggling D/C (PC7) in between. A full-screen flush returns as soon
+//! as the frame has been copied, and the CPU is free while it goes out over the wire. Poll
+//! [is_busy], [wait] or register a callback with [set_on_complete] to find out when it's done.
+//!
+//! The DMA2_STREAM3 interrupt has to be unmasked and call [interrupt]:
+//!
+//! ```ignore
+//! #[interrupt]
+//! fn DMA2_STREAM3() {
+//!     oled_dma::interrupt();
+//! }
+//! ```
+//!
+//! If the queue fills up, writes wait for it to drain, which relies on that interrupt. Don't draw
+//! from a context that blocks the DMA interrupt.
+use core::{
+    cell::RefCell,
+    convert::Infallible,
+    ptr,
+    sync::atomic::{AtomicBool, Ordering},
+};
+use cortex_m::interrupt::Mutex;
+use heapless::{consts::U32, Vec};
+use sh1106::interface::DisplayInterface;
+use stm32f4xx_hal::{
+    gpio::{
+        gpioa::{PA5, PA7},
+        gpioc::PC7,
+        Alternate, Output, PushPull, AF5,
+    },
+    prelude::*,
+    spi::{NoMiso, Spi},
+    stm32::{DMA2, RCC, SPI1},
+};
+
+/// SPI1 with SCK on PA5 (D13) and MOSI on PA7 (D11), already set up as for the blocking interface
+pub type OledSpi = Spi<SPI1, (PA5<Alternate<AF5>>, NoMiso, PA7<Alternate<AF5>>)>;
+pub type DcPin = PC7<Output<PushPull>>;
+
+/// Room for a full frame of a 132x64 display plus the addressing commands for each page
+const BUFFER_SIZE: usize = 1280;
+const STREAM: usize = 3;
+const SPI1_TX_CHANNEL: u8 = 3;
+
+/// Bytes queued up for the DMA. Only the parts that aren't being sent are ever written to.
+static mut BUFFER: [u8; BUFFER_SIZE] = [0; BUFFER_SIZE];
+static BUSY: AtomicBool = AtomicBool::new(false);
+static STATE: Mutex<RefCell<Option<State>>> = Mutex::new(RefCell::new(None));
+
+/// A run of commands or data in [BUFFER]
+struct Chunk {
+    is_data: bool,
+    start: usize,
+    len: usize,
+}
+
+struct State {
+    dc: DcPin,
+    // Held on to so that nothing else can touch them
+    _spi: OledSpi,
+    _dma: DMA2,
+    chunks: Vec<Chunk, U32>,
+    /// Index of the next chunk to send
+    next: usize,
+    /// Bytes of [BUFFER] in use
+    used: usize,
+    sending: bool,
+    on_complete: Option<fn()>,
+}
+
+impl State {
+    /// Append bytes to the queue and get the DMA going if it isn't already. Returns false if
+    /// there's no room.
+    fn enqueue(&mut self, is_data: bool, bytes: &[u8]) -> bool {
+        if bytes.is_empty() {
+            return true;
+        }
+        if !self.sending && self.next == self.chunks.len() {
+            // Everything has gone out, so start over at the beginning of the buffer
+            self.chunks.clear();
+            self.next = 0;
+            self.used = 0;
+        }
+        if self.used + bytes.len() > BUFFER_SIZE {
+            return false;
+        }
+        // Merge with the last chunk if it's of the same kind and hasn't started going out yet
+        let last_pending = self.next < self.chunks.len();
+        match self.chunks.last_mut() {
+            Some(last) if last_pending && last.is_data == is_data => {
+                last.len += bytes.len();
+            }
+            _ => {
+                let chunk = Chunk {
+                    is_data,
+                    start: self.used,
+                    len: bytes.len(),
+                };
+                if self.chunks.push(chunk).is_err() {
+                    return false;
+                }
+            }
+        }
+        unsafe {
+            let dst = (ptr::addr_of_mut!(BUFFER) as *mut u8).add(self.used);
+            ptr::copy_nonoverlapping(bytes.as_ptr(), dst, bytes.len());
+        }
+        self.used += bytes.len();
+
+        if !self.sending {
+            self.start_next();
+        }
+        true
+    }
+
+    fn start_next(&mut self) {
+        let chunk = &self.chunks[self.next];
+        self.next += 1;
+
+        // D/C can't change until the last byte of the previous chunk has left the shift register
+        wait_for_spi_idle();
+        if chunk.is_data {
+            self.dc.set_high().ok();
+        } else {
+            self.dc.set_low().ok();
+        }
+
+        let dma = unsafe { &*DMA2::ptr() };
+        let stream = &dma.st[STREAM];
+        let address = ptr::addr_of!(BUFFER) as u32 + chunk.start as u32;
+        stream.m0ar.write(|w| w.m0a().bits(address));
+        stream.ndtr.write(|w| w.ndt().bits(chunk.len as u16));
+        clear_flags();
+        stream.cr.modify(|_, w| w.en().enabled());
+
+        self.sending = true;
+        BUSY.store(true, Ordering::Release);
+    }
+}
+
+fn wait_for_spi_idle() {
+    let spi = unsafe { &*SPI1::ptr() };
+    while spi.sr.read().txe().bit_is_clear() || spi.sr.read().bsy().bit_is_set() {}
+}
+
+fn clear_flags() {
+    let dma = unsafe { &*DMA2::ptr() };
+    dma.lifcr.write(|w| {
+        w.ctcif3()
+            .set_bit()
+            .chtif3()
+            .set_bit()
+            .cteif3()
+            .set_bit()
+            .cdmeif3()
+            .set_bit()
+            .cfeif3()
+            .set_bit()
+    });
+}
+
+/// Handle for the queue. Only one can exist, since it takes over SPI1 and DMA2.
+pub struct DmaInterface {
+    _private: (),
+}
+
+impl DmaInterface {
+    /// Take over SPI1 and DMA2. Panics if called twice.
+    pub fn new(spi: OledSpi, dma: DMA2, dc: DcPin) -> Self {
+        let rcc = unsafe { &*RCC::ptr() };
+        rcc.ahb1enr.modify(|_, w| w.dma2en().set_bit());
+
+        let spi_regs = unsafe { &*SPI1::ptr() };
+        let stream = &dma.st[STREAM];
+        stream.cr.modify(|_, w| w.en().disabled());
+        while stream.cr.read().en().is_enabled() {}
+        stream
+            .par
+            .write(|w| w.pa().bits(&spi_regs.dr as *const _ as u32));
+        stream.cr.write(|w| {
+            w.chsel()
+                .bits(SPI1_TX_CHANNEL)
+                .pl()
+                .high()
+                .msize()
+                .bits8()
+                .psize()
+                .bits8()
+                .minc()
+                .incremented()
+                .dir()
+                .memory_to_peripheral()
+                .tcie()
+                .enabled()
+        });
+        spi_regs.cr2.modify(|_, w| w.txdmaen().set_bit());
+
+        cortex_m::interrupt::free(|cs| {
+            let mut state = STATE.borrow(cs).borrow_mut();
+            assert!(state.is_none(), "DmaInterface already exists");
+            *state = Some(State {
+                dc,
+                _spi: spi,
+                _dma: dma,
+                chunks: Vec::new(),
+                next: 0,
+                used: 0,
+                sending: false,
+                on_complete: None,
+            });
+        });
+        Self { _private: () }
+    }
+
+    fn send(&mut self, is_data: bool, bytes: &[u8]) {
+        for piece in bytes.chunks(BUFFER_SIZE) {
+            loop {
+                let queued = cortex_m::interrupt::free(|cs| {
+                    let mut state = STATE.borrow(cs).borrow_mut();
+                    state.as_mut().unwrap().enqueue(is_data, piece)
+                });
+                if queued {
+                    break;
+                }
+                wait();
+            }
+        }
+    }
+}
+
+impl DisplayInterface for DmaInterface {
+    type Error = Infallible;
+
+    fn init(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn send_commands(&mut self, cmds: &[u8]) -> Result<(), Self::Error> {
+        self.send(false, cmds);
+        Ok(())
+    }
+
+    fn send_data(&mut self, buf: &[u8]) -> Result<(), Self::Error> {
+        self.send(true, buf);
+        Ok(())
+    }
+}
+
+/// Whether there's anything still waiting to go out
+pub fn is_busy() -> bool {
+    BUSY.load(Ordering::Acquire)
+}
+
+/// Busy-wait for everything queued so far to go out
+pub fn wait() {
+    while is_busy() {}
+}
+
+/// Call `callback` from the DMA interrupt whenever the queue runs empty, e.g. to spawn an RTIC task
+/// or set a flag. It runs outside of any critical section.
+pub fn set_on_complete(callback: fn()) {
+    cortex_m::interrupt::free(|cs| {
+        if let Some(state) = STATE.borrow(cs).borrow_mut().as_mut() {
+            state.on_complete = Some(callback);
+        }
+    });
+}
+
+/// DMA2_STREAM3 interrupt handler. Starts the next chunk, if there is one.
+pub fn interrupt() {
+    let on_complete = cortex_m::interrupt::free(|cs| {
+        let mut state = STATE.borrow(cs).borrow_mut();
+        let state = state.as_mut()?;
+        clear_flags();
+        state.sending = false;
+        if state.next < state.chunks.len() {
+            state.start_next();
+            None
+        } else {
+            wait_for_spi_idle();
+            BUSY.store(false, Ordering::Release);
+            state.on_complete
+        }
+    });
+    if let Some(callback) = on_complete {
+        callback();
+    }
+}
@@ -0,0 +1,348 @@
+//! Live plots for the OLED: sparklines, strip charts and bar graphs. Good for keeping an eye on ADC
+//! readings, throughput or loop timing.
+//!
+//! Samples go into a fixed-capacity [Series], which drops the oldest sample once it's full. The
+//! widgets implement [View], so they can be laid out like any other UI widget. Line plots put the
+//! newest sample at the right edge and go back one sample per pixel column. With [Scale::Auto],
+//! the vertical axis stretches to fit whatever is on screen.
+use crate::ui::{self, View, LINE_HEIGHT};
+use core::fmt::Write;
+use embedded_graphics::{
+    pixelcolor::BinaryColor,
+    prelude::*,
+    primitives::{Line, Rectangle},
+    style::PrimitiveStyle,
+};
+use heapless::{consts::U12, spsc::Queue, ArrayLength, String};
+
+/// Ring buffer of the most recent `N` samples
+pub struct Series<N: ArrayLength<i32>> {
+    samples: Queue<i32, N>,
+}
+
+impl<N: ArrayLength<i32>> Default for Series<N> {
+    fn default() -> Self {
+        Self {
+            samples: Queue::new(),
+        }
+    }
+}
+
+impl<N: ArrayLength<i32>> Series<N> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a sample, dropping the oldest one if there's no room
+    pub fn push(&mut self, value: i32) {
+        if self.samples.len() == self.samples.capacity() {
+            self.samples.dequeue();
+        }
+        self.samples.enqueue(value).ok();
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        while self.samples.dequeue().is_some() {}
+    }
+
+    pub fn latest(&self) -> Option<i32> {
+        self.samples.iter().next_back().copied()
+    }
+
+    /// Oldest to newest
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = i32> + '_ {
+        self.samples.iter().copied()
+    }
+
+    /// The last `count` samples, newest first
+    pub fn recent(&self, count: usize) -> impl Iterator<Item = i32> + '_ {
+        self.iter().rev().take(count)
+    }
+
+    /// Smallest and largest of the last `count` samples
+    pub fn range(&self, count: usize) -> Option<(i32, i32)> {
+        min_max(self.recent(count))
+    }
+}
+
+fn min_max(values: impl Iterator<Item = i32>) -> Option<(i32, i32)> {
+    values.fold(None, |range, value| match range {
+        None => Some((value, value)),
+        Some((min, max)) => Some((min.min(value), max.max(value))),
+    })
+}
+
+/// Vertical range of a plot
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Scale {
+    /// Fit the samples on screen
+    Auto,
+    /// Fixed range. Anything outside it gets clipped to the edge.
+    Fixed { min: i32, max: i32 },
+}
+
+impl Scale {
+    /// Range to plot, never empty
+    fn range(self, values: impl Iterator<Item = i32>) -> (i32, i32) {
+        let (min, max) = match self {
+            Scale::Auto => min_max(values).unwrap_or((0, 0)),
+            Scale::Fixed { min, max } => (min.min(max), min.max(max)),
+        };
+        if min == max {
+            (min.saturating_sub(1), max.saturating_add(1))
+        } else {
+            (min, max)
+        }
+    }
+}
+
+/// Vertical pixel position of `value` between `top` and `bottom`
+fn to_y(value: i32, (min, max): (i32, i32), top: i32, bottom: i32) -> i32 {
+    let value = i64::from(value.max(min).min(max));
+    let offset =
+        (value - i64::from(min)) * i64::from(bottom - top) / (i64::from(max) - i64::from(min));
+    bottom - offset as i32
+}
+
+/// Plot `samples`, newest first, as connected points going left from the right edge of `area`
+fn draw_line_plot<D: DrawTarget<BinaryColor>>(
+    samples: impl Iterator<Item = i32>,
+    range: (i32, i32),
+    area: Rectangle,
+    target: &mut D,
+) -> Result<(), D::Error> {
+    let style = PrimitiveStyle::with_stroke(BinaryColor::On, 1);
+    let (top, bottom) = (area.top_left.y, area.bottom_right.y);
+    let mut previous: Option<Point> = None;
+    for (age, value) in samples.enumerate() {
+        let x = area.bottom_right.x - age as i32;
+        if x < area.top_left.x {
+            break;
+        }
+        let point = Point::new(x, to_y(value, range, top, bottom));
+        match previous {
+            Some(previous) => Line::new(point, previous).into_styled(style).draw(target)?,
+            None => Pixel(point, BinaryColor::On).draw(target)?,
+        }
+        previous = Some(point);
+    }
+    Ok(())
+}
+
+/// Compact line plot without labels or a frame, e.g. for a status bar
+pub struct Sparkline<'a, N: ArrayLength<i32>> {
+    pub series: &'a Series<N>,
+    pub scale: Scale,
+}
+
+impl<'a, N: ArrayLength<i32>> Sparkline<'a, N> {
+    pub fn new(series: &'a Series<N>) -> Self {
+        Self {
+            series,
+            scale: Scale::Auto,
+        }
+    }
+}
+
+impl<N: ArrayLength<i32>> View for Sparkline<'_, N> {
+    fn draw<D: DrawTarget<BinaryColor>>(
+        &self,
+        area: Rectangle,
+        target: &mut D,
+    ) -> Result<(), D::Error> {
+        let count = ui::width(&area) as usize;
+        let range = self.scale.range(self.series.recent(count));
+        draw_line_plot(self.series.recent(count), range, area, target)
+    }
+}
+
+/// Time axis for a [StripChart]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimeAxis {
+    /// Time between samples
+    pub sample_period_ms: u32,
+    /// Time between tick marks. 0 for none.
+    pub tick_ms: u32,
+}
+
+/// Line plot in a frame, with the vertical range labeled on the left. With a [TimeAxis], it also
+/// gets tick marks along the bottom and a label for how far back the plot goes.
+pub struct StripChart<'a, N: ArrayLength<i32>> {
+    pub series: &'a Series<N>,
+    pub scale: Scale,
+    pub time_axis: Option<TimeAxis>,
+}
+
+impl<'a, N: ArrayLength<i32>> StripChart<'a, N> {
+    pub fn new(series: &'a Series<N>) -> Self {
+        Self {
+            series,
+            scale: Scale::Auto,
+            time_axis: None,
+        }
+    }
+}
+
+/// e.g. 250ms, 3s or 1.5s
+fn format_duration(ms: u32) -> String<U12> {
+    let mut text = String::new();
+    if ms < 1000 {
+        write!(text, "{}ms", ms).ok();
+    } else if ms % 1000 < 100 {
+        write!(text, "{}s", ms / 1000).ok();
+    } else {
+        write!(text, "{}.{}s", ms / 1000, ms % 1000 / 100).ok();
+    }
+    text
+}
+
+impl<N: ArrayLength<i32>> View for StripChart<'_, N> {
+    fn draw<D: DrawTarget<BinaryColor>>(
+        &self,
+        area: Rectangle,
+        target: &mut D,
+    ) -> Result<(), D::Error> {
+        // Leave a line at the bottom for the time axis label
+        let frame_bottom = match self.time_axis {
+            Some(_) => area.bottom_right.y - LINE_HEIGHT,
+            None => area.bottom_right.y,
+        };
+
+        // The range depends on how many samples fit, which depends on how wide the labels are. Size
+        // the labels for the whole series, which is at least as wide as any part of it.
+        let label = |value: i32| {
+            let mut text: String<U12> = String::new();
+            write!(text, "{}", value).ok();
+            text
+        };
+        let label_width = |(min, max): (i32, i32)| {
+            ui::text_width(&label(min)).max(ui::text_width(&label(max))) + 2
+        };
+        let all = self.scale.range(self.series.iter());
+        let frame_left = area.top_left.x + label_width(all);
+        let frame = Rectangle::new(
+            Point::new(frame_left, area.top_left.y),
+            Point::new(area.bottom_right.x, frame_bottom),
+        );
+        let inside = Rectangle::new(
+            frame.top_left + Point::new(1, 1),
+            frame.bottom_right - Point::new(1, 1),
+        );
+        let count = ui::width(&inside).max(0) as usize;
+        let range = self.scale.range(self.series.recent(count));
+
+        frame
+            .into_styled(PrimitiveStyle::with_stroke(BinaryColor::On, 1))
+            .draw(target)?;
+        let label_space = frame_left - area.top_left.x;
+        ui::draw_text(&label(range.1), area.top_left, label_space, false, target)?;
+        ui::draw_text(
+            &label(range.0),
+            Point::new(area.top_left.x, frame_bottom + 1 - LINE_HEIGHT),
+            label_space,
+            false,
+            target,
+        )?;
+
+        if let Some(axis) = self.time_axis {
+            let period = axis.sample_period_ms.max(1);
+            if axis.tick_ms > 0 {
+                let spacing = (axis.tick_ms / period).max(2) as i32;
+                let mut x = inside.bottom_right.x - spacing;
+                while x >= inside.top_left.x {
+                    Line::new(Point::new(x, frame_bottom - 2), Point::new(x, frame_bottom))
+                        .into_styled(PrimitiveStyle::with_stroke(BinaryColor::On, 1))
+                        .draw(target)?;
+                    x -= spacing;
+                }
+            }
+            let mut span: String<U12> = String::new();
+            write!(
+                span,
+                "-{}",
+                format_duration(period.saturating_mul(count as u32))
+            )
+            .ok();
+            ui::draw_text(
+                &span,
+                Point::new(frame_left, frame_bottom + 1),
+                ui::width(&frame),
+                false,
+                target,
+            )?;
+            let now = "now";
+            ui::draw_text(
+                now,
+                Point::new(
+                    area.bottom_right.x + 1 - ui::text_width(now),
+                    frame_bottom + 1,
+                ),
+                ui::text_width(now),
+                false,
+                target,
+            )?;
+        }
+
+        draw_line_plot(self.series.recent(count), range, inside, target)
+    }
+}
+
+/// One vertical bar per value, e.g. one per ADC channel. The bars grow from zero, so negative
+/// values hang down.
+pub struct BarGraph<'a> {
+    pub values: &'a [i32],
+    pub scale: Scale,
+    /// Pixels between bars
+    pub gap: i32,
+}
+
+impl<'a> BarGraph<'a> {
+    pub fn new(values: &'a [i32]) -> Self {
+        Self {
+            values,
+            scale: Scale::Auto,
+            gap: 1,
+        }
+    }
+}
+
+impl View for BarGraph<'_> {
+    fn draw<D: DrawTarget<BinaryColor>>(
+        &self,
+        area: Rectangle,
+        target: &mut D,
+    ) -> Result<(), D::Error> {
+        if self.values.is_empty() {
+            return Ok(());
+        }
+        // Always include the baseline
+        let range = self
+            .scale
+            .range(self.values.iter().copied().chain(core::iter::once(0)));
+        let (top, bottom) = (area.top_left.y, area.bottom_right.y);
+        let zero = to_y(0, range, top, bottom);
+        let count = self.values.len() as i32;
+        let bar_width = ((ui::width(&area) - self.gap * (count - 1)) / count).max(1);
+        for (i, &value) in self.values.iter().enumerate() {
+            let left = area.top_left.x + i as i32 * (bar_width + self.gap);
+            if left > area.bottom_right.x {
+                break;
+            }
+            let y = to_y(value, range, top, bottom);
+            let bar = Rectangle::new(
+                Point::new(left, y.min(zero)),
+                Point::new((left + bar_width - 1).min(area.bottom_right.x), y.max(zero)),
+            );
+            ui::fill(bar, BinaryColor::On, target)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,252 @@
+//! How long named sections of code take, in CPU cycles from the DWT cycle counter.
+//!
+//! Call [enable] once, then time sections with a [Section] guard, which stops the clock when it's
+//! dropped, or the [profile!](crate::profile!) macro around an expression. Each name keeps a count
+//! and the min, max and average, and [report] or [report_itm] prints them all. Sections can nest,
+//! in which case the outer one includes the inner one and a bit of bookkeeping.
+//!
+//! ```ignore
+//! profile::enable(&mut cp.DCB, &mut cp.DWT, board.clocks);
+//!
+//! profile!("draw_kes", demo::draw_kes(&mut disp)).unwrap();
+//! {
+//!     let _section = profile::section("flush");
+//!     disp.flush().unwrap();
+//! }
+//! profile::report_itm(&mut itm.stim[0]);
+//! ```
+//!
+//! The counter wraps around every 2^32 cycles (51 seconds at 84MHz), so anything longer comes out
+//! wrong.
+use core::{cell::RefCell, fmt};
+use cortex_m::{
+    interrupt::Mutex,
+    itm,
+    peripheral::{itm::Stim, DCB, DWT},
+};
+use heapless::{consts::U16, Vec};
+use stm32f4xx_hal::rcc::Clocks;
+
+/// How many differently named sections can be tracked
+pub const CAPACITY: usize = 16;
+
+/// Timings of one section, in cycles
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Stats {
+    pub count: u32,
+    pub total: u64,
+    pub min: u32,
+    pub max: u32,
+}
+
+impl Default for Stats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Stats {
+    pub const fn new() -> Self {
+        Self {
+            count: 0,
+            total: 0,
+            min: u32::MAX,
+            max: 0,
+        }
+    }
+
+    pub fn record(&mut self, cycles: u32) {
+        self.count = self.count.saturating_add(1);
+        self.total += u64::from(cycles);
+        self.min = self.min.min(cycles);
+        self.max = self.max.max(cycles);
+    }
+
+    /// 0 if nothing's been recorded
+    pub fn avg(&self) -> u32 {
+        match self.count {
+            0 => 0,
+            count => (self.total / u64::from(count)) as u32,
+        }
+    }
+}
+
+/// [Stats] by section name, in the order the sections first ran
+#[derive(Clone, Default)]
+pub struct Profile {
+    sections: Vec<(&'static str, Stats), U16>,
+}
+
+impl Profile {
+    pub fn new() -> Self {
+        Self {
+            sections: Vec::new(),
+        }
+    }
+
+    /// Returns false if this is a new name and there's no room for it
+    pub fn record(&mut self, name: &'static str, cycles: u32) -> bool {
+        if let Some((_, stats)) = self.sections.iter_mut().find(|(other, _)| *other == name) {
+            stats.record(cycles);
+            return true;
+        }
+        let mut stats = Stats::new();
+        stats.record(cycles);
+        self.sections.push((name, stats)).is_ok()
+    }
+
+    pub fn get(&self, name: &str) -> Option<Stats> {
+        self.sections
+            .iter()
+            .find(|(other, _)| *other == name)
+            .map(|&(_, stats)| stats)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, Stats)> + '_ {
+        self.sections.iter().copied()
+    }
+
+    /// Forget everything
+    pub fn clear(&mut self) {
+        // heapless's own clear trips debug assertions in newer compilers
+        self.sections = Vec::new();
+    }
+
+    /// One line per section: count, then min, average and max in cycles, then the average in
+    /// microseconds at `sysclk_hz`
+    pub fn write_report<W: fmt::Write>(&self, w: &mut W, sysclk_hz: u32) -> fmt::Result {
+        write!(
+            w,
+            "{:<16} {:>8} {:>10} {:>10} {:>10} {:>8}\r\n",
+            "section", "count", "min", "avg", "max", "avg us"
+        )?;
+        for (name, stats) in self.iter() {
+            let avg_us = u64::from(stats.avg()) * 1_000_000 / u64::from(sysclk_hz.max(1));
+            write!(
+                w,
+                "{:<16} {:>8} {:>10} {:>10} {:>10} {:>8}\r\n",
+                name,
+                stats.count,
+                stats.min,
+                stats.avg(),
+                stats.max,
+                avg_us
+            )?;
+        }
+        Ok(())
+    }
+}
+
+struct Profiler {
+    profile: Profile,
+    sysclk_hz: u32,
+}
+
+static PROFILER: Mutex<RefCell<Option<Profiler>>> = Mutex::new(RefCell::new(None));
+
+/// Start the cycle counter. Sections that run before this aren't recorded.
+pub fn enable(dcb: &mut DCB, dwt: &mut DWT, clocks: Clocks) {
+    dcb.enable_trace();
+    dwt.enable_cycle_counter();
+    cortex_m::interrupt::free(|cs| {
+        PROFILER.borrow(cs).replace(Some(Profiler {
+            profile: Profile::new(),
+            sysclk_hz: clocks.sysclk().0,
+        }))
+    });
+}
+
+/// Add a timing by hand, e.g. one taken with [DWT::cycle_count] across a function boundary.
+/// Returns false if it wasn't recorded, either because there's no room for a new name or because
+/// [enable] hasn't been called.
+pub fn record(name: &'static str, cycles: u32) -> bool {
+    cortex_m::interrupt::free(|cs| {
+        PROFILER
+            .borrow(cs)
+            .borrow_mut()
+            .as_mut()
+            .is_some_and(|profiler| profiler.profile.record(name, cycles))
+    })
+}
+
+/// Times from here until it's dropped
+#[must_use = "the section ends as soon as this is dropped"]
+pub struct Section {
+    name: &'static str,
+    start: u32,
+}
+
+/// Start timing `name`
+pub fn section(name: &'static str) -> Section {
+    Section {
+        name,
+        start: DWT::cycle_count(),
+    }
+}
+
+impl Drop for Section {
+    fn drop(&mut self) {
+        let cycles = DWT::cycle_count().wrapping_sub(self.start);
+        record(self.name, cycles);
+    }
+}
+
+/// Time an expression as section `$name`, evaluating to its value
+#[macro_export]
+macro_rules! profile {
+    ($name:expr, $body:expr) => {{
+        let _section = $crate::profile::section($name);
+        $body
+    }};
+}
+
+/// A copy of everything recorded so far. Empty before [enable].
+pub fn snapshot() -> Profile {
+    cortex_m::interrupt::free(|cs| {
+        PROFILER
+            .borrow(cs)
+            .borrow()
+            .as_ref()
+            .map(|profiler| profiler.profile.clone())
+            .unwrap_or_default()
+    })
+}
+
+/// Start over, e.g. after a warm-up period
+pub fn reset() {
+    cortex_m::interrupt::free(|cs| {
+        if let Some(profiler) = PROFILER.borrow(cs).borrow_mut().as_mut() {
+            profiler.profile.clear();
+        }
+    });
+}
+
+/// Print every section to anything that implements [fmt::Write], like
+/// [crate::uart_driver::UartContext]. Sections that finish while it's printing are kept for the
+/// next report.
+pub fn report<W: fmt::Write>(w: &mut W) -> fmt::Result {
+    let (profile, sysclk_hz) = cortex_m::interrupt::free(|cs| {
+        PROFILER
+            .borrow(cs)
+            .borrow()
+            .as_ref()
+            .map(|profiler| (profiler.profile.clone(), profiler.sysclk_hz))
+            .unwrap_or_default()
+    });
+    profile.write_report(w, sysclk_hz)
+}
+
+/// [report] over an ITM stimulus port
+pub fn report_itm(stim: &mut Stim) {
+    report(&mut ItmWriter(stim)).ok();
+}
+
+/// [fmt::Write] over an ITM stimulus port
+pub(crate) struct ItmWriter<'a>(pub(crate) &'a mut Stim);
+
+impl fmt::Write for ItmWriter<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        itm::write_str(self.0, s);
+        Ok(())
+    }
+}
@@ -0,0 +1,503 @@
+//! QR codes for the OLED, e.g. to show a serial number or URL that a technician can scan with their
+//! phone.
+//!
+//! This only covers what fits on a small display: byte mode, versions 1 through 4 (21x21 up to 33x33
+//! modules) and error correction levels L and M. That's up to 78 bytes at level L or 62 at level M.
+//! [QrCode] implements [View] and draws itself as large as it fits, dark modules unlit on a lit
+//! background.
+use crate::ui::{self, View};
+use embedded_graphics::{pixelcolor::BinaryColor, prelude::*, primitives::Rectangle};
+
+pub const MAX_VERSION: u8 = 4;
+const MAX_SIZE: usize = 17 + 4 * MAX_VERSION as usize;
+const MAX_CODEWORDS: usize = 100;
+const MAX_ECC_CODEWORDS: usize = 26;
+
+/// Light modules drawn around the code. The standard asks for 4, but phones cope with 2, which lets
+/// a version 2 code be drawn at double size in 64x64 pixels.
+pub const QUIET_ZONE: i32 = 2;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EccLevel {
+    /// Recovers about 7% of the code
+    L,
+    /// Recovers about 15% of the code
+    M,
+}
+
+/// How the codewords of a version are split up
+struct Layout {
+    data_codewords: usize,
+    ecc_codewords_per_block: usize,
+    blocks: usize,
+}
+
+const fn layout(data_codewords: usize, ecc_codewords_per_block: usize, blocks: usize) -> Layout {
+    Layout {
+        data_codewords,
+        ecc_codewords_per_block,
+        blocks,
+    }
+}
+
+/// Versions 1 through 4
+const LAYOUTS_L: [Layout; 4] = [
+    layout(19, 7, 1),
+    layout(34, 10, 1),
+    layout(55, 15, 1),
+    layout(80, 20, 1),
+];
+const LAYOUTS_M: [Layout; 4] = [
+    layout(16, 10, 1),
+    layout(28, 16, 1),
+    layout(44, 26, 1),
+    layout(64, 18, 2),
+];
+
+impl EccLevel {
+    fn layout(self, version: u8) -> &'static Layout {
+        let layouts = match self {
+            EccLevel::L => &LAYOUTS_L,
+            EccLevel::M => &LAYOUTS_M,
+        };
+        &layouts[usize::from(version - 1)]
+    }
+
+    /// Most bytes that fit in a code of the given version, from 1 to [MAX_VERSION]
+    pub fn capacity(self, version: u8) -> usize {
+        // Less the 4 bit mode indicator and 8 bit length
+        self.layout(version).data_codewords - 2
+    }
+
+    fn format_bits(self) -> u16 {
+        match self {
+            EccLevel::L => 0b01,
+            EccLevel::M => 0b00,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// More data than fits in a version 4 code at the requested error correction level
+    TooLong,
+}
+
+/// Square of modules, one bit per module
+#[derive(Clone)]
+struct Grid([u64; MAX_SIZE]);
+
+impl Grid {
+    fn get(&self, x: usize, y: usize) -> bool {
+        self.0[y] >> x & 1 != 0
+    }
+
+    fn set(&mut self, x: usize, y: usize, on: bool) {
+        if on {
+            self.0[y] |= 1 << x;
+        } else {
+            self.0[y] &= !(1 << x);
+        }
+    }
+}
+
+/// Encoded QR code
+#[derive(Clone)]
+pub struct QrCode {
+    version: u8,
+    ecc: EccLevel,
+    mask: u8,
+    /// Set for dark modules
+    modules: Grid,
+}
+
+impl QrCode {
+    /// Encode `data` in the smallest version that fits it
+    pub fn encode(data: &[u8], ecc: EccLevel) -> Result<Self, Error> {
+        let version = (1..=MAX_VERSION)
+            .find(|&version| data.len() <= ecc.capacity(version))
+            .ok_or(Error::TooLong)?;
+        let mut code = Self {
+            version,
+            ecc,
+            mask: 0,
+            modules: Grid([0; MAX_SIZE]),
+        };
+
+        let mut codewords = [0; MAX_CODEWORDS];
+        let layout = ecc.layout(version);
+        let total = add_error_correction(&mut codewords, &data_codewords(data, layout), layout);
+        let mut function = Grid([0; MAX_SIZE]);
+        code.draw_function_patterns(&mut function);
+        code.draw_codewords(&codewords[..total], &function);
+
+        // Pick the mask that makes the code easiest to read
+        let mut best = (u32::MAX, 0);
+        for mask in 0..8 {
+            code.apply_mask(mask, &function);
+            code.draw_format(mask, &mut function);
+            let penalty = code.penalty();
+            if penalty < best.0 {
+                best = (penalty, mask);
+            }
+            // Masking again undoes it
+            code.apply_mask(mask, &function);
+        }
+        code.mask = best.1;
+        code.apply_mask(code.mask, &function);
+        code.draw_format(code.mask, &mut function);
+        Ok(code)
+    }
+
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    pub fn ecc(&self) -> EccLevel {
+        self.ecc
+    }
+
+    /// Modules per side, not counting the quiet zone
+    pub fn size(&self) -> usize {
+        17 + 4 * usize::from(self.version)
+    }
+
+    /// Whether the module at column `x` and row `y` is dark
+    pub fn is_dark(&self, x: usize, y: usize) -> bool {
+        self.modules.get(x, y)
+    }
+
+    fn set_function(&mut self, function: &mut Grid, x: usize, y: usize, dark: bool) {
+        self.modules.set(x, y, dark);
+        function.set(x, y, true);
+    }
+
+    /// Finder, timing and alignment patterns. Also reserves room for the format bits.
+    fn draw_function_patterns(&mut self, function: &mut Grid) {
+        let size = self.size();
+        for i in 0..size {
+            self.set_function(function, 6, i, i % 2 == 0);
+            self.set_function(function, i, 6, i % 2 == 0);
+        }
+
+        // Finder patterns and the separators around them
+        for &(cx, cy) in &[(3, 3), (size - 4, 3), (3, size - 4)] {
+            for dy in -4i32..=4 {
+                for dx in -4i32..=4 {
+                    let (x, y) = (cx as i32 + dx, cy as i32 + dy);
+                    if (0..size as i32).contains(&x) && (0..size as i32).contains(&y) {
+                        let distance = dx.abs().max(dy.abs());
+                        self.set_function(
+                            function,
+                            x as usize,
+                            y as usize,
+                            distance != 2 && distance != 4,
+                        );
+                    }
+                }
+            }
+        }
+
+        // Versions 2 through 6 have a single alignment pattern near the bottom right corner
+        if self.version > 1 {
+            let center = size - 7;
+            for y in center - 2..=center + 2 {
+                for x in center - 2..=center + 2 {
+                    let distance = (x as i32 - center as i32)
+                        .abs()
+                        .max((y as i32 - center as i32).abs());
+                    self.set_function(function, x, y, distance != 1);
+                }
+            }
+        }
+
+        self.draw_format(0, function);
+    }
+
+    /// Error correction level and mask, twice over
+    fn draw_format(&mut self, mask: u8, function: &mut Grid) {
+        let data = self.ecc.format_bits() << 3 | u16::from(mask);
+        let mut remainder = data;
+        for _ in 0..10 {
+            remainder = (remainder << 1) ^ ((remainder >> 9) * 0x537);
+        }
+        let bits = (data << 10 | remainder) ^ 0x5412;
+        let bit = |i: usize| bits >> i & 1 != 0;
+
+        let size = self.size();
+        // Around the top left finder
+        for i in 0..6 {
+            self.set_function(function, 8, i, bit(i));
+        }
+        self.set_function(function, 8, 7, bit(6));
+        self.set_function(function, 8, 8, bit(7));
+        self.set_function(function, 7, 8, bit(8));
+        for i in 9..15 {
+            self.set_function(function, 14 - i, 8, bit(i));
+        }
+        // Split between the other two
+        for i in 0..8 {
+            self.set_function(function, size - 1 - i, 8, bit(i));
+        }
+        for i in 8..15 {
+            self.set_function(function, 8, size - 15 + i, bit(i));
+        }
+        // Always dark
+        self.set_function(function, 8, size - 8, true);
+    }
+
+    /// Fill everything that isn't a function pattern, zigzagging up and down two columns at a time
+    /// starting from the bottom right
+    fn draw_codewords(&mut self, codewords: &[u8], function: &Grid) {
+        let size = self.size();
+        let mut i = 0;
+        let mut right = size - 1;
+        loop {
+            // Skip over the vertical timing pattern
+            if right == 6 {
+                right = 5;
+            }
+            let upward = (right + 1) & 2 == 0;
+            for vertical in 0..size {
+                let y = if upward {
+                    size - 1 - vertical
+                } else {
+                    vertical
+                };
+                for x in [right, right - 1].iter().copied() {
+                    if !function.get(x, y) && i < codewords.len() * 8 {
+                        self.modules
+                            .set(x, y, codewords[i / 8] >> (7 - i % 8) & 1 != 0);
+                        i += 1;
+                    }
+                }
+            }
+            if right < 2 {
+                break;
+            }
+            right -= 2;
+        }
+    }
+
+    fn apply_mask(&mut self, mask: u8, function: &Grid) {
+        let size = self.size();
+        for y in 0..size {
+            for x in 0..size {
+                let flip = match mask {
+                    0 => (x + y) % 2 == 0,
+                    1 => y % 2 == 0,
+                    2 => x % 3 == 0,
+                    3 => (x + y) % 3 == 0,
+                    4 => (x / 3 + y / 2) % 2 == 0,
+                    5 => x * y % 2 + x * y % 3 == 0,
+                    6 => (x * y % 2 + x * y % 3) % 2 == 0,
+                    _ => ((x + y) % 2 + x * y % 3) % 2 == 0,
+                };
+                if flip && !function.get(x, y) {
+                    self.modules.set(x, y, !self.modules.get(x, y));
+                }
+            }
+        }
+    }
+
+    /// Lower is better. Penalizes long runs, blocks, things that look like finder patterns and an
+    /// uneven balance of dark and light.
+    fn penalty(&self) -> u32 {
+        let size = self.size();
+        let mut penalty = 0;
+        for i in 0..size {
+            penalty += line_penalty(|j| self.is_dark(j, i), size);
+            penalty += line_penalty(|j| self.is_dark(i, j), size);
+        }
+        for y in 0..size - 1 {
+            for x in 0..size - 1 {
+                let dark = self.is_dark(x, y);
+                if dark == self.is_dark(x + 1, y)
+                    && dark == self.is_dark(x, y + 1)
+                    && dark == self.is_dark(x + 1, y + 1)
+                {
+                    penalty += 3;
+                }
+            }
+        }
+        let dark: u32 = self.modules.0.iter().map(|row| row.count_ones()).sum();
+        let total = (size * size) as u32;
+        penalty + (dark * 100 / total).abs_diff(50) / 5 * 10
+    }
+}
+
+fn line_penalty(dark: impl Fn(usize) -> bool, size: usize) -> u32 {
+    const FINDER_LIKE: [bool; 11] = [
+        true, false, true, true, true, false, true, false, false, false, false,
+    ];
+    let mut penalty = 0;
+    let mut run = 1;
+    for j in 1..=size {
+        if j < size && dark(j) == dark(j - 1) {
+            run += 1;
+        } else {
+            if run >= 5 {
+                penalty += run - 2;
+            }
+            run = 1;
+        }
+    }
+    for start in 0..=size - FINDER_LIKE.len() {
+        let matches = |reversed: bool| {
+            FINDER_LIKE.iter().enumerate().all(|(k, &expected)| {
+                let k = if reversed {
+                    FINDER_LIKE.len() - 1 - k
+                } else {
+                    k
+                };
+                dark(start + k) == expected
+            })
+        };
+        if matches(false) || matches(true) {
+            penalty += 40;
+        }
+    }
+    penalty
+}
+
+/// Mode indicator, length, data and padding
+fn data_codewords(data: &[u8], layout: &Layout) -> [u8; MAX_CODEWORDS] {
+    let mut codewords = [0; MAX_CODEWORDS];
+    let mut len = 0;
+    let mut push = |value: u32, bits: usize| {
+        for i in (0..bits).rev() {
+            codewords[len / 8] |= ((value >> i & 1) as u8) << (7 - len % 8);
+            len += 1;
+        }
+    };
+    // Byte mode
+    push(0b0100, 4);
+    push(data.len() as u32, 8);
+    for &byte in data {
+        push(byte.into(), 8);
+    }
+    // Up to four zero bits to terminate, then zeroes up to a whole byte. The buffer is already
+    // zeroed, so just skip ahead.
+    let capacity = layout.data_codewords * 8;
+    len = (len + 4).min(capacity);
+    len = len.div_ceil(8) * 8;
+    for (i, codeword) in codewords[len / 8..layout.data_codewords]
+        .iter_mut()
+        .enumerate()
+    {
+        *codeword = if i % 2 == 0 { 0xEC } else { 0x11 };
+    }
+    codewords
+}
+
+/// Split the data into blocks, add error correction to each and interleave them into `codewords`.
+/// Returns the number of codewords.
+fn add_error_correction(
+    codewords: &mut [u8; MAX_CODEWORDS],
+    data: &[u8; MAX_CODEWORDS],
+    layout: &Layout,
+) -> usize {
+    // Blocks are all the same size in versions 1 through 4
+    let block_len = layout.data_codewords / layout.blocks;
+    let ecc_len = layout.ecc_codewords_per_block;
+    let divisor = reed_solomon_divisor(ecc_len);
+    for block in 0..layout.blocks {
+        let block_data = &data[block * block_len..(block + 1) * block_len];
+        for (i, &codeword) in block_data.iter().enumerate() {
+            codewords[i * layout.blocks + block] = codeword;
+        }
+        let ecc = reed_solomon_remainder(block_data, &divisor, ecc_len);
+        for (i, &codeword) in ecc[..ecc_len].iter().enumerate() {
+            codewords[layout.data_codewords + i * layout.blocks + block] = codeword;
+        }
+    }
+    layout.data_codewords + ecc_len * layout.blocks
+}
+
+/// Multiply in GF(2^8) modulo x^8 + x^4 + x^3 + x^2 + 1
+fn gf_mul(a: u8, b: u8) -> u8 {
+    let mut product: u8 = 0;
+    for i in (0..8).rev() {
+        product = (product << 1) ^ ((product >> 7) * 0x1D);
+        product ^= (b >> i & 1) * a;
+    }
+    product
+}
+
+/// Generator polynomial of the given degree, highest power first, without the leading 1
+fn reed_solomon_divisor(degree: usize) -> [u8; MAX_ECC_CODEWORDS] {
+    let mut divisor = [0; MAX_ECC_CODEWORDS];
+    divisor[degree - 1] = 1;
+    let mut root = 1;
+    for _ in 0..degree {
+        for j in 0..degree {
+            divisor[j] = gf_mul(divisor[j], root);
+            if j + 1 < degree {
+                divisor[j] ^= divisor[j + 1];
+            }
+        }
+        root = gf_mul(root, 2);
+    }
+    divisor
+}
+
+fn reed_solomon_remainder(
+    data: &[u8],
+    divisor: &[u8; MAX_ECC_CODEWORDS],
+    degree: usize,
+) -> [u8; MAX_ECC_CODEWORDS] {
+    let mut remainder = [0; MAX_ECC_CODEWORDS];
+    for &byte in data {
+        let factor = byte ^ remainder[0];
+        remainder.copy_within(1..degree, 0);
+        remainder[degree - 1] = 0;
+        for (r, &d) in remainder[..degree].iter_mut().zip(divisor) {
+            *r ^= gf_mul(d, factor);
+        }
+    }
+    remainder
+}
+
+impl View for QrCode {
+    /// Centered in `area` at the largest whole number of pixels per module that leaves room for the
+    /// quiet zone. The whole area is lit up to serve as the quiet zone. If even one pixel per module
+    /// doesn't fit, that's all that gets drawn.
+    fn draw<D: DrawTarget<BinaryColor>>(
+        &self,
+        area: Rectangle,
+        target: &mut D,
+    ) -> Result<(), D::Error> {
+        let size = self.size() as i32;
+        let (width, height) = (ui::width(&area), ui::height(&area));
+        let scale = width.min(height) / (size + 2 * QUIET_ZONE);
+        ui::fill(area, BinaryColor::On, target)?;
+        if scale == 0 {
+            return Ok(());
+        }
+
+        let origin =
+            area.top_left + Point::new((width - size * scale) / 2, (height - size * scale) / 2);
+        // One rectangle per horizontal run of dark modules
+        for y in 0..size {
+            let mut x = 0;
+            while x < size {
+                if !self.is_dark(x as usize, y as usize) {
+                    x += 1;
+                    continue;
+                }
+                let start = x;
+                while x < size && self.is_dark(x as usize, y as usize) {
+                    x += 1;
+                }
+                let top_left = origin + Point::new(start * scale, y * scale);
+                let bottom_right = origin + Point::new(x * scale - 1, (y + 1) * scale - 1);
+                ui::fill(
+                    Rectangle::new(top_left, bottom_right),
+                    BinaryColor::Off,
+                    target,
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,95 @@
+//! Stack usage measurement via stack painting.
+//!
+//! The stack grows down from `_stack_start` (the top of RAM) towards the end of the static data,
+//! which cortex-m-rt marks with `__sheap` (right after `.bss` and `.uninit`). [paint] fills the
+//! unused part of that region with a known pattern early on. [usage] later looks for the lowest
+//! word that no longer holds the pattern, which gives the high-water mark of the stack.
+//!
+//! Interrupt handlers run on the same (main) stack, so their usage is included.
+use core::{fmt, ptr};
+
+/// Unlikely to be a common value on the stack
+const PAINT: u32 = 0xACAC_ACAC;
+
+extern "C" {
+    static mut __sheap: u32;
+    static _stack_start: u32;
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct StackUsage {
+    /// Deepest the stack has been, in bytes
+    pub used: usize,
+    /// Space available for the stack, in bytes
+    pub size: usize,
+}
+
+impl StackUsage {
+    pub fn percent(&self) -> usize {
+        self.used * 100 / self.size
+    }
+}
+
+impl fmt::Display for StackUsage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "stack: {}/{} bytes ({}%)",
+            self.used,
+            self.size,
+            self.percent()
+        )
+    }
+}
+
+fn stack_bottom() -> *mut u32 {
+    ptr::addr_of_mut!(__sheap)
+}
+
+fn stack_top() -> *const u32 {
+    ptr::addr_of!(_stack_start)
+}
+
+/// Fill the unused part of the stack with the paint pattern. Call this once, as early as possible,
+/// e.g. at the top of `main` or RTIC's `init`.
+#[inline(never)]
+pub fn paint() {
+    let sp = stack_pointer();
+    let mut addr = stack_bottom();
+    // Everything below the stack pointer is free. There's no red zone on ARM.
+    while (addr as usize) < sp {
+        unsafe { ptr::write_volatile(addr, PAINT) };
+        addr = addr.wrapping_add(1);
+    }
+}
+
+/// High-water mark of the stack since [paint] was called
+pub fn usage() -> StackUsage {
+    let bottom = stack_bottom();
+    let top = stack_top() as usize;
+    let mut addr = bottom;
+    while (addr as usize) < top && unsafe { ptr::read_volatile(addr) } == PAINT {
+        addr = addr.wrapping_add(1);
+    }
+    StackUsage {
+        used: top - addr as usize,
+        size: top - bottom as usize,
+    }
+}
+
+#[inline(always)]
+fn stack_pointer() -> usize {
+    #[cfg(target_arch = "arm")]
+    {
+        let sp: usize;
+        unsafe {
+            core::arch::asm!("mov {}, sp", out(reg) sp, options(nomem, nostack, preserves_flags));
+        }
+        sp
+    }
+    #[cfg(not(target_arch = "arm"))]
+    {
+        // Only meaningful on the target. Paint nothing.
+        stack_bottom() as usize
+    }
+}
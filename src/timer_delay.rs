@@ -0,0 +1,270 @@
+//! Delays that sleep instead of spinning, on any general-purpose timer, plus [Timeout] for giving
+//! up on things that take too long.
+//!
+//! [TimerDelay] implements embedded-hal's `DelayMs` and `DelayUs`. While a delay is running, the
+//! timer counts microseconds and interrupts every [TICK_US]. The CPU sleeps with `WFI` between
+//! ticks and only spins for whatever's left under a tick at the end. There's no critical section,
+//! so other interrupts are handled as usual the whole time. Without one, an interrupt can slip in
+//! between checking the time and going to sleep, in which case the delay runs over by up to a
+//! tick.
+//!
+//! Unmask the timer's interrupt ([DelayTimer::INTERRUPT]) in the NVIC and call [interrupt] from
+//! its handler. Don't delay from a context that blocks that interrupt, or the CPU never wakes up.
+//!
+//! ```ignore
+//! let mut delay = TimerDelay::new(board.spare.TIM3, board.clocks);
+//! unsafe { NVIC::unmask(TIM3::INTERRUPT) };
+//! delay.delay_ms(500_u32);
+//!
+//! #[interrupt]
+//! fn TIM3() {
+//!     timer_delay::interrupt::<TIM3>();
+//! }
+//! ```
+use crate::board;
+use core::sync::atomic::{AtomicU32, Ordering};
+use embedded_hal::{
+    blocking::delay::{DelayMs, DelayUs},
+    timer::CountDown,
+};
+use stm32f4xx_hal::{
+    rcc::Clocks,
+    stm32::{self, Interrupt, RCC},
+};
+
+/// Time between ticks while a delay is running
+pub const TICK_US: u32 = 1000;
+
+/// General-purpose timers a [TimerDelay] can run on
+pub trait DelayTimer {
+    /// Its interrupt vector
+    const INTERRUPT: Interrupt;
+
+    /// Power it up and get it ready to count microseconds, wrapping around every [TICK_US]
+    fn setup(&self, clocks: Clocks);
+    /// Start counting from 0
+    fn start(&self);
+    fn stop(&self);
+    /// Microseconds since the last tick
+    fn count(&self) -> u32;
+    /// Ticks since [DelayTimer::start]
+    fn ticks() -> &'static AtomicU32;
+    /// Acknowledge a tick. Only touches the status register, so it's fine from the interrupt
+    /// handler while a [TimerDelay] owns the timer.
+    fn clear_tick();
+}
+
+macro_rules! delay_timers {
+    ($($TIM:ident: (
+        $enr:ident, $en:ident, $rstr:ident, $rst:ident, $timer_hz:path, $interrupt:ident
+    ),)+) => {
+        $(
+            impl DelayTimer for stm32::$TIM {
+                const INTERRUPT: Interrupt = Interrupt::$interrupt;
+
+                fn setup(&self, clocks: Clocks) {
+                    let rcc = unsafe { &*RCC::ptr() };
+                    rcc.$enr.modify(|_, w| w.$en().set_bit());
+                    rcc.$rstr.modify(|_, w| w.$rst().set_bit());
+                    rcc.$rstr.modify(|_, w| w.$rst().clear_bit());
+
+                    // Any value that fits is a valid prescaler and reload value
+                    unsafe {
+                        self.psc.write(|w| w.bits($timer_hz(clocks) / 1_000_000 - 1));
+                        self.arr.write(|w| w.bits(TICK_US - 1));
+                    }
+                    // Only wrapping around counts as a tick, not loading the prescaler
+                    self.cr1.modify(|_, w| w.urs().set_bit());
+                    self.egr.write(|w| w.ug().set_bit());
+                    self.sr.write(|w| unsafe { w.bits(0) });
+                    self.dier.write(|w| w.uie().set_bit());
+                }
+
+                fn start(&self) {
+                    Self::ticks().store(0, Ordering::Relaxed);
+                    self.cnt.write(|w| unsafe { w.bits(0) });
+                    self.cr1.modify(|_, w| w.cen().set_bit());
+                }
+
+                fn stop(&self) {
+                    self.cr1.modify(|_, w| w.cen().clear_bit());
+                }
+
+                fn count(&self) -> u32 {
+                    self.cnt.read().bits()
+                }
+
+                fn ticks() -> &'static AtomicU32 {
+                    static TICKS: AtomicU32 = AtomicU32::new(0);
+                    &TICKS
+                }
+
+                fn clear_tick() {
+                    let tim = unsafe { &*stm32::$TIM::ptr() };
+                    tim.sr.write(|w| unsafe { w.bits(0) });
+                }
+            }
+        )+
+    };
+}
+
+delay_timers! {
+    TIM2: (apb1enr, tim2en, apb1rstr, tim2rst, board::apb1_timer_hz, TIM2),
+    TIM3: (apb1enr, tim3en, apb1rstr, tim3rst, board::apb1_timer_hz, TIM3),
+    TIM4: (apb1enr, tim4en, apb1rstr, tim4rst, board::apb1_timer_hz, TIM4),
+    TIM5: (apb1enr, tim5en, apb1rstr, tim5rst, board::apb1_timer_hz, TIM5),
+    TIM9: (apb2enr, tim9en, apb2rstr, tim9rst, board::apb2_timer_hz, TIM1_BRK_TIM9),
+    TIM10: (apb2enr, tim10en, apb2rstr, tim10rst, board::apb2_timer_hz, TIM1_UP_TIM10),
+    TIM11: (apb2enr, tim11en, apb2rstr, tim11rst, board::apb2_timer_hz, TIM1_TRG_COM_TIM11),
+}
+
+/// Call this from the interrupt handler of the timer a [TimerDelay] runs on
+pub fn interrupt<TIM: DelayTimer>() {
+    TIM::clear_tick();
+    TIM::ticks().fetch_add(1, Ordering::Relaxed);
+}
+
+/// Sleepy delays on one of the general-purpose timers
+pub struct TimerDelay<TIM> {
+    tim: TIM,
+}
+
+impl<TIM: DelayTimer> TimerDelay<TIM> {
+    /// Take over `tim`. Unmask its interrupt in the NVIC afterwards.
+    pub fn new(tim: TIM, clocks: Clocks) -> Self {
+        tim.setup(clocks);
+        Self { tim }
+    }
+
+    /// Microseconds since the delay started
+    fn elapsed(&self) -> u64 {
+        loop {
+            let ticks = TIM::ticks().load(Ordering::Relaxed);
+            let count = self.tim.count();
+            // Try again if a tick went by in between
+            if TIM::ticks().load(Ordering::Relaxed) == ticks {
+                return u64::from(ticks) * u64::from(TICK_US) + u64::from(count);
+            }
+        }
+    }
+
+    fn delay(&mut self, us: u64) {
+        self.tim.start();
+        loop {
+            let elapsed = self.elapsed();
+            if elapsed >= us {
+                break;
+            }
+            // Sleep through whole ticks, but spin for the last bit
+            if us - elapsed > u64::from(TICK_US) {
+                cortex_m::asm::wfi();
+            }
+        }
+        self.tim.stop();
+    }
+
+    /// Hand the timer back
+    pub fn free(self) -> TIM {
+        self.tim.stop();
+        self.tim
+    }
+}
+
+impl<TIM: DelayTimer> DelayUs<u32> for TimerDelay<TIM> {
+    fn delay_us(&mut self, us: u32) {
+        self.delay(us.into());
+    }
+}
+
+impl<TIM: DelayTimer> DelayUs<u16> for TimerDelay<TIM> {
+    fn delay_us(&mut self, us: u16) {
+        self.delay(us.into());
+    }
+}
+
+impl<TIM: DelayTimer> DelayUs<u8> for TimerDelay<TIM> {
+    fn delay_us(&mut self, us: u8) {
+        self.delay(us.into());
+    }
+}
+
+impl<TIM: DelayTimer> DelayMs<u32> for TimerDelay<TIM> {
+    fn delay_ms(&mut self, ms: u32) {
+        self.delay(u64::from(ms) * 1000);
+    }
+}
+
+impl<TIM: DelayTimer> DelayMs<u16> for TimerDelay<TIM> {
+    fn delay_ms(&mut self, ms: u16) {
+        self.delay(u64::from(ms) * 1000);
+    }
+}
+
+impl<TIM: DelayTimer> DelayMs<u8> for TimerDelay<TIM> {
+    fn delay_ms(&mut self, ms: u8) {
+        self.delay(u64::from(ms) * 1000);
+    }
+}
+
+/// Why [Timeout::poll] gave up
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimeoutError<E> {
+    TimedOut,
+    /// What was being waited for failed
+    Other(E),
+}
+
+/// A deadline on any `CountDown` timer, e.g. one of the HAL's `Timer`s. Nothing blocks: check
+/// [Timeout::is_expired] now and then, or wrap a non-blocking operation with [Timeout::poll].
+///
+/// ```ignore
+/// let mut timeout = Timeout::start(Timer::tim3(tim, 1.hz(), clocks), 4.hz());
+/// // A byte, or TimedOut after 250ms
+/// let byte = nb::block!(timeout.poll(|| serial.read()));
+/// ```
+pub struct Timeout<C> {
+    timer: C,
+    expired: bool,
+}
+
+impl<C: CountDown> Timeout<C> {
+    /// Start `timer` counting down from `timeout`. Whatever the timer had already flagged is
+    /// thrown away, since the HAL's timers keep running from their constructor and restarting one
+    /// doesn't clear its update flag.
+    pub fn start<T: Into<C::Time>>(mut timer: C, timeout: T) -> Self {
+        timer.start(timeout);
+        timer.wait().ok();
+        Self {
+            timer,
+            expired: false,
+        }
+    }
+
+    /// Stays true once the time is up, even if the timer keeps going
+    pub fn is_expired(&mut self) -> bool {
+        if !self.expired {
+            self.expired = self.timer.wait().is_ok();
+        }
+        self.expired
+    }
+
+    /// Try `operation` once. `WouldBlock` until it either finishes or the time is up.
+    pub fn poll<T, E>(
+        &mut self,
+        operation: impl FnOnce() -> nb::Result<T, E>,
+    ) -> nb::Result<T, TimeoutError<E>> {
+        match operation() {
+            Ok(value) => Ok(value),
+            Err(nb::Error::Other(e)) => Err(nb::Error::Other(TimeoutError::Other(e))),
+            Err(nb::Error::WouldBlock) if self.is_expired() => {
+                Err(nb::Error::Other(TimeoutError::TimedOut))
+            }
+            Err(nb::Error::WouldBlock) => Err(nb::Error::WouldBlock),
+        }
+    }
+
+    /// Hand the timer back
+    pub fn free(self) -> C {
+        self.timer
+    }
+}
@@ -0,0 +1,284 @@
+//! Lots of software timers sharing TIM2.
+//!
+//! TIM2 counts microseconds from 0 to `u32::MAX` and wraps around, roughly every 71 minutes.
+//! Timers wait in a [TimerQueue] sorted by deadline, and the CC1 compare interrupt is always set
+//! for whichever comes first, so nothing runs in between. Deadlines are compared relative to each
+//! other rather than as plain numbers, so wraparound doesn't upset the order, but it does mean no
+//! timer can be more than [MAX_DELAY_US] away.
+//!
+//! Call [start], unmask TIM2 in the NVIC and call [interrupt] from its handler. Then set up timers
+//! with [after] and [every] from anywhere.
+//!
+//! ```ignore
+//! timer_service::start(board.spare.TIM2, board.clocks);
+//! unsafe { NVIC::unmask(Interrupt::TIM2) };
+//! timer_service::every(500_000, toggle_led)?;
+//!
+//! #[interrupt]
+//! fn TIM2() {
+//!     timer_service::interrupt();
+//! }
+//! ```
+use crate::board::FreeRunningTimer;
+use core::cell::RefCell;
+use cortex_m::{interrupt::Mutex, peripheral::NVIC};
+use heapless::{consts::U16, Vec};
+use stm32f4xx_hal::{
+    rcc::Clocks,
+    stm32::{Interrupt, TIM2},
+};
+
+/// How fast TIM2 counts
+pub const TICK_HZ: u32 = 1_000_000;
+/// How many timers can be waiting at once
+pub const CAPACITY: usize = 16;
+/// Longest delay or period. Half the counter's range, so that deadlines can always be told apart.
+pub const MAX_DELAY_US: u32 = i32::MAX as u32;
+
+/// Called from interrupt context
+pub type Callback = fn();
+
+/// Refers to a timer for [cancel]. Ids are handed out in order and only repeat after 2^32 of them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimerId(u32);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// [CAPACITY] timers are already waiting
+    Full,
+    /// Longer than [MAX_DELAY_US]
+    TooLong,
+    /// [start] hasn't been called
+    NotStarted,
+}
+
+#[derive(Clone, Copy)]
+struct Timer {
+    id: TimerId,
+    deadline: u32,
+    period: Option<u32>,
+    callback: Callback,
+}
+
+/// Whether `deadline` has come by `now`, allowing for the counter wrapping around in between
+pub fn is_due(deadline: u32, now: u32) -> bool {
+    now.wrapping_sub(deadline) as i32 >= 0
+}
+
+/// Timers sorted by deadline, in ticks of whatever clock the caller goes by
+pub struct TimerQueue {
+    /// Soonest first
+    timers: Vec<Timer, U16>,
+    next_id: u32,
+}
+
+impl Default for TimerQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TimerQueue {
+    pub fn new() -> Self {
+        Self {
+            timers: Vec::new(),
+            next_id: 0,
+        }
+    }
+
+    /// Call `callback` once `delay` ticks after `now`, then every `period` ticks after that if
+    /// there is one. A period of 0 counts as none.
+    pub fn schedule(
+        &mut self,
+        now: u32,
+        delay: u32,
+        period: Option<u32>,
+        callback: Callback,
+    ) -> Result<TimerId, Error> {
+        if delay > MAX_DELAY_US || period.is_some_and(|period| period > MAX_DELAY_US) {
+            return Err(Error::TooLong);
+        }
+        if self.timers.len() == CAPACITY {
+            return Err(Error::Full);
+        }
+        let id = TimerId(self.next_id);
+        self.next_id = self.next_id.wrapping_add(1);
+        self.insert(Timer {
+            id,
+            deadline: now.wrapping_add(delay),
+            period: period.filter(|&period| period > 0),
+            callback,
+        });
+        Ok(id)
+    }
+
+    /// Keeps the queue sorted. Timers with the same deadline go off in the order they were added.
+    fn insert(&mut self, timer: Timer) {
+        // Every deadline is within MAX_DELAY_US of the others, so their differences sort them
+        let index = self
+            .timers
+            .iter()
+            .position(|other| (timer.deadline.wrapping_sub(other.deadline) as i32) < 0)
+            .unwrap_or(self.timers.len());
+        if self.timers.push(timer).is_ok() {
+            self.timers[index..].rotate_right(1);
+        }
+    }
+
+    /// Returns false if there's no such timer, e.g. because it was a one-shot that already went off
+    pub fn cancel(&mut self, id: TimerId) -> bool {
+        match self.timers.iter().position(|timer| timer.id == id) {
+            Some(index) => {
+                self.timers[index..].rotate_left(1);
+                self.timers.pop();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// When the soonest timer goes off
+    pub fn next_deadline(&self) -> Option<u32> {
+        self.timers.first().map(|timer| timer.deadline)
+    }
+
+    /// Take out the soonest timer if it's due by `now`, putting it back for its next go if it's
+    /// periodic. Periodic timers keep to their schedule, so one that's fallen behind is due again
+    /// straight away.
+    pub fn pop_due(&mut self, now: u32) -> Option<(TimerId, Callback)> {
+        let timer = *self.timers.first()?;
+        if !is_due(timer.deadline, now) {
+            return None;
+        }
+        self.cancel(timer.id);
+        if let Some(period) = timer.period {
+            self.insert(Timer {
+                deadline: timer.deadline.wrapping_add(period),
+                ..timer
+            });
+        }
+        Some((timer.id, timer.callback))
+    }
+
+    pub fn len(&self) -> usize {
+        self.timers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.timers.is_empty()
+    }
+}
+
+struct Service {
+    tim: TIM2,
+    queue: TimerQueue,
+}
+
+impl Service {
+    fn now(&self) -> u32 {
+        self.tim.cnt.read().bits()
+    }
+
+    /// Point the compare interrupt at the soonest deadline
+    fn arm(&mut self) {
+        match self.queue.next_deadline() {
+            Some(deadline) => {
+                // Any 32-bit value is a valid compare value
+                unsafe { self.tim.ccr1.write(|w| w.bits(deadline)) };
+                self.tim.sr.modify(|_, w| w.cc1if().clear_bit());
+                self.tim.dier.modify(|_, w| w.cc1ie().set_bit());
+                // The compare only fires as the counter reaches the deadline, which it may already
+                // have gone past
+                if is_due(deadline, self.now()) {
+                    NVIC::pend(Interrupt::TIM2);
+                }
+            }
+            None => self.tim.dier.modify(|_, w| w.cc1ie().clear_bit()),
+        }
+    }
+}
+
+static SERVICE: Mutex<RefCell<Option<Service>>> = Mutex::new(RefCell::new(None));
+
+/// Take over TIM2 and start counting from 0. Unmask TIM2 in the NVIC afterwards.
+pub fn start(tim: TIM2, clocks: Clocks) {
+    tim.setup_free_running(clocks, TICK_HZ);
+    tim.cr1.modify(|_, w| w.cen().set_bit());
+
+    cortex_m::interrupt::free(|cs| {
+        SERVICE.borrow(cs).replace(Some(Service {
+            tim,
+            queue: TimerQueue::new(),
+        }))
+    });
+}
+
+/// Microseconds since [start], wrapping around. 0 before then.
+pub fn now() -> u32 {
+    cortex_m::interrupt::free(|cs| {
+        SERVICE
+            .borrow(cs)
+            .borrow()
+            .as_ref()
+            .map_or(0, |service| service.now())
+    })
+}
+
+fn schedule(delay_us: u32, period_us: Option<u32>, callback: Callback) -> Result<TimerId, Error> {
+    cortex_m::interrupt::free(|cs| {
+        let mut service = SERVICE.borrow(cs).borrow_mut();
+        let service = service.as_mut().ok_or(Error::NotStarted)?;
+        let now = service.now();
+        let id = service.queue.schedule(now, delay_us, period_us, callback)?;
+        service.arm();
+        Ok(id)
+    })
+}
+
+/// Call `callback` once, `delay_us` from now
+pub fn after(delay_us: u32, callback: Callback) -> Result<TimerId, Error> {
+    schedule(delay_us, None, callback)
+}
+
+/// Call `callback` every `period_us`, starting `period_us` from now
+pub fn every(period_us: u32, callback: Callback) -> Result<TimerId, Error> {
+    schedule(period_us, Some(period_us), callback)
+}
+
+/// Stop a timer. Returns false if it had already gone off for good or been cancelled.
+pub fn cancel(id: TimerId) -> bool {
+    cortex_m::interrupt::free(|cs| {
+        let mut service = SERVICE.borrow(cs).borrow_mut();
+        match service.as_mut() {
+            Some(service) => {
+                let cancelled = service.queue.cancel(id);
+                service.arm();
+                cancelled
+            }
+            None => false,
+        }
+    })
+}
+
+/// Call this from the TIM2 interrupt handler. Callbacks run with interrupts enabled, so they can
+/// set up and cancel timers.
+pub fn interrupt() {
+    let mut due: Vec<Callback, U16> = Vec::new();
+    cortex_m::interrupt::free(|cs| {
+        if let Some(service) = SERVICE.borrow(cs).borrow_mut().as_mut() {
+            let now = service.now();
+            // A periodic timer that's far behind could be due over and over, so stop once the
+            // batch is full and let the pended interrupt pick up the rest
+            while due.len() < due.capacity() {
+                match service.queue.pop_due(now) {
+                    Some((_, callback)) => due.push(callback).ok(),
+                    None => break,
+                };
+            }
+            service.arm();
+        }
+    });
+    for callback in &due {
+        callback();
+    }
+}
@@ -1,7 +1,7 @@
 //! Janky interrupt-based serial driver. It can transmit bytes via [write_byte] or [write].
 //! Otherwise it echos any received bytes back to the sender
 //!
-use core::iter::IntoIterator;
+use core::{fmt, iter::IntoIterator};
 use heapless::{consts::U32, spsc::SingleCore};
 use stm32f4xx_hal as hal;
 use stm32f4xx_hal::{
@@ -57,9 +57,32 @@ pub fn write<T: IntoIterator<Item = u8>>(ctx: &mut UartContext, bytes: T) {
     }
 }
 
+/// Like [write_byte], but instead of dropping data when the TX queue is full, busy-wait and push
+/// queued bytes out by hand. Safe to call from within a critical section, where the USART2
+/// interrupt can't drain the queue for us.
+pub fn write_byte_blocking(ctx: &mut UartContext, byte: u8) {
+    while ctx.tx_queue.len() == ctx.tx_queue.capacity() {
+        if ctx.handle.is_txe() {
+            let next_byte = ctx.tx_queue.dequeue().unwrap();
+            ctx.handle.write(next_byte).ok();
+        }
+    }
+    write_byte(ctx, byte);
+}
+
+/// Lets `write!` and friends be used with the driver. Output is never dropped.
+impl fmt::Write for UartContext {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for byte in s.bytes() {
+            write_byte_blocking(self, byte);
+        }
+        Ok(())
+    }
+}
+
 pub fn interrupt(ctx: &mut UartContext) {
     if ctx.handle.is_rxne() {
-        if let Some(rx_byte) = ctx.handle.read().ok() {
+        if let Ok(rx_byte) = ctx.handle.read() {
             ctx.tx_queue.enqueue(rx_byte).ok();
             // Drop oldest data if the queue is full
             if ctx.rx_queue.len() == ctx.rx_queue.capacity() {
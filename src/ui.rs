@@ -0,0 +1,462 @@
+//! Tiny widget toolkit for on-device configuration screens, navigated with a single button: a short
+//! press moves on to the next choice and a long press selects it. [Event::from_button] picks those
+//! out of the [ButtonEvent]s from [crate::button].
+//!
+//! Everything draws to any `DrawTarget<BinaryColor>` in the 6x8 font, so screens can be checked on
+//! the host against an in-memory target. Widgets are laid out by handing them a [Rectangle] to draw
+//! in. [Screen] puts a [StatusBar] at the top, the current widget below it and an optional [Modal]
+//! on top of everything.
+use crate::button::ButtonEvent;
+use core::fmt::Write;
+use embedded_graphics::{
+    fonts::{Font6x8, Text},
+    pixelcolor::BinaryColor,
+    prelude::*,
+    primitives::Rectangle,
+    style::{PrimitiveStyle, PrimitiveStyleBuilder, TextStyle, TextStyleBuilder},
+};
+use heapless::{consts::U16, String};
+
+pub(crate) const CHAR_WIDTH: i32 = 6;
+pub(crate) const LINE_HEIGHT: i32 = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Event {
+    /// Short press
+    Next,
+    /// Long press
+    Select,
+}
+
+impl Event {
+    /// Clicks move on and long presses select. Presses, releases and double-clicks don't mean
+    /// anything here.
+    pub fn from_button(event: ButtonEvent) -> Option<Self> {
+        match event {
+            ButtonEvent::Click => Some(Event::Next),
+            ButtonEvent::LongPress => Some(Event::Select),
+            ButtonEvent::Press | ButtonEvent::Release | ButtonEvent::DoubleClick => None,
+        }
+    }
+}
+
+/// Something that can be drawn in a given area
+pub trait View {
+    fn draw<D: DrawTarget<BinaryColor>>(
+        &self,
+        area: Rectangle,
+        target: &mut D,
+    ) -> Result<(), D::Error>;
+}
+
+/// A view that reacts to button events
+pub trait Widget: View {
+    /// What the widget reports once the user is done with it
+    type Output;
+
+    fn handle(&mut self, event: Event) -> Option<Self::Output>;
+}
+
+pub(crate) fn width(area: &Rectangle) -> i32 {
+    area.bottom_right.x - area.top_left.x + 1
+}
+
+pub(crate) fn height(area: &Rectangle) -> i32 {
+    area.bottom_right.y - area.top_left.y + 1
+}
+
+/// As much of `text` as fits in `max_width` pixels
+pub(crate) fn fit(text: &str, max_width: i32) -> &str {
+    let max_chars = (max_width / CHAR_WIDTH).max(0) as usize;
+    match text.char_indices().nth(max_chars) {
+        Some((end, _)) => &text[..end],
+        None => text,
+    }
+}
+
+pub(crate) fn text_width(text: &str) -> i32 {
+    text.chars().count() as i32 * CHAR_WIDTH
+}
+
+pub(crate) fn draw_text<D: DrawTarget<BinaryColor>>(
+    text: &str,
+    position: Point,
+    max_width: i32,
+    inverted: bool,
+    target: &mut D,
+) -> Result<(), D::Error> {
+    let style = if inverted {
+        TextStyleBuilder::new(Font6x8)
+            .text_color(BinaryColor::Off)
+            .background_color(BinaryColor::On)
+            .build()
+    } else {
+        TextStyle::new(Font6x8, BinaryColor::On)
+    };
+    Text::new(fit(text, max_width), position)
+        .into_styled(style)
+        .draw(target)
+}
+
+/// Text centered horizontally in `area`, with its top at `y`
+fn draw_centered<D: DrawTarget<BinaryColor>>(
+    text: &str,
+    area: &Rectangle,
+    y: i32,
+    target: &mut D,
+) -> Result<(), D::Error> {
+    let text = fit(text, width(area));
+    let x = area.top_left.x + (width(area) - text_width(text)) / 2;
+    draw_text(text, Point::new(x, y), width(area), false, target)
+}
+
+pub(crate) fn fill<D: DrawTarget<BinaryColor>>(
+    area: Rectangle,
+    color: BinaryColor,
+    target: &mut D,
+) -> Result<(), D::Error> {
+    area.into_styled(PrimitiveStyle::with_fill(color))
+        .draw(target)
+}
+
+/// Scrolling list of choices. The selected one is drawn inverted.
+pub struct Menu<'a> {
+    items: &'a [&'a str],
+    selected: usize,
+}
+
+impl<'a> Menu<'a> {
+    pub fn new(items: &'a [&'a str]) -> Self {
+        Self { items, selected: 0 }
+    }
+
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+}
+
+impl View for Menu<'_> {
+    fn draw<D: DrawTarget<BinaryColor>>(
+        &self,
+        area: Rectangle,
+        target: &mut D,
+    ) -> Result<(), D::Error> {
+        let rows = (height(&area) / LINE_HEIGHT).max(1) as usize;
+        // Scroll just enough to keep the selection on screen
+        let top = (self.selected + 1).saturating_sub(rows);
+
+        for (row, (index, item)) in self
+            .items
+            .iter()
+            .enumerate()
+            .skip(top)
+            .take(rows)
+            .enumerate()
+        {
+            let y = area.top_left.y + row as i32 * LINE_HEIGHT;
+            let selected = index == self.selected;
+            if selected {
+                let bar = Rectangle::new(
+                    Point::new(area.top_left.x, y),
+                    Point::new(area.bottom_right.x, y + LINE_HEIGHT - 1),
+                );
+                fill(bar, BinaryColor::On, target)?;
+            }
+            draw_text(
+                item,
+                Point::new(area.top_left.x, y),
+                width(&area),
+                selected,
+                target,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Widget for Menu<'_> {
+    /// Index of the chosen item
+    type Output = usize;
+
+    fn handle(&mut self, event: Event) -> Option<usize> {
+        match event {
+            Event::Next => {
+                if !self.items.is_empty() {
+                    self.selected = (self.selected + 1) % self.items.len();
+                }
+                None
+            }
+            Event::Select if self.items.is_empty() => None,
+            Event::Select => Some(self.selected),
+        }
+    }
+}
+
+/// Picks a number. Each short press steps the value up, wrapping around from `max` to `min`.
+pub struct Spinner<'a> {
+    label: &'a str,
+    value: i32,
+    min: i32,
+    max: i32,
+    step: i32,
+}
+
+impl<'a> Spinner<'a> {
+    /// `value` is clamped to `min..=max`
+    pub fn new(label: &'a str, value: i32, min: i32, max: i32, step: i32) -> Self {
+        Self {
+            label,
+            value: value.max(min).min(max),
+            min,
+            max,
+            step: step.max(1),
+        }
+    }
+
+    pub fn value(&self) -> i32 {
+        self.value
+    }
+}
+
+impl View for Spinner<'_> {
+    fn draw<D: DrawTarget<BinaryColor>>(
+        &self,
+        area: Rectangle,
+        target: &mut D,
+    ) -> Result<(), D::Error> {
+        let middle = area.top_left.y + (height(&area) - LINE_HEIGHT) / 2;
+        draw_centered(self.label, &area, middle - LINE_HEIGHT - 2, target)?;
+        let mut value: String<U16> = String::new();
+        write!(value, "< {} >", self.value).ok();
+        draw_centered(&value, &area, middle, target)
+    }
+}
+
+impl Widget for Spinner<'_> {
+    /// The chosen value
+    type Output = i32;
+
+    fn handle(&mut self, event: Event) -> Option<i32> {
+        match event {
+            Event::Next => {
+                self.value = if self.value >= self.max {
+                    self.min
+                } else {
+                    self.value.saturating_add(self.step).min(self.max)
+                };
+                None
+            }
+            Event::Select => Some(self.value),
+        }
+    }
+}
+
+/// Horizontal bar filled in proportion to `value / max`
+pub struct ProgressBar {
+    pub value: u32,
+    pub max: u32,
+}
+
+impl ProgressBar {
+    pub fn new(max: u32) -> Self {
+        Self { value: 0, max }
+    }
+}
+
+impl View for ProgressBar {
+    fn draw<D: DrawTarget<BinaryColor>>(
+        &self,
+        area: Rectangle,
+        target: &mut D,
+    ) -> Result<(), D::Error> {
+        area.into_styled(PrimitiveStyle::with_stroke(BinaryColor::On, 1))
+            .draw(target)?;
+        let inner_width = width(&area) - 4;
+        let filled = if self.max == 0 {
+            inner_width
+        } else {
+            (inner_width as u64 * u64::from(self.value.min(self.max)) / u64::from(self.max)) as i32
+        };
+        if filled > 0 && height(&area) > 4 {
+            let bar = Rectangle::new(
+                area.top_left + Point::new(2, 2),
+                Point::new(area.top_left.x + 1 + filled, area.bottom_right.y - 2),
+            );
+            fill(bar, BinaryColor::On, target)?;
+        }
+        Ok(())
+    }
+}
+
+/// One line of text at the top of the screen, with an underline. `right` is right-aligned, e.g. for
+/// a clock or battery level.
+#[derive(Default)]
+pub struct StatusBar<'a> {
+    pub left: &'a str,
+    pub right: &'a str,
+}
+
+impl StatusBar<'_> {
+    /// Height including the underline
+    pub const HEIGHT: i32 = LINE_HEIGHT + 2;
+}
+
+impl View for StatusBar<'_> {
+    fn draw<D: DrawTarget<BinaryColor>>(
+        &self,
+        area: Rectangle,
+        target: &mut D,
+    ) -> Result<(), D::Error> {
+        let right = fit(self.right, width(&area));
+        let right_width = text_width(right);
+        draw_text(
+            self.left,
+            area.top_left,
+            width(&area) - right_width - CHAR_WIDTH,
+            false,
+            target,
+        )?;
+        draw_text(
+            right,
+            Point::new(area.bottom_right.x + 1 - right_width, area.top_left.y),
+            right_width,
+            false,
+            target,
+        )?;
+        let y = area.top_left.y + LINE_HEIGHT;
+        let underline = Rectangle::new(
+            Point::new(area.top_left.x, y),
+            Point::new(area.bottom_right.x, y),
+        );
+        fill(underline, BinaryColor::On, target)
+    }
+}
+
+/// Boxed message drawn over whatever is underneath. Lines are split on `\n`. Any button press
+/// dismisses it.
+pub struct Modal<'a> {
+    pub message: &'a str,
+}
+
+impl<'a> Modal<'a> {
+    pub fn new(message: &'a str) -> Self {
+        Self { message }
+    }
+}
+
+impl View for Modal<'_> {
+    fn draw<D: DrawTarget<BinaryColor>>(
+        &self,
+        area: Rectangle,
+        target: &mut D,
+    ) -> Result<(), D::Error> {
+        let lines = self.message.lines().count().max(1) as i32;
+        let longest = self.message.lines().map(text_width).max().unwrap_or(0);
+        let box_width = (longest + 8).min(width(&area));
+        let box_height = (lines * LINE_HEIGHT + 8).min(height(&area));
+        let top_left = area.top_left
+            + Point::new(
+                (width(&area) - box_width) / 2,
+                (height(&area) - box_height) / 2,
+            );
+        let frame = Rectangle::new(
+            top_left,
+            top_left + Point::new(box_width - 1, box_height - 1),
+        );
+        frame
+            .into_styled(
+                PrimitiveStyleBuilder::new()
+                    .stroke_color(BinaryColor::On)
+                    .stroke_width(1)
+                    .fill_color(BinaryColor::Off)
+                    .build(),
+            )
+            .draw(target)?;
+        let inside = Rectangle::new(
+            frame.top_left + Point::new(4, 4),
+            frame.bottom_right - Point::new(4, 4),
+        );
+        for (row, line) in self.message.lines().enumerate() {
+            let y = inside.top_left.y + row as i32 * LINE_HEIGHT;
+            if y + LINE_HEIGHT - 1 > inside.bottom_right.y {
+                break;
+            }
+            draw_centered(line, &inside, y, target)?;
+        }
+        Ok(())
+    }
+}
+
+impl Widget for Modal<'_> {
+    type Output = ();
+
+    fn handle(&mut self, _event: Event) -> Option<()> {
+        Some(())
+    }
+}
+
+/// A status bar, the widget currently in focus and possibly a modal on top
+pub struct Screen<'a> {
+    pub status: StatusBar<'a>,
+    pub modal: Option<Modal<'a>>,
+}
+
+impl<'a> Screen<'a> {
+    pub fn new(status: StatusBar<'a>) -> Self {
+        Self {
+            status,
+            modal: None,
+        }
+    }
+
+    /// Show a message until the next button press
+    pub fn show_modal(&mut self, message: &'a str) {
+        self.modal = Some(Modal::new(message));
+    }
+
+    /// Route an event to the modal if one is up, otherwise to `body`
+    pub fn handle<W: Widget>(&mut self, event: Event, body: &mut W) -> Option<W::Output> {
+        if let Some(modal) = self.modal.as_mut() {
+            if modal.handle(event).is_some() {
+                self.modal = None;
+            }
+            None
+        } else {
+            body.handle(event)
+        }
+    }
+
+    /// Clear `target` and draw everything
+    pub fn draw<V: View, D: DrawTarget<BinaryColor>>(
+        &self,
+        body: &V,
+        target: &mut D,
+    ) -> Result<(), D::Error> {
+        let size = target.size();
+        let all = Rectangle::new(
+            Point::zero(),
+            Point::new(size.width as i32 - 1, size.height as i32 - 1),
+        );
+        fill(all, BinaryColor::Off, target)?;
+        let (status_area, body_area) = split_top(all, StatusBar::HEIGHT);
+        self.status.draw(status_area, target)?;
+        body.draw(body_area, target)?;
+        if let Some(modal) = &self.modal {
+            modal.draw(all, target)?;
+        }
+        Ok(())
+    }
+}
+
+/// Split `area` into a strip of `height` pixels at the top and whatever's left
+pub fn split_top(area: Rectangle, height: i32) -> (Rectangle, Rectangle) {
+    let top = Rectangle::new(
+        area.top_left,
+        Point::new(area.bottom_right.x, area.top_left.y + height - 1),
+    );
+    let rest = Rectangle::new(
+        Point::new(area.top_left.x, area.top_left.y + height),
+        area.bottom_right,
+    );
+    (top, rest)
+}
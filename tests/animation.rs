@@ -0,0 +1,169 @@
+//! Host tests for sprite animation. Run with
+//! `cargo test --target x86_64-unknown-linux-gnu --test animation` (or your host's target triple).
+mod common;
+
+use common::{snapshot::assert_snapshot, Canvas};
+use embedded_graphics::prelude::*;
+use sandbox_stm32f4_rust::{
+    animation::{Animation, Blend, BlendTarget, Playback, SpriteSheet},
+    assets::{self, Asset},
+    demo,
+    display::{Config, Controller},
+};
+use sh1106::{displayrotation::DisplayRotation, interface::DisplayInterface};
+
+/// Four 2x1 frames: off off, on off, off on, on on
+static COUNTER: Asset = Asset {
+    data: &[0b0000_0000, 0b1000_0000, 0b0100_0000, 0b1100_0000],
+    width: 2,
+    height: 4,
+};
+
+fn frames(animation: &mut Animation, count: usize) -> Vec<u32> {
+    (0..count)
+        .map(|_| {
+            let frame = animation.frame();
+            animation.advance(1);
+            frame
+        })
+        .collect()
+}
+
+#[test]
+fn playback_modes() {
+    let sheet = SpriteSheet::new(&COUNTER, 4);
+    assert_eq!(sheet.frames(), 4);
+    assert_eq!(sheet.size(), (2, 1));
+
+    let mut looping = Animation::new(&sheet, Playback::Loop);
+    assert_eq!(frames(&mut looping, 9), [0, 1, 2, 3, 0, 1, 2, 3, 0]);
+
+    let mut ping_pong = Animation::new(&sheet, Playback::PingPong);
+    assert_eq!(frames(&mut ping_pong, 9), [0, 1, 2, 3, 2, 1, 0, 1, 2]);
+
+    let mut once = Animation::new(&sheet, Playback::Once);
+    assert_eq!(frames(&mut once, 6), [0, 1, 2, 3, 3, 3]);
+    assert!(once.is_finished());
+    once.rewind();
+    assert_eq!(once.frame(), 0);
+    assert!(!once.is_finished());
+}
+
+#[test]
+fn advancing_many_frames_at_once_matches_one_at_a_time() {
+    let sheet = SpriteSheet::new(&COUNTER, 4);
+    for &playback in &[Playback::Once, Playback::Loop, Playback::PingPong] {
+        for ticks in 0..20 {
+            let mut stepped = Animation::new(&sheet, playback);
+            let mut jumped = Animation::new(&sheet, playback);
+            // Start partway through, heading backwards for ping-pong
+            stepped.advance(4);
+            jumped.advance(4);
+            for _ in 0..ticks {
+                stepped.advance(1);
+            }
+            jumped.advance(ticks);
+            assert_eq!(jumped.frame(), stepped.frame(), "{:?} {}", playback, ticks);
+        }
+    }
+
+    // A single frame has nowhere to go
+    let still = SpriteSheet::new(&COUNTER, 1);
+    let mut ping_pong = Animation::new(&still, Playback::PingPong);
+    ping_pong.advance(5);
+    assert_eq!(ping_pong.frame(), 0);
+}
+
+#[test]
+fn blend_modes() {
+    let sheet = SpriteSheet::new(&COUNTER, 4);
+    let background = || {
+        let mut canvas = Canvas::new();
+        // Right pixel lit
+        canvas.set_pixel(1, 0, true);
+        canvas
+    };
+    let row = |canvas: &Canvas| [canvas.pixels[0][0], canvas.pixels[0][1]];
+
+    // Frame 1 is just the left pixel
+    let mut canvas = background();
+    sheet.draw(1, Point::zero(), Blend::Opaque, &mut canvas);
+    assert_eq!(row(&canvas), [true, false]);
+
+    let mut canvas = background();
+    sheet.draw(1, Point::zero(), Blend::Transparent, &mut canvas);
+    assert_eq!(row(&canvas), [true, true]);
+
+    // Frame 3 is both
+    let mut canvas = background();
+    sheet.draw(3, Point::zero(), Blend::Xor, &mut canvas);
+    assert_eq!(row(&canvas), [true, false]);
+    sheet.draw(3, Point::zero(), Blend::Xor, &mut canvas);
+    assert_eq!(row(&canvas), [false, true]);
+}
+
+#[test]
+fn sprites_can_hang_off_the_edges() {
+    let sheet = SpriteSheet::new(&assets::SPINNER, 8);
+    let mut canvas = Canvas::new();
+    for &(x, y) in &[(-8, -8), (120, 56), (-100, 0), (200, 200)] {
+        sheet.draw(0, Point::new(x, y), Blend::Opaque, &mut canvas);
+    }
+    assert!(canvas.lit_in(0..8, 0..8) > 0);
+    assert!(canvas.lit_in(120..128, 56..64) > 0);
+    assert_eq!(canvas.lit_in(8..120, 0..64), 0);
+}
+
+struct NoDisplay;
+
+impl DisplayInterface for NoDisplay {
+    type Error = ();
+
+    fn init(&mut self) -> Result<(), ()> {
+        Ok(())
+    }
+
+    fn send_commands(&mut self, _: &[u8]) -> Result<(), ()> {
+        Ok(())
+    }
+
+    fn send_data(&mut self, _: &[u8]) -> Result<(), ()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn framebuffer_reads_back_rotated_pixels() {
+    let mut disp = Config::new(Controller::Sh1106)
+        .with_rotation(DisplayRotation::Rotate90)
+        .connect(NoDisplay);
+    let sheet = SpriteSheet::new(&COUNTER, 4);
+    // Rotated, the display is 64 wide and 128 tall
+    sheet.draw(3, Point::new(62, 127), Blend::Xor, &mut disp);
+    assert!(disp.get_pixel(62, 127));
+    assert!(disp.get_pixel(63, 127));
+    assert!(!disp.get_pixel(61, 127));
+    assert!(!disp.get_pixel(64, 127));
+    sheet.draw(3, Point::new(62, 127), Blend::Xor, &mut disp);
+    assert!(!disp.get_pixel(62, 127));
+}
+
+#[test]
+fn sprites_over_shapes() {
+    let spinner = SpriteSheet::new(&assets::SPINNER, 8);
+    let pulse = SpriteSheet::new(&assets::PULSE, 7);
+    let mut canvas = Canvas::new();
+    demo::draw_shapes(&mut canvas).unwrap();
+    // Every spinner frame along the top, the pulse overlapping the square
+    for frame in 0..spinner.frames() {
+        spinner.draw(
+            frame,
+            Point::new(frame as i32 * 16, 0),
+            Blend::Transparent,
+            &mut canvas,
+        );
+    }
+    pulse.draw(6, Point::new(45, 22), Blend::Xor, &mut canvas);
+    pulse.draw(2, Point::new(80, 46), Blend::Opaque, &mut canvas);
+    assert_snapshot("sprites_over_shapes", &canvas);
+}
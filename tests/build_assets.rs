@@ -0,0 +1,62 @@
+//! Host tests for the PNG conversion in `build/assets.rs`, run against the small images in
+//! `tests/fixtures/assets/`. Run with
+//! `cargo test --target x86_64-unknown-linux-gnu --test build_assets` (or your host's target
+//! triple).
+#[path = "../build/assets.rs"]
+mod assets;
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Convert the fixtures once per test into a directory of its own
+fn convert(test: &str) -> PathBuf {
+    let out = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(test);
+    fs::create_dir_all(&out).unwrap();
+    assets::convert_all(
+        &PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/assets"),
+        &out,
+    );
+    out
+}
+
+fn raw(out: &Path, name: &str) -> Vec<u8> {
+    fs::read(out.join("assets").join(format!("{}.raw", name))).unwrap()
+}
+
+#[test]
+fn threshold_and_invert() {
+    let out = convert("threshold_and_invert");
+    // 0, 36, 73, 109, 146, 182, 219, 255 against the default cutoff of 128
+    assert_eq!(raw(&out, "ramp"), [0b0000_1111]);
+    // Cutoff of 100, then flipped
+    assert_eq!(raw(&out, "ramp_inverted"), [0b1110_0000]);
+    // Transparent counts as unlit
+    assert_eq!(raw(&out, "alpha"), [0b1000_0000]);
+}
+
+#[test]
+fn dithering() {
+    let out = convert("dithering");
+    // A flat 100 is all unlit with a plain threshold. Floyd-Steinberg passes 7/16 of each error to
+    // the right: 100 -> 143.75 (lit) -> 51.3 -> 122.5.
+    assert_eq!(raw(&out, "flat_floyd"), [0b0100_0000]);
+    // Atkinson passes 1/8 to each of the next two: 100 -> 112.5 -> 126.6 -> 129.9 (lit)
+    assert_eq!(raw(&out, "flat_atkinson"), [0b0001_0000]);
+}
+
+#[test]
+fn resize_and_packing() {
+    let out = convert("resize_and_packing");
+    // 4x2 down to 2x1, keeping the aspect ratio. The left 2x2 block averages 191, the right one 0.
+    assert_eq!(raw(&out, "blocks"), [0b1000_0000]);
+    // Rows are padded to whole bytes
+    assert_eq!(raw(&out, "wide"), [0xFF, 0b1000_0000]);
+
+    let source = fs::read_to_string(out.join("assets.rs")).unwrap();
+    assert!(source.contains("pub const BLOCKS: Asset = Asset {"));
+    assert!(source.contains("    width: 2,\n    height: 1,\n"));
+    assert!(source.contains("pub const WIDE: Asset = Asset {"));
+    assert!(source.contains("    width: 9,\n    height: 1,\n"));
+}
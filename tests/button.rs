@@ -0,0 +1,158 @@
+//! Host tests for button debouncing and click detection. Run with
+//! `cargo test --target x86_64-unknown-linux-gnu --test button` (or your host's target triple).
+use sandbox_stm32f4_rust::button::{ButtonEvent, Debouncer, Timings};
+use ButtonEvent::*;
+
+/// Holds the button in each state for the given number of milliseconds, updating every
+/// millisecond like a busy driver would, and collects the events
+fn run(debouncer: &mut Debouncer, start_ms: u32, steps: &[(bool, u32)]) -> Vec<ButtonEvent> {
+    let mut now = start_ms;
+    let mut events = Vec::new();
+    for &(pressed, duration) in steps {
+        for _ in 0..duration {
+            debouncer.update(pressed, now);
+            now = now.wrapping_add(1);
+            events.extend(std::iter::from_fn(|| debouncer.next_event()));
+        }
+    }
+    events
+}
+
+/// A press with a few milliseconds of contact bounce at each end
+fn bouncy_press(held_ms: u32) -> Vec<(bool, u32)> {
+    vec![
+        (true, 2),
+        (false, 1),
+        (true, 3),
+        (false, 2),
+        (true, held_ms),
+        (false, 1),
+        (true, 2),
+        (false, 1),
+    ]
+}
+
+#[test]
+fn bounces_are_ignored() {
+    let mut debouncer = Debouncer::new(Timings::default());
+    let mut steps = bouncy_press(100);
+    steps.push((false, 1000));
+    assert_eq!(run(&mut debouncer, 0, &steps), [Press, Release, Click]);
+    assert!(debouncer.is_idle());
+
+    // Blips shorter than the debounce time never register
+    let events = run(&mut debouncer, 5000, &[(true, 19), (false, 100)]);
+    assert_eq!(events, []);
+}
+
+#[test]
+fn double_click() {
+    let mut debouncer = Debouncer::new(Timings::default());
+    let mut steps = bouncy_press(80);
+    steps.push((false, 100));
+    steps.extend(bouncy_press(80));
+    steps.push((false, 1000));
+    assert_eq!(
+        run(&mut debouncer, 0, &steps),
+        [Press, Release, Press, Release, DoubleClick]
+    );
+
+    // Too slow for a double-click
+    let steps = [(true, 80), (false, 400), (true, 80), (false, 1000)];
+    assert_eq!(
+        run(&mut debouncer, 10_000, &steps),
+        [Press, Release, Click, Press, Release, Click]
+    );
+}
+
+#[test]
+fn long_press_is_reported_while_held() {
+    let timings = Timings::default();
+    let mut debouncer = Debouncer::new(timings);
+    let events = run(&mut debouncer, 0, &[(true, timings.long_press_ms + 1)]);
+    assert_eq!(events, [Press, LongPress]);
+    // Nothing left to time until it's released
+    assert!(debouncer.is_idle());
+    let events = run(&mut debouncer, 5000, &[(false, 1000)]);
+    assert_eq!(events, [Release]);
+
+    // A click followed by a long press
+    let steps = [(true, 50), (false, 50), (true, 1000), (false, 1000)];
+    assert_eq!(
+        run(&mut debouncer, 10_000, &steps),
+        [Press, Release, Press, Click, LongPress, Release]
+    );
+}
+
+#[test]
+fn clicks_are_immediate_without_double_clicks() {
+    let mut debouncer = Debouncer::new(Timings {
+        double_click_ms: 0,
+        ..Timings::default()
+    });
+    let events = run(&mut debouncer, 0, &[(true, 50), (false, 30)]);
+    assert_eq!(events, [Press, Release, Click]);
+    let events = run(&mut debouncer, 100, &[(true, 50), (false, 30)]);
+    assert_eq!(events, [Press, Release, Click]);
+}
+
+#[test]
+fn updates_can_be_sparse() {
+    // The driver only samples every few milliseconds, and not at all while idle
+    let mut debouncer = Debouncer::new(Timings::default());
+    debouncer.update(true, 1000);
+    assert!(!debouncer.is_idle());
+    debouncer.update(true, 1025);
+    debouncer.update(false, 1100);
+    debouncer.update(false, 1125);
+    assert_eq!(debouncer.next_event(), Some(Press));
+    assert_eq!(debouncer.next_event(), Some(Release));
+    assert_eq!(debouncer.next_event(), None);
+    assert!(!debouncer.is_idle());
+    debouncer.update(false, 1400);
+    assert_eq!(debouncer.next_event(), Some(Click));
+    assert!(debouncer.is_idle());
+}
+
+#[test]
+fn timestamps_can_wrap_around() {
+    let mut debouncer = Debouncer::new(Timings::default());
+    let steps = [
+        (true, 700),
+        (false, 100),
+        (true, 50),
+        (false, 50),
+        (true, 50),
+        (false, 500),
+    ];
+    assert_eq!(
+        run(&mut debouncer, u32::MAX - 500, &steps),
+        [
+            Press,
+            LongPress,
+            Release,
+            Press,
+            Release,
+            Press,
+            Release,
+            DoubleClick
+        ]
+    );
+}
+
+#[test]
+fn events_queue_up_until_read() {
+    let mut debouncer = Debouncer::new(Timings::default());
+    let mut now = 0;
+    for _ in 0..10 {
+        debouncer.update(true, now);
+        debouncer.update(true, now + 50);
+        debouncer.update(false, now + 100);
+        debouncer.update(false, now + 150);
+        now += 1000;
+    }
+    // Only the oldest are kept
+    let events: Vec<_> = std::iter::from_fn(|| debouncer.next_event()).collect();
+    assert_eq!(events.len(), 8);
+    assert_eq!(events[..3], [Press, Release, Click]);
+}
@@ -0,0 +1,52 @@
+//! Host tests for the 64-bit clock. Run with
+//! `cargo test --target x86_64-unknown-linux-gnu --test clock` (or your host's target triple).
+use sandbox_stm32f4_rust::clock::{extend, Duration, Instant};
+use std::convert::TryFrom;
+
+#[test]
+fn counter_extends_to_64_bits() {
+    let half = 1u64 << 31;
+    // Around the first few wraparounds and halfway points, and a long way in
+    let boundaries = (0..8).map(|n| n * half).chain(Some(12345 * half));
+    for boundary in boundaries {
+        for offset in [-1000i64, -1, 0, 1, 1000] {
+            let now = match boundary.checked_add_signed(offset) {
+                Some(now) => now,
+                None => continue,
+            };
+            let counter = now as u32;
+            let periods = (now >> 31) as u32;
+            assert_eq!(extend(periods, counter), now, "at {}", now);
+            // The interrupt for the last halfway point or wraparound hasn't been handled yet
+            if periods > 0 {
+                assert_eq!(extend(periods - 1, counter), now, "late at {}", now);
+            }
+        }
+    }
+}
+
+#[test]
+fn instants_and_durations() {
+    assert_eq!(Duration::from_secs(2), Duration::from_millis(2000));
+    assert_eq!(Duration::from_millis(3), Duration::from_micros(3000));
+    assert_eq!(Duration::from_micros(2_500_999).as_millis(), 2500);
+    assert_eq!(Duration::from_micros(2_500_999).as_secs(), 2);
+
+    let start = Instant::from_micros(1_000);
+    let later = start + Duration::from_millis(5);
+    assert_eq!(later.as_micros(), 6_000);
+    assert!(later > start);
+    assert_eq!(later - start, Duration::from_millis(5));
+    assert_eq!(later - Duration::from_millis(5), start);
+    // Going backwards gives nothing rather than a huge duration
+    assert_eq!(start - later, Duration::from_micros(0));
+
+    let mut t = start;
+    t += Duration::from_micros(1);
+    t -= Duration::from_micros(2);
+    assert_eq!(t.as_micros(), 999);
+
+    // RTIC's timer queue only handles durations that fit in 32 bits
+    assert_eq!(u32::try_from(Duration::from_secs(60)), Ok(60_000_000));
+    assert!(u32::try_from(Duration::from_secs(5000)).is_err());
+}
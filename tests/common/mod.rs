@@ -0,0 +1,78 @@
+//! Shared helpers for the host tests. Not every test uses all of them.
+#![allow(dead_code)]
+
+pub mod snapshot;
+
+use embedded_graphics::{
+    drawable::Pixel, geometry::Size, pixelcolor::BinaryColor, prelude::*, primitives::Rectangle,
+};
+use sandbox_stm32f4_rust::animation::BlendTarget;
+use std::ops::Range;
+
+pub const WIDTH: usize = 128;
+pub const HEIGHT: usize = 64;
+
+/// 128x64 in-memory display. Drawing outside of it fails the test.
+pub struct Canvas {
+    pub pixels: [[bool; WIDTH]; HEIGHT],
+}
+
+impl Canvas {
+    pub fn new() -> Self {
+        Self {
+            pixels: [[false; WIDTH]; HEIGHT],
+        }
+    }
+
+    /// Number of lit pixels in the given area
+    pub fn lit_in(&self, x: Range<usize>, y: Range<usize>) -> usize {
+        self.pixels[y]
+            .iter()
+            .map(|row| row[x.clone()].iter().filter(|&&on| on).count())
+            .sum()
+    }
+
+    /// Rows with a lit pixel in column `x`
+    pub fn lit_rows(&self, x: usize) -> Vec<usize> {
+        (0..HEIGHT).filter(|&y| self.pixels[y][x]).collect()
+    }
+
+    pub fn full() -> Rectangle {
+        Rectangle::new(
+            Point::zero(),
+            Point::new(WIDTH as i32 - 1, HEIGHT as i32 - 1),
+        )
+    }
+}
+
+impl DrawTarget<BinaryColor> for Canvas {
+    type Error = core::convert::Infallible;
+
+    fn draw_pixel(&mut self, Pixel(pos, color): Pixel<BinaryColor>) -> Result<(), Self::Error> {
+        assert!(
+            (0..WIDTH as i32).contains(&pos.x) && (0..HEIGHT as i32).contains(&pos.y),
+            "drew outside the display at {:?}",
+            pos
+        );
+        self.pixels[pos.y as usize][pos.x as usize] = color.is_on();
+        Ok(())
+    }
+
+    fn size(&self) -> Size {
+        Size::new(WIDTH as u32, HEIGHT as u32)
+    }
+}
+
+impl BlendTarget for Canvas {
+    fn get_pixel(&self, x: u32, y: u32) -> bool {
+        let (x, y) = (x as usize, y as usize);
+        x < WIDTH && y < HEIGHT && self.pixels[y][x]
+    }
+
+    fn set_pixel(&mut self, x: u32, y: u32, on: bool) {
+        let (x, y) = (x as usize, y as usize);
+        if x < WIDTH && y < HEIGHT {
+            self.pixels[y][x] = on;
+        }
+    }
+}
@@ -0,0 +1,161 @@
+//! Golden-image tests for anything drawn on a [Canvas].
+//!
+//! [assert_snapshot] compares a canvas against `tests/snapshots/<name>.pbm`. On a mismatch, it writes
+//! what was drawn, what was expected and a diff to [output_dir] as PNGs and fails the test. That's
+//! `snapshots/` in cargo's scratch directory for tests, e.g.
+//! `target/x86_64-unknown-linux-gnu/tmp/snapshots/`. In the diff, pixels that should be lit but
+//! aren't are red and pixels that are lit but shouldn't be are green. Everything else is dimmed.
+//!
+//! Run with `UPDATE_SNAPSHOTS=1` to accept the new output, then check the goldens into git.
+use super::{Canvas, HEIGHT, WIDTH};
+use std::{
+    fmt::Write as _,
+    fs,
+    path::{Path, PathBuf},
+};
+
+impl Canvas {
+    /// Plain (ASCII) PBM, which diffs well in git. 1 is lit.
+    pub fn to_pbm(&self) -> String {
+        let mut pbm = format!("P1\n{} {}\n", WIDTH, HEIGHT);
+        for row in self.pixels.iter() {
+            // Keep lines under 70 characters, as the format asks
+            for half in row.chunks(WIDTH / 2) {
+                for &on in half {
+                    pbm.push(if on { '1' } else { '0' });
+                }
+                pbm.push('\n');
+            }
+        }
+        pbm
+    }
+
+    /// Read back what [Canvas::to_pbm] wrote. Panics on anything else.
+    pub fn from_pbm(pbm: &str) -> Self {
+        // Comments run from # to the end of the line
+        let mut tokens = pbm
+            .lines()
+            .map(|line| line.split('#').next().unwrap())
+            .flat_map(str::split_whitespace);
+        assert_eq!(tokens.next(), Some("P1"), "not a plain PBM");
+        let size: Vec<usize> = tokens
+            .by_ref()
+            .take(2)
+            .map(|token| token.parse().unwrap())
+            .collect();
+        assert_eq!(size, [WIDTH, HEIGHT], "wrong size");
+
+        let mut canvas = Canvas::new();
+        let mut bits = tokens.flat_map(str::chars);
+        for row in canvas.pixels.iter_mut() {
+            for pixel in row.iter_mut() {
+                *pixel = match bits.next() {
+                    Some('1') => true,
+                    Some('0') => false,
+                    other => panic!("bad pixel {:?}", other),
+                };
+            }
+        }
+        canvas
+    }
+
+    /// Lit pixels are white, as on the display
+    pub fn write_png(&self, path: &Path) {
+        let rgb: Vec<[u8; 3]> = self
+            .pixels
+            .iter()
+            .flatten()
+            .map(|&on| if on { [255; 3] } else { [0; 3] })
+            .collect();
+        write_rgb_png(path, &rgb);
+    }
+}
+
+fn write_rgb_png(path: &Path, pixels: &[[u8; 3]]) {
+    let file = fs::File::create(path).unwrap();
+    let mut encoder = png::Encoder::new(file, WIDTH as u32, HEIGHT as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().unwrap();
+    writer.write_image_data(pixels.concat().as_slice()).unwrap();
+}
+
+fn golden_path(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/snapshots")
+        .join(format!("{}.pbm", name))
+}
+
+/// Under cargo's scratch space for tests, so it follows `CARGO_TARGET_DIR` and stays out of the
+/// source tree
+pub fn output_dir() -> PathBuf {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("snapshots");
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Fail unless `canvas` matches the golden image called `name`
+pub fn assert_snapshot(name: &str, canvas: &Canvas) {
+    let golden = golden_path(name);
+    if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        fs::create_dir_all(golden.parent().unwrap()).unwrap();
+        fs::write(&golden, canvas.to_pbm()).unwrap();
+        return;
+    }
+
+    let output = output_dir();
+    let actual_path = output.join(format!("{}.actual.png", name));
+    let expected = match fs::read_to_string(&golden) {
+        Ok(pbm) => Canvas::from_pbm(&pbm),
+        Err(_) => {
+            canvas.write_png(&actual_path);
+            panic!(
+                "no golden image at {}. Drew {}. Run with UPDATE_SNAPSHOTS=1 to save it.",
+                golden.display(),
+                actual_path.display()
+            );
+        }
+    };
+
+    let mut diff = Vec::with_capacity(WIDTH * HEIGHT);
+    let mut differences = 0;
+    let mut first = None;
+    for (y, (actual_row, expected_row)) in canvas.pixels.iter().zip(&expected.pixels).enumerate() {
+        for (x, (&actual, &expected)) in actual_row.iter().zip(expected_row).enumerate() {
+            diff.push(match (expected, actual) {
+                (true, false) => [255, 0, 0],
+                (false, true) => [0, 255, 0],
+                (true, true) => [80; 3],
+                (false, false) => [0; 3],
+            });
+            if actual != expected {
+                differences += 1;
+                first.get_or_insert((x, y));
+            }
+        }
+    }
+    if differences == 0 {
+        return;
+    }
+
+    canvas.write_png(&actual_path);
+    let expected_path = output.join(format!("{}.expected.png", name));
+    expected.write_png(&expected_path);
+    let diff_path = output.join(format!("{}.diff.png", name));
+    write_rgb_png(&diff_path, &diff);
+    let mut message = format!(
+        "{} differs from its golden image in {} pixels, starting at {:?}.",
+        name,
+        differences,
+        first.unwrap()
+    );
+    write!(
+        message,
+        "\nactual: {}\nexpected: {}\ndiff: {}\nRun with UPDATE_SNAPSHOTS=1 if the change is intended.",
+        actual_path.display(),
+        expected_path.display(),
+        diff_path.display()
+    )
+    .unwrap();
+    panic!("{}", message);
+}
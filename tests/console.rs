@@ -0,0 +1,88 @@
+//! Host tests for the console's text handling and escape codes. Run with
+//! `cargo test --target x86_64-unknown-linux-gnu --test console` (or your host's target triple).
+use sandbox_stm32f4_rust::{
+    console::{Console, COLUMNS, ROWS},
+    display::{Config, Controller, PanelInterface},
+};
+use sh1106::interface::DisplayInterface;
+
+struct NoDisplay;
+
+impl DisplayInterface for NoDisplay {
+    type Error = ();
+
+    fn init(&mut self) -> Result<(), ()> {
+        Ok(())
+    }
+
+    fn send_commands(&mut self, _: &[u8]) -> Result<(), ()> {
+        Ok(())
+    }
+
+    fn send_data(&mut self, _: &[u8]) -> Result<(), ()> {
+        Ok(())
+    }
+}
+
+fn console() -> Console<PanelInterface<NoDisplay>> {
+    Console::new(Config::new(Controller::Sh1106).connect(NoDisplay))
+}
+
+/// Every row, without the padding
+fn lines(console: &Console<PanelInterface<NoDisplay>>) -> Vec<&str> {
+    (0..ROWS).map(|row| console.line(row).trim_end()).collect()
+}
+
+#[test]
+fn cursor_movement() {
+    let mut console = console();
+    console.write_bytes(b"hello\x1b[3;5HX");
+    assert_eq!(console.cursor(), (2, 5));
+    assert_eq!(lines(&console)[..3], ["hello", "", "    X"]);
+
+    console.write_bytes(b"\x1b[H");
+    assert_eq!(console.cursor(), (0, 0));
+    console.write_bytes(b"\x1b[4H");
+    assert_eq!(console.cursor(), (3, 0));
+    console.write_bytes(b"\x1b[0;0f");
+    assert_eq!(console.cursor(), (0, 0));
+    // Off the screen stops at the edge
+    console.write_bytes(b"\x1b[99;99H");
+    assert_eq!(console.cursor(), (ROWS - 1, COLUMNS - 1));
+}
+
+#[test]
+fn clearing() {
+    let mut console = console();
+    console.write_bytes(b"abc\ndef\x1b[2J");
+    assert!(lines(&console).iter().all(|line| line.is_empty()));
+    assert_eq!(console.cursor(), (1, 3));
+
+    console.write_bytes(b"\x1b[Hhello\x1b[1;3H\x1b[K");
+    assert_eq!(lines(&console)[0], "he");
+    assert_eq!(console.cursor(), (0, 2));
+    // Only 2J clears the screen
+    console.write_bytes(b"\x1b[1J\x1b[J");
+    assert_eq!(lines(&console)[0], "he");
+}
+
+#[test]
+fn sequences_split_across_writes() {
+    let mut console = console();
+    console.write_bytes(b"ab\x1b");
+    console.write_bytes(b"[2");
+    console.write_bytes(b";4");
+    assert_eq!(console.cursor(), (0, 2));
+    console.write_bytes(b"Hc");
+    assert_eq!(console.cursor(), (1, 4));
+    assert_eq!(lines(&console)[..2], ["ab", "   c"]);
+}
+
+#[test]
+fn unknown_sequences_are_swallowed() {
+    let mut console = console();
+    // Colors, a private mode, and a two-byte escape
+    console.write_bytes(b"a\x1b[1;31mb\x1b[?25lc\x1bcd\x1b[0m");
+    assert_eq!(lines(&console)[0], "abcd");
+    assert_eq!(console.cursor(), (0, 4));
+}
@@ -0,0 +1,166 @@
+//! Host tests for the display abstraction, checking what goes out over the bus. Run with
+//! `cargo test --target x86_64-unknown-linux-gnu --test display` (or your host's target triple).
+use embedded_graphics::{pixelcolor::BinaryColor, prelude::*};
+use sandbox_stm32f4_rust::display::{Config, Controller, DEFAULT_I2C_ADDRESS};
+use sh1106::interface::DisplayInterface;
+use std::{cell::RefCell, rc::Rc};
+
+#[derive(Clone, Debug, PartialEq)]
+enum Sent {
+    Commands(Vec<u8>),
+    Data(Vec<u8>),
+}
+
+/// Records everything sent to the display
+#[derive(Clone, Default)]
+struct Recorder(Rc<RefCell<Vec<Sent>>>);
+
+impl Recorder {
+    fn take(&self) -> Vec<Sent> {
+        self.0.borrow_mut().drain(..).collect()
+    }
+
+    /// All commands, run together
+    fn commands(&self) -> Vec<u8> {
+        self.take()
+            .into_iter()
+            .filter_map(|sent| match sent {
+                Sent::Commands(commands) => Some(commands),
+                Sent::Data(_) => None,
+            })
+            .flatten()
+            .collect()
+    }
+}
+
+impl DisplayInterface for Recorder {
+    type Error = ();
+
+    fn init(&mut self) -> Result<(), ()> {
+        Ok(())
+    }
+
+    fn send_commands(&mut self, commands: &[u8]) -> Result<(), ()> {
+        self.0.borrow_mut().push(Sent::Commands(commands.to_vec()));
+        Ok(())
+    }
+
+    fn send_data(&mut self, data: &[u8]) -> Result<(), ()> {
+        self.0.borrow_mut().push(Sent::Data(data.to_vec()));
+        Ok(())
+    }
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack
+        .windows(needle.len())
+        .any(|window| window == needle)
+}
+
+#[test]
+fn ssd1306_gets_a_charge_pump_instead_of_dc_dc() {
+    let sh1106 = Recorder::default();
+    Config::new(Controller::Sh1106)
+        .connect(sh1106.clone())
+        .init()
+        .unwrap();
+    let commands = sh1106.commands();
+    assert!(contains(&commands, &[0xAD, 0x8B]));
+    assert!(!contains(&commands, &[0x8D, 0x14]));
+
+    let ssd1306 = Recorder::default();
+    Config::new(Controller::Ssd1306)
+        .connect(ssd1306.clone())
+        .init()
+        .unwrap();
+    let commands = ssd1306.commands();
+    assert!(!contains(&commands, &[0xAD, 0x8B]));
+    // Charge pump on and page addressing, before the display gets turned on at the very end
+    assert!(contains(&commands, &[0x8D, 0x14, 0x20, 0x02]));
+    assert_eq!(commands.last(), Some(&0xAF));
+}
+
+#[test]
+fn only_the_sh1106_is_offset_by_two_columns() {
+    for &(controller, column) in &[(Controller::Sh1106, 2), (Controller::Ssd1306, 0)] {
+        let recorder = Recorder::default();
+        let mut disp = Config::new(controller).connect(recorder.clone());
+        disp.init().unwrap();
+        disp.flush().unwrap();
+        recorder.take();
+
+        disp.draw_pixel(Pixel(Point::new(0, 9), BinaryColor::On))
+            .unwrap();
+        disp.flush().unwrap();
+        // sh1106 moves back to the start of the draw area afterwards, which doesn't matter
+        assert_eq!(
+            recorder.take()[..4],
+            [
+                // Page 1, then the low and high halves of the column
+                Sent::Commands(vec![0xB1]),
+                Sent::Commands(vec![column]),
+                Sent::Commands(vec![0x10]),
+                Sent::Data(vec![0b10]),
+            ]
+        );
+    }
+}
+
+/// Address and bytes of each write
+type Writes = Vec<(u8, Vec<u8>)>;
+
+/// Records I2C writes
+#[derive(Clone, Default)]
+struct I2cRecorder(Rc<RefCell<Writes>>);
+
+impl embedded_hal::blocking::i2c::Write for I2cRecorder {
+    type Error = ();
+
+    fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), ()> {
+        self.0.borrow_mut().push((address, bytes.to_vec()));
+        Ok(())
+    }
+}
+
+#[test]
+fn i2c_prefixes_commands_and_data_and_keeps_the_draw_position() {
+    let i2c = I2cRecorder::default();
+    let mut disp = Config::new(Controller::Ssd1306).i2c(i2c.clone(), DEFAULT_I2C_ADDRESS);
+    disp.init().unwrap();
+    disp.flush().unwrap();
+    let writes: Vec<_> = i2c.0.borrow_mut().drain(..).collect();
+    assert!(writes
+        .iter()
+        .all(|(address, _)| *address == DEFAULT_I2C_ADDRESS));
+    // One write per page with the whole row of 128 columns
+    let data: Vec<_> = writes
+        .iter()
+        .filter(|(_, bytes)| bytes[0] == 0x40)
+        .collect();
+    assert_eq!(data.len(), 8);
+    assert!(data.iter().all(|(_, bytes)| bytes.len() == 129));
+    assert!(writes
+        .iter()
+        .all(|(_, bytes)| bytes[0] == 0x00 || bytes[0] == 0x40));
+
+    // A small change only sends that part, at the right spot
+    disp.draw_pixel(Pixel(Point::new(100, 63), BinaryColor::On))
+        .unwrap();
+    disp.flush().unwrap();
+    let writes: Vec<_> = i2c
+        .0
+        .borrow_mut()
+        .drain(..)
+        .map(|(_, bytes)| bytes)
+        .collect();
+    assert_eq!(writes.iter().filter(|bytes| bytes[0] == 0x40).count(), 1);
+    assert_eq!(
+        writes[..4],
+        [
+            vec![0x00, 0xB7],
+            vec![0x00, 100 & 0xF],
+            vec![0x00, 0x10 | 100 >> 4],
+            vec![0x40, 0x80],
+        ]
+    );
+}
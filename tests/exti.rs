@@ -0,0 +1,62 @@
+//! Host tests for EXTI line bookkeeping. Run with
+//! `cargo test --target x86_64-unknown-linux-gnu --test exti` (or your host's target triple).
+use sandbox_stm32f4_rust::exti::{
+    vector, Error, InputEvent, LineTable, Port, Registration, Trigger,
+};
+use stm32f4xx_hal::stm32::Interrupt;
+
+fn ignore(_: InputEvent) {}
+
+fn on(port: Port) -> Registration {
+    Registration {
+        port,
+        trigger: Trigger::Both,
+        handler: ignore,
+    }
+}
+
+#[test]
+fn one_pin_per_line() {
+    let mut table = LineTable::new();
+    assert_eq!(table.claim(4, on(Port::A)), Ok(()));
+    // PB4 would need the line PA4 already has
+    assert_eq!(
+        table.claim(4, on(Port::B)),
+        Err(Error::LineTaken { port: Port::A })
+    );
+    // Even the same pin can't have it twice
+    assert_eq!(
+        table.claim(4, on(Port::A)),
+        Err(Error::LineTaken { port: Port::A })
+    );
+    assert_eq!(table.get(4).unwrap().port, Port::A);
+
+    // Other lines are unaffected
+    assert_eq!(table.claim(5, on(Port::B)), Ok(()));
+    assert_eq!(table.claim(15, on(Port::C)), Ok(()));
+    assert_eq!(table.claim(16, on(Port::C)), Err(Error::BadLine));
+
+    // Releasing a line frees it up for another port
+    assert_eq!(table.release(4).unwrap().port, Port::A);
+    assert!(table.release(4).is_none());
+    assert_eq!(table.claim(4, on(Port::B)), Ok(()));
+    assert_eq!(table.get(4).unwrap().port, Port::B);
+}
+
+#[test]
+fn shared_vectors() {
+    // `Interrupt` can't be compared directly
+    let vectors: Vec<_> = (0..16).map(|line| vector(line) as u8).collect();
+    assert_eq!(
+        vectors[..5],
+        [
+            Interrupt::EXTI0 as u8,
+            Interrupt::EXTI1 as u8,
+            Interrupt::EXTI2 as u8,
+            Interrupt::EXTI3 as u8,
+            Interrupt::EXTI4 as u8,
+        ]
+    );
+    assert_eq!(vectors[5..10], [Interrupt::EXTI9_5 as u8; 5]);
+    assert_eq!(vectors[10..], [Interrupt::EXTI15_10 as u8; 6]);
+}
@@ -0,0 +1,119 @@
+//! Host tests for the proportional fonts. Run with
+//! `cargo test --target x86_64-unknown-linux-gnu --test font` (or your host's target triple).
+mod common;
+
+use common::{snapshot::assert_snapshot, Canvas};
+use embedded_graphics::{prelude::*, primitives::Rectangle};
+use sandbox_stm32f4_rust::{
+    font::{Align, TextBox, LARGE, SMALL},
+    ui::View,
+};
+
+const SHRUG: &str = r"¯\_(ツ)_/¯";
+
+#[test]
+fn glyphs_are_as_wide_as_they_need_to_be() {
+    for font in &[SMALL, LARGE] {
+        assert!(font.char_width('i') < font.char_width('m'));
+        assert!(font.glyph('ツ').is_some());
+        assert!(font.glyph('¯').is_some());
+        // Missing characters take the replacement's place
+        assert!(font.glyph('☃').is_none());
+        assert_eq!(font.char_width('☃'), font.char_width('?'));
+        assert_eq!(
+            font.text_width("Wii"),
+            font.char_width('W') + 2 * font.char_width('i')
+        );
+    }
+    assert_eq!(SMALL.line_height(), 8);
+    assert_eq!(LARGE.line_height(), 16);
+}
+
+#[test]
+fn wrapping() {
+    // Wide enough for `widest` and no more
+    let wrap = |text, widest| {
+        SMALL
+            .wrap(text, SMALL.text_width(widest))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        wrap("the quick brown fox", "brown fox"),
+        ["the quick", "brown fox"]
+    );
+    // Spaces at the break are dropped, line breaks are kept
+    assert_eq!(
+        wrap("one    two\n\nthree", "one tw"),
+        ["one", "two", "", "three"]
+    );
+    // Long words get broken wherever they overflow
+    assert_eq!(wrap("abcdefgh ij", "abcd"), ["abcd", "efgh", "ij"]);
+    // Each line fits, even with a single character that doesn't
+    for line in SMALL.wrap("Mmmmm", 1) {
+        assert_eq!(line.chars().count(), 1);
+    }
+    assert_eq!(SMALL.wrap("", 10).collect::<Vec<_>>(), [""]);
+}
+
+#[test]
+fn trailing_newline_ends_the_last_line() {
+    let wrap = |text| SMALL.wrap(text, 100).collect::<Vec<_>>();
+    assert_eq!(wrap("one\ntwo\n"), ["one", "two"]);
+    assert_eq!(wrap("one\n\n"), ["one", ""]);
+    assert_eq!(wrap("\n"), [""]);
+}
+
+#[test]
+fn alignment() {
+    let area = Rectangle::new(Point::new(10, 0), Point::new(109, 7));
+    let columns = |align| {
+        let mut canvas = Canvas::new();
+        TextBox::new("|", &SMALL)
+            .with_align(align)
+            .draw(area, &mut canvas)
+            .unwrap();
+        (0..128)
+            .filter(|&x| !canvas.lit_rows(x).is_empty())
+            .collect::<Vec<_>>()
+    };
+    let bar = SMALL.char_width('|') as usize;
+    assert_eq!(columns(Align::Left), [10]);
+    assert_eq!(columns(Align::Right), [110 - bar]);
+    assert_eq!(columns(Align::Center), [10 + (100 - bar) / 2]);
+}
+
+#[test]
+fn text_stays_in_its_box() {
+    let text = "a lot of words that will not all fit in such a small box, no matter what";
+    let area = Rectangle::new(Point::new(20, 10), Point::new(59, 29));
+    let mut canvas = Canvas::new();
+    let text_box = TextBox::new(text, &SMALL);
+    text_box.draw(area, &mut canvas).unwrap();
+    assert!(text_box.height(40) > 20);
+    assert!(canvas.lit_in(20..60, 10..26) > 0);
+    // Only whole lines are drawn
+    assert_eq!(canvas.lit_in(0..128, 26..64), 0);
+    assert_eq!(canvas.lit_in(0..20, 0..64), 0);
+    assert_eq!(canvas.lit_in(60..128, 0..64), 0);
+}
+
+#[test]
+fn text() {
+    let mut canvas = Canvas::new();
+    let (top, bottom) = (
+        Rectangle::new(Point::new(0, 0), Point::new(127, 15)),
+        Rectangle::new(Point::new(0, 18), Point::new(127, 63)),
+    );
+    TextBox::new(SHRUG, &LARGE)
+        .with_align(Align::Center)
+        .draw(top, &mut canvas)
+        .unwrap();
+    TextBox::new(
+        "Proportional fonts fit a lot more on the screen: iiiii vs. WWWWW, café, 25°C, ±1%.",
+        &SMALL,
+    )
+    .with_align(Align::Center)
+    .draw(bottom, &mut canvas)
+    .unwrap();
+    assert_snapshot("text", &canvas);
+}
@@ -0,0 +1,177 @@
+//! Host tests for the interrupt load bookkeeping. Run with
+//! `cargo test --target x86_64-unknown-linux-gnu --test irq_load` (or your host's target triple).
+use cortex_m::interrupt::Nr;
+use sandbox_stm32f4_rust::irq_load::{IrqStats, LoadMonitor, CAPACITY};
+
+#[derive(Clone, Copy, Debug)]
+enum Vector {
+    Exti0 = 6,
+    Tim2 = 28,
+    Usart2 = 38,
+}
+
+unsafe impl Nr for Vector {
+    fn nr(&self) -> u8 {
+        *self as u8
+    }
+}
+
+/// Any vector at all
+#[derive(Clone, Copy, Debug)]
+struct Number(u8);
+
+unsafe impl Nr for Number {
+    fn nr(&self) -> u8 {
+        self.0
+    }
+}
+
+fn monitor(now: u32) -> LoadMonitor<Vector> {
+    let mut monitor = LoadMonitor::new(now);
+    assert!(monitor.watch(Vector::Tim2));
+    assert!(monitor.watch(Vector::Usart2));
+    monitor
+}
+
+#[test]
+fn idle_thread_and_handlers() {
+    let mut monitor = monitor(0);
+    monitor.enter_idle(0);
+    // Wakes up idle
+    monitor.enter(Vector::Tim2, 100, None);
+    monitor.exit(150);
+    monitor.exit(160);
+    // From thread mode
+    monitor.enter(Vector::Usart2, 200, None);
+    monitor.exit(240);
+    monitor.enter(Vector::Usart2, 300, None);
+    monitor.exit(310);
+
+    assert_eq!(monitor.window_cycles(400), 400);
+    assert_eq!(monitor.idle_cycles(), 110);
+    assert_eq!(
+        monitor.irq(Vector::Usart2),
+        Some(IrqStats {
+            count: 2,
+            busy: 50,
+            longest: 40,
+            worst_latency: None,
+        })
+    );
+    assert_eq!(monitor.irq(Vector::Tim2).map(|stats| stats.busy), Some(50));
+    assert_eq!(monitor.irq(Vector::Exti0), None);
+    assert_eq!(monitor.thread_cycles(400), 400 - 110 - 50 - 50);
+}
+
+#[test]
+fn nested_handlers_only_count_their_own_time() {
+    let mut monitor = monitor(0);
+    monitor.enter(Vector::Usart2, 0, None);
+    monitor.enter(Vector::Tim2, 10, None);
+    // Not watched, but it calls enter anyway, so it's thread mode rather than TIM2
+    monitor.enter(Vector::Exti0, 15, None);
+    monitor.exit(20);
+    monitor.exit(30);
+    monitor.exit(50);
+
+    assert_eq!(
+        monitor.irq(Vector::Usart2).map(|stats| stats.busy),
+        Some(30)
+    );
+    assert_eq!(monitor.irq(Vector::Tim2).map(|stats| stats.busy), Some(15));
+    assert_eq!(monitor.thread_cycles(50), 5);
+}
+
+#[test]
+fn latency_and_reset() {
+    let mut monitor = monitor(0);
+    for (now, latency) in [(0, 30), (100, 80), (200, 20)] {
+        monitor.enter(Vector::Tim2, now, Some(latency));
+        monitor.exit(now + 10);
+    }
+    assert_eq!(
+        monitor
+            .irq(Vector::Tim2)
+            .and_then(|stats| stats.worst_latency),
+        Some(80)
+    );
+
+    // Idle that's still going only counts from the reset on
+    monitor.enter_idle(300);
+    monitor.reset(400);
+    assert_eq!(monitor.irq(Vector::Tim2), Some(IrqStats::default()));
+    monitor.exit(450);
+    assert_eq!(monitor.idle_cycles(), 50);
+    assert_eq!(monitor.window_cycles(500), 100);
+}
+
+#[test]
+fn cycle_counter_wraparound() {
+    let start = u32::MAX - 99;
+    let mut monitor = monitor(start);
+    monitor.enter_idle(start);
+    monitor.enter(Vector::Tim2, start.wrapping_add(90), None);
+    monitor.exit(start.wrapping_add(120));
+    monitor.exit(start.wrapping_add(200));
+    assert_eq!(monitor.window_cycles(start.wrapping_add(300)), 300);
+    assert_eq!(monitor.idle_cycles(), 170);
+    assert_eq!(monitor.irq(Vector::Tim2).map(|stats| stats.busy), Some(30));
+}
+
+#[test]
+fn limits() {
+    let mut monitor: LoadMonitor<Vector> = LoadMonitor::new(0);
+    // Watching twice doesn't take up room
+    assert!(monitor.watch(Vector::Tim2));
+    assert!(monitor.watch(Vector::Tim2));
+    assert_eq!(monitor.iter().count(), 1);
+    let mut full = LoadMonitor::new(0);
+    for nr in 0..CAPACITY as u8 {
+        assert!(full.watch(Number(nr)));
+    }
+    assert!(!full.watch(Number(CAPACITY as u8)));
+
+    // Nesting deeper than there are priority levels still matches up exits with entries
+    for now in 0..20 {
+        monitor.enter(Vector::Tim2, now, None);
+    }
+    for now in 20..40 {
+        monitor.exit(now);
+    }
+    monitor.enter(Vector::Tim2, 100, None);
+    monitor.exit(110);
+    let stats = monitor.irq(Vector::Tim2).unwrap();
+    // The ones that didn't fit count towards the ones they interrupted
+    assert_eq!(stats.count, 18);
+    assert_eq!(stats.busy, 39 + 10);
+}
+
+#[test]
+fn report() {
+    let mut monitor = monitor(0);
+    monitor.enter_idle(0);
+    monitor.exit(70_000);
+    monitor.enter(Vector::Tim2, 70_000, Some(168));
+    monitor.exit(70_840);
+    let mut report = String::new();
+    monitor
+        .write_report(&mut report, 84_000, 84_000_000)
+        .unwrap();
+    assert_eq!(
+        report,
+        "1 ms: idle 83.3%, thread 15.6%\r\n\
+         irq                 count   load  longest us  latency us\r\n\
+         Tim2                    1   1.0%          10           2\r\n\
+         Usart2                  0   0.0%           0           -\r\n\
+         - latency is only known for handlers that use enter_late\r\n"
+    );
+
+    // No note once every handler has a latency
+    monitor.enter(Vector::Usart2, 84_000, Some(84));
+    monitor.exit(84_100);
+    report.clear();
+    monitor
+        .write_report(&mut report, 84_100, 84_000_000)
+        .unwrap();
+    assert!(!report.contains("enter_late"));
+}
@@ -0,0 +1,125 @@
+//! Host tests for LED patterns. Run with `cargo test --target x86_64-unknown-linux-gnu --test led`
+//! (or your host's target triple).
+use sandbox_stm32f4_rust::led::{
+    Pattern, Player, BLINK_CODE_MS, BLINK_CODE_PAUSE_MS, HEARTBEAT, MORSE_UNIT_MS,
+};
+
+/// The first `total_ms` of a pattern as runs of (on, milliseconds)
+fn timeline(pattern: Pattern, total_ms: u32) -> Vec<(bool, u32)> {
+    let mut player = Player::new(pattern);
+    let mut runs: Vec<(bool, u32)> = Vec::new();
+    for _ in 0..total_ms {
+        match runs.last_mut() {
+            Some((on, ms)) if *on == player.is_on() => *ms += 1,
+            _ => runs.push((player.is_on(), 1)),
+        }
+        player.advance(1);
+    }
+    runs
+}
+
+#[test]
+fn sequences_repeat() {
+    assert_eq!(
+        timeline(HEARTBEAT, 2000),
+        [
+            (true, 60),
+            (false, 140),
+            (true, 60),
+            (false, 740),
+            (true, 60),
+            (false, 140),
+            (true, 60),
+            (false, 740),
+        ]
+    );
+    assert_eq!(timeline(Pattern::On, 5000), [(true, 5000)]);
+    assert_eq!(timeline(Pattern::Off, 5000), [(false, 5000)]);
+    // Nothing to play is the same as off, and zero-length steps don't hang
+    assert_eq!(timeline(Pattern::Sequence(&[]), 100), [(false, 100)]);
+    assert_eq!(timeline(Pattern::Sequence(&[0, 0]), 10).len(), 10);
+}
+
+#[test]
+fn blink_codes() {
+    let (blink, pause) = (BLINK_CODE_MS, BLINK_CODE_PAUSE_MS);
+    let cycle = 2 * blink * 2 + blink + pause;
+    assert_eq!(
+        timeline(Pattern::BlinkCode(3), 2 * cycle),
+        [
+            (true, blink),
+            (false, blink),
+            (true, blink),
+            (false, blink),
+            (true, blink),
+            (false, pause),
+            (true, blink),
+            (false, blink),
+            (true, blink),
+            (false, blink),
+            (true, blink),
+            (false, pause),
+        ]
+    );
+    assert_eq!(timeline(Pattern::BlinkCode(0), 1000), [(false, 1000)]);
+}
+
+#[test]
+fn morse() {
+    let unit = MORSE_UNIT_MS;
+    let (dot, dash) = ((true, unit), (true, 3 * unit));
+    let gap = |units| (false, units * unit);
+    // S O S, then a word gap before it starts over
+    let sos = [
+        dot,
+        gap(1),
+        dot,
+        gap(1),
+        dot,
+        gap(3),
+        dash,
+        gap(1),
+        dash,
+        gap(1),
+        dash,
+        gap(3),
+        dot,
+        gap(1),
+        dot,
+        gap(1),
+        dot,
+        gap(7),
+    ];
+    let length = sos.iter().map(|&(_, ms)| ms).sum();
+    assert_eq!(timeline(Pattern::Morse("SOS"), length), sos);
+    assert_eq!(timeline(Pattern::Morse("sos"), length), sos);
+
+    // Spaces and punctuation separate words
+    assert_eq!(
+        timeline(Pattern::Morse("e, t"), 14 * unit),
+        [dot, gap(7), (true, 3 * unit), gap(3)]
+    );
+    assert_eq!(timeline(Pattern::Morse("?!"), 1000), [(false, 1000)]);
+    // Digits
+    assert_eq!(
+        timeline(Pattern::Morse("5"), 9 * unit),
+        [dot, gap(1), dot, gap(1), dot, gap(1), dot, gap(1), dot]
+    );
+}
+
+#[test]
+fn switching_patterns_starts_over() {
+    let mut player = Player::new(HEARTBEAT);
+    player.advance(30);
+    assert!(player.is_on());
+    player.set(Pattern::BlinkCode(2));
+    assert_eq!(player.pattern(), Pattern::BlinkCode(2));
+    assert!(player.is_on());
+    assert!(player.advance(BLINK_CODE_MS - 1));
+    assert!(!player.advance(1));
+
+    // Big jumps land in the right place
+    let mut player = Player::new(HEARTBEAT);
+    assert!(player.advance(1000 * 100 + 60 + 140 + 30));
+    assert!(!player.advance(100));
+}
@@ -0,0 +1,51 @@
+//! Host tests for PWM LED brightness. Run with
+//! `cargo test --target x86_64-unknown-linux-gnu --test led_pwm` (or your host's target triple).
+use sandbox_stm32f4_rust::led_pwm::{
+    breath_duty, breath_timing, gamma, FULL, MAX_BREATH_STEPS, PWM_HZ,
+};
+
+#[test]
+fn gamma_goes_from_off_to_full() {
+    assert_eq!(gamma(0), 0);
+    assert_eq!(gamma(255), FULL);
+    for level in 1..=255 {
+        assert!(gamma(level) >= gamma(level - 1));
+    }
+    // The dimmest levels are still distinguishable from off
+    assert!(gamma(1) > 0);
+    // Half brightness takes far less than half the light
+    assert!(gamma(128) < FULL / 4);
+}
+
+#[test]
+fn breaths_fade_in_and_out() {
+    let steps = 1000;
+    assert_eq!(breath_duty(0, steps), 0);
+    assert_eq!(breath_duty(steps / 2, steps), FULL);
+    for step in 1..=steps / 2 {
+        assert!(breath_duty(step, steps) >= breath_duty(step - 1, steps));
+        let (up, down) = (breath_duty(step, steps), breath_duty(steps - step, steps));
+        assert!(up.max(down) - up.min(down) <= 1, "step {}", step);
+    }
+}
+
+#[test]
+fn breaths_last_as_long_as_asked() {
+    // Timer clocks for 16MHz, 84MHz and 100MHz system clocks
+    for &timer_hz in &[16_000_000, 84_000_000, 100_000_000] {
+        for &period_ms in &[200, 1000, 3000, 10_000, 60_000] {
+            let (prescaler, steps) = breath_timing(timer_hz, period_ms);
+            assert!(steps <= MAX_BREATH_STEPS);
+            let step_ticks = u64::from(prescaler + 1) * u64::from(FULL);
+            let breath_ticks = steps as u64 * step_ticks;
+            let expected_ticks = u64::from(timer_hz) * u64::from(period_ms) / 1000;
+            // Off by less than a PWM period
+            assert!(expected_ticks - breath_ticks < step_ticks);
+            // Short breaths don't slow the PWM down
+            let pwm_hz = u64::from(timer_hz) / step_ticks;
+            if period_ms <= 1000 {
+                assert!(pwm_hz >= u64::from(PWM_HZ) * 9 / 10, "{} Hz", pwm_hz);
+            }
+        }
+    }
+}
@@ -0,0 +1,147 @@
+//! Host tests for the plotting widgets. Run with
+//! `cargo test --target x86_64-unknown-linux-gnu --test plot` (or your host's target triple).
+mod common;
+
+use common::Canvas;
+use embedded_graphics::{prelude::*, primitives::Rectangle};
+use heapless::consts::{U256, U4};
+use sandbox_stm32f4_rust::{
+    plot::{BarGraph, Scale, Series, Sparkline, StripChart, TimeAxis},
+    ui::View,
+};
+
+#[test]
+fn series_drops_oldest_samples() {
+    let mut series: Series<U4> = Series::new();
+    assert!(series.is_empty());
+    assert_eq!(series.latest(), None);
+    for value in 1..=6 {
+        series.push(value);
+    }
+    assert_eq!(series.len(), 4);
+    assert_eq!(series.iter().collect::<Vec<_>>(), [3, 4, 5, 6]);
+    assert_eq!(series.recent(2).collect::<Vec<_>>(), [6, 5]);
+    assert_eq!(series.latest(), Some(6));
+    assert_eq!(series.range(3), Some((4, 6)));
+    series.clear();
+    assert!(series.is_empty());
+    assert_eq!(series.range(3), None);
+}
+
+#[test]
+fn sparkline_puts_newest_sample_on_the_right() {
+    let mut series: Series<U256> = Series::new();
+    for value in 0..=10 {
+        series.push(value);
+    }
+    let area = Rectangle::new(Point::zero(), Point::new(127, 10));
+
+    let mut canvas = Canvas::new();
+    Sparkline::new(&series).draw(area, &mut canvas).unwrap();
+    // Autoscaled, so the ramp goes corner to corner, one pixel per sample
+    assert_eq!(canvas.lit_rows(127), [0]);
+    assert_eq!(canvas.lit_rows(117), [10]);
+    assert_eq!(canvas.lit_in(0..117, 0..64), 0);
+
+    let mut canvas = Canvas::new();
+    Sparkline {
+        series: &series,
+        scale: Scale::Fixed { min: 0, max: 20 },
+    }
+    .draw(area, &mut canvas)
+    .unwrap();
+    assert_eq!(canvas.lit_rows(127), [5]);
+    assert_eq!(canvas.lit_rows(117), [10]);
+}
+
+#[test]
+fn flat_and_out_of_range_samples_stay_in_bounds() {
+    let mut series: Series<U256> = Series::new();
+    let area = Rectangle::new(Point::new(10, 10), Point::new(40, 20));
+
+    // Nothing to draw
+    let mut canvas = Canvas::new();
+    Sparkline::new(&series).draw(area, &mut canvas).unwrap();
+    assert_eq!(canvas.lit_in(0..128, 0..64), 0);
+
+    // A flat line ends up in the middle
+    for _ in 0..200 {
+        series.push(7);
+    }
+    let mut canvas = Canvas::new();
+    Sparkline::new(&series).draw(area, &mut canvas).unwrap();
+    assert_eq!(canvas.lit_rows(40), [15]);
+    assert_eq!(canvas.lit_in(0..128, 0..64), 31);
+
+    // Clipped to the edges
+    series.push(i32::MAX);
+    series.push(i32::MIN);
+    let mut canvas = Canvas::new();
+    Sparkline {
+        series: &series,
+        scale: Scale::Fixed { min: -1, max: 1 },
+    }
+    .draw(area, &mut canvas)
+    .unwrap();
+    // A vertical line from the top edge to the bottom one, spread over the last two columns
+    assert_eq!(canvas.lit_in(39..41, 10..21), 11);
+    assert_eq!(canvas.lit_rows(40).last(), Some(&20));
+    assert_eq!(canvas.lit_rows(39).first(), Some(&10));
+}
+
+#[test]
+fn strip_chart_labels_range_and_time_axis() {
+    let mut series: Series<U256> = Series::new();
+    for value in 0..300 {
+        series.push(value % 50 - 20);
+    }
+
+    let mut plain = Canvas::new();
+    StripChart::new(&series)
+        .draw(Canvas::full(), &mut plain)
+        .unwrap();
+    // Frame to the right of the labels, "-20" being the widest
+    let frame_left = 3 * 6 + 2;
+    assert_eq!(plain.lit_rows(frame_left).len(), 64);
+    assert_eq!(plain.lit_rows(127).len(), 64);
+    assert!(plain.lit_in(0..frame_left, 0..8) > 0);
+    assert!(plain.lit_in(0..frame_left, 56..64) > 0);
+    assert_eq!(plain.lit_in(0..frame_left, 8..56), 0);
+
+    let mut chart = StripChart::new(&series);
+    chart.time_axis = Some(TimeAxis {
+        sample_period_ms: 100,
+        tick_ms: 1000,
+    });
+    let mut with_axis = Canvas::new();
+    chart.draw(Canvas::full(), &mut with_axis).unwrap();
+    // The frame is shorter to make room for the labels underneath
+    assert_eq!(with_axis.lit_rows(127).len(), 56);
+    assert!(with_axis.lit_in(frame_left..128, 56..64) > 0);
+    // Ticks every 10 samples, going back from the newest one
+    let frame_bottom = 55;
+    assert!(with_axis.pixels[frame_bottom - 2][126 - 10]);
+    assert!(!with_axis.pixels[frame_bottom - 2][126 - 5]);
+}
+
+#[test]
+fn bar_graph_grows_from_zero() {
+    let values = [10, -10, 0, 5];
+    let area = Rectangle::new(Point::zero(), Point::new(30, 20));
+    let mut canvas = Canvas::new();
+    BarGraph::new(&values).draw(area, &mut canvas).unwrap();
+
+    // Four 7 pixel bars with 1 pixel gaps. Zero is halfway down.
+    assert_eq!(canvas.lit_rows(0), (0..=10).collect::<Vec<_>>());
+    assert_eq!(canvas.lit_rows(7), []);
+    assert_eq!(canvas.lit_rows(8), (10..=20).collect::<Vec<_>>());
+    assert_eq!(canvas.lit_rows(16), [10]);
+    assert_eq!(canvas.lit_rows(24), (5..=10).collect::<Vec<_>>());
+    assert_eq!(canvas.lit_in(31..128, 0..64), 0);
+
+    // More bars than pixels
+    let many = [1; 200];
+    let mut canvas = Canvas::new();
+    BarGraph::new(&many).draw(area, &mut canvas).unwrap();
+    assert_eq!(canvas.lit_in(31..128, 0..64), 0);
+}
@@ -0,0 +1,73 @@
+//! Host tests for the profiler's bookkeeping. Run with
+//! `cargo test --target x86_64-unknown-linux-gnu --test profile` (or your host's target triple).
+use sandbox_stm32f4_rust::profile::{Profile, Stats, CAPACITY};
+
+#[test]
+fn stats() {
+    let mut stats = Stats::new();
+    assert_eq!(stats.avg(), 0);
+    for cycles in [300, 100, 200, 401] {
+        stats.record(cycles);
+    }
+    assert_eq!(stats.count, 4);
+    assert_eq!(stats.min, 100);
+    assert_eq!(stats.max, 401);
+    assert_eq!(stats.total, 1001);
+    // Rounds down
+    assert_eq!(stats.avg(), 250);
+
+    // The total doesn't overflow with lots of long sections
+    let mut stats = Stats::new();
+    for _ in 0..4 {
+        stats.record(u32::MAX);
+    }
+    assert_eq!(stats.avg(), u32::MAX);
+}
+
+#[test]
+fn sections_by_name() {
+    let mut profile = Profile::new();
+    assert!(profile.record("flush", 5000));
+    assert!(profile.record("draw", 1000));
+    assert!(profile.record("flush", 7000));
+    // Names are compared by value, not by address
+    let name = String::from("draw");
+    assert_eq!(profile.get(&name).map(|stats| stats.count), Some(1));
+    assert_eq!(profile.get("flush").map(|stats| stats.avg()), Some(6000));
+    assert_eq!(profile.get("uart"), None);
+    // In the order they first ran
+    let names: Vec<_> = profile.iter().map(|(name, _)| name).collect();
+    assert_eq!(names, ["flush", "draw"]);
+
+    profile.clear();
+    assert_eq!(profile.iter().count(), 0);
+}
+
+#[test]
+fn full() {
+    const NAMES: [&str; CAPACITY + 1] = [
+        "0", "1", "2", "3", "4", "5", "6", "7", "8", "9", "10", "11", "12", "13", "14", "15", "16",
+    ];
+    let mut profile = Profile::new();
+    for name in &NAMES[..CAPACITY] {
+        assert!(profile.record(name, 1));
+    }
+    assert!(!profile.record(NAMES[CAPACITY], 1));
+    // Existing names still get recorded
+    assert!(profile.record("3", 1));
+    assert_eq!(profile.get("3").map(|stats| stats.count), Some(2));
+}
+
+#[test]
+fn report() {
+    let mut profile = Profile::new();
+    profile.record("uart_interrupt", 840);
+    profile.record("uart_interrupt", 1680);
+    let mut report = String::new();
+    profile.write_report(&mut report, 84_000_000).unwrap();
+    assert_eq!(
+        report,
+        "section             count        min        avg        max   avg us\r\n\
+         uart_interrupt          2        840       1260       1680       15\r\n"
+    );
+}
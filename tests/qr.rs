@@ -0,0 +1,126 @@
+//! Host tests for the QR encoder, which decode what it draws with `rqrr`. Run with
+//! `cargo test --target x86_64-unknown-linux-gnu --test qr` (or your host's target triple).
+mod common;
+
+use common::Canvas;
+use embedded_graphics::{prelude::*, primitives::Rectangle};
+use sandbox_stm32f4_rust::{
+    qr::{EccLevel, Error, QrCode, MAX_VERSION},
+    ui::View,
+};
+
+/// Draw the code in a 64x64 area and scan it back in
+fn draw_and_decode(code: &QrCode) -> (rqrr::MetaData, Vec<u8>) {
+    const SIDE: usize = 64;
+    let mut canvas = Canvas::new();
+    code.draw(
+        Rectangle::new(Point::new(32, 0), Point::new(32 + SIDE as i32 - 1, 63)),
+        &mut canvas,
+    )
+    .unwrap();
+    assert_eq!(canvas.lit_in(0..32, 0..64), 0);
+    assert_eq!(canvas.lit_in(96..128, 0..64), 0);
+
+    // Lit pixels are the light modules. rqrr can't find codes with one pixel per module, so blow the
+    // display up a bit, like a camera would.
+    const ZOOM: usize = 4;
+    let mut image =
+        rqrr::PreparedImage::prepare_from_greyscale(SIDE * ZOOM, SIDE * ZOOM, |x, y| {
+            if canvas.pixels[y / ZOOM][32 + x / ZOOM] {
+                255
+            } else {
+                0
+            }
+        });
+    let grids = image.detect_grids();
+    assert_eq!(grids.len(), 1, "expected exactly one code");
+    let mut decoded = Vec::new();
+    let meta = grids[0].decode_to(&mut decoded).unwrap();
+    (meta, decoded)
+}
+
+#[test]
+fn decodes_every_version_and_level() {
+    for &ecc in &[EccLevel::L, EccLevel::M] {
+        for version in 1..=MAX_VERSION {
+            // Fill the version up, so it can't pick a smaller one
+            let data: Vec<u8> = (0..ecc.capacity(version))
+                .map(|i| b'A' + (i % 26) as u8)
+                .collect();
+            let code = QrCode::encode(&data, ecc).unwrap();
+            assert_eq!(code.version(), version);
+            assert_eq!(code.size(), 17 + 4 * usize::from(version));
+
+            let (meta, decoded) = draw_and_decode(&code);
+            assert_eq!(decoded, data, "{:?} version {}", ecc, version);
+            assert_eq!(meta.version.0, usize::from(version));
+            // The format bits for L are 01 and M 00
+            let expected_level = match ecc {
+                EccLevel::L => 1,
+                EccLevel::M => 0,
+            };
+            assert_eq!(meta.ecc_level, expected_level);
+        }
+    }
+}
+
+#[test]
+fn decodes_serial_numbers_urls_and_binary() {
+    let inputs: [&[u8]; 4] = [
+        b"",
+        b"SN-000123",
+        b"https://github.com/kesyog/sandbox-stm32f4-rust",
+        &[0x00, 0xff, 0x80, 0x7f, 0xc3, 0x28],
+    ];
+    for &data in &inputs {
+        let code = QrCode::encode(data, EccLevel::M).unwrap();
+        assert_eq!(draw_and_decode(&code).1, data);
+    }
+}
+
+#[test]
+fn picks_the_smallest_version_that_fits() {
+    assert_eq!(EccLevel::L.capacity(1), 17);
+    assert_eq!(EccLevel::M.capacity(MAX_VERSION), 62);
+    assert_eq!(QrCode::encode(&[0; 17], EccLevel::L).unwrap().version(), 1);
+    assert_eq!(QrCode::encode(&[0; 17], EccLevel::M).unwrap().version(), 2);
+    assert_eq!(QrCode::encode(&[0; 18], EccLevel::L).unwrap().version(), 2);
+    assert_eq!(
+        QrCode::encode(&[0; 63], EccLevel::M).err(),
+        Some(Error::TooLong)
+    );
+    assert!(QrCode::encode(&[0; 78], EccLevel::L).is_ok());
+    assert_eq!(
+        QrCode::encode(&[0; 79], EccLevel::L).err(),
+        Some(Error::TooLong)
+    );
+}
+
+#[test]
+fn scales_to_fit_with_a_quiet_zone() {
+    let full = Rectangle::new(Point::zero(), Point::new(63, 63));
+    let dark_extent = |code: &QrCode| {
+        let mut canvas = Canvas::new();
+        code.draw(full, &mut canvas).unwrap();
+        let dark_columns: Vec<_> = (0..64).filter(|&x| canvas.lit_rows(x).len() < 64).collect();
+        (dark_columns[0], *dark_columns.last().unwrap())
+    };
+
+    // 21 modules plus the quiet zone fit twice over
+    let small = QrCode::encode(b"hi", EccLevel::L).unwrap();
+    assert_eq!(dark_extent(&small), (11, 52));
+    // 33 don't
+    let large = QrCode::encode(&[0; 60], EccLevel::M).unwrap();
+    assert_eq!(large.version(), 4);
+    assert_eq!(dark_extent(&large), (15, 47));
+
+    // Too small for even one pixel per module
+    let mut canvas = Canvas::new();
+    large
+        .draw(
+            Rectangle::new(Point::zero(), Point::new(30, 30)),
+            &mut canvas,
+        )
+        .unwrap();
+    assert_eq!(canvas.lit_in(0..31, 0..31), 31 * 31);
+}
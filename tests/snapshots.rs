@@ -0,0 +1,111 @@
+//! Golden-image tests for things drawn on the OLED. See `tests/common/snapshot.rs` for how to update
+//! the images in `tests/snapshots/`. Run with
+//! `cargo test --target x86_64-unknown-linux-gnu --test snapshots` (or your host's target triple).
+mod common;
+
+use common::{
+    snapshot::{self, assert_snapshot},
+    Canvas,
+};
+use embedded_graphics::{prelude::*, primitives::Rectangle};
+use heapless::consts::U128;
+use sandbox_stm32f4_rust::{
+    demo,
+    plot::{Series, StripChart, TimeAxis},
+    qr::{EccLevel, QrCode},
+    ui::{Menu, Screen, Spinner, StatusBar, View},
+};
+
+#[test]
+fn kes() {
+    let mut canvas = Canvas::new();
+    demo::draw_kes(&mut canvas).unwrap();
+    assert_snapshot("kes", &canvas);
+}
+
+#[test]
+fn shapes() {
+    let mut canvas = Canvas::new();
+    demo::draw_shapes(&mut canvas).unwrap();
+    assert_snapshot("shapes", &canvas);
+}
+
+#[test]
+fn menu_with_modal() {
+    let items = ["Contrast", "Progress demo", "About"];
+    let menu = Menu::new(&items);
+    let mut screen = Screen::new(StatusBar {
+        left: "Settings",
+        right: "12s",
+    });
+    screen.show_modal("v0.1.0");
+    let mut canvas = Canvas::new();
+    screen.draw(&menu, &mut canvas).unwrap();
+    assert_snapshot("menu_with_modal", &canvas);
+}
+
+#[test]
+fn spinner() {
+    let spinner = Spinner::new("Contrast", 128, 0, 255, 32);
+    let mut canvas = Canvas::new();
+    Screen::new(StatusBar {
+        left: "Settings",
+        right: "",
+    })
+    .draw(&spinner, &mut canvas)
+    .unwrap();
+    assert_snapshot("spinner", &canvas);
+}
+
+#[test]
+fn strip_chart() {
+    let mut series: Series<U128> = Series::new();
+    for i in 0..128 {
+        series.push((i * 7) % 60 - 30);
+    }
+    let mut chart = StripChart::new(&series);
+    chart.time_axis = Some(TimeAxis {
+        sample_period_ms: 50,
+        tick_ms: 1000,
+    });
+    let mut canvas = Canvas::new();
+    chart.draw(Canvas::full(), &mut canvas).unwrap();
+    assert_snapshot("strip_chart", &canvas);
+}
+
+#[test]
+fn qr_code() {
+    let code = QrCode::encode(b"SN-000123", EccLevel::M).unwrap();
+    let mut canvas = Canvas::new();
+    code.draw(
+        Rectangle::new(Point::zero(), Point::new(63, 63)),
+        &mut canvas,
+    )
+    .unwrap();
+    assert_snapshot("qr_code", &canvas);
+}
+
+#[test]
+fn pbm_round_trips() {
+    let mut canvas = Canvas::new();
+    demo::draw_shapes(&mut canvas).unwrap();
+    let pbm = canvas.to_pbm();
+    assert!(pbm.lines().all(|line| line.len() <= 70));
+    assert_eq!(Canvas::from_pbm(&pbm).pixels, canvas.pixels);
+}
+
+#[test]
+fn mismatch_writes_a_diff() {
+    // Would overwrite the golden with the broken drawing
+    if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        return;
+    }
+    let mut canvas = Canvas::new();
+    demo::draw_shapes(&mut canvas).unwrap();
+    canvas.pixels[10][10] = true;
+    let result = std::panic::catch_unwind(|| assert_snapshot("shapes", &canvas));
+    let message = *result.unwrap_err().downcast::<String>().unwrap();
+    assert!(message.contains("in 1 pixels, starting at (10, 10)"));
+    let diff = snapshot::output_dir().join("shapes.diff.png");
+    assert!(diff.exists());
+}
@@ -0,0 +1,108 @@
+//! Host tests for timeouts. Run with
+//! `cargo test --target x86_64-unknown-linux-gnu --test timer_delay` (or your host's target triple).
+use embedded_hal::timer::CountDown;
+use sandbox_stm32f4_rust::timer_delay::{Timeout, TimeoutError};
+use std::{cell::Cell, rc::Rc};
+use void::Void;
+
+/// Periodic like the HAL's timers, including restarting without clearing the flag
+struct FakeTimer {
+    now: Rc<Cell<u32>>,
+    period: u32,
+    deadline: u32,
+    flag: bool,
+}
+
+impl FakeTimer {
+    fn new(now: &Rc<Cell<u32>>) -> Self {
+        Self {
+            now: now.clone(),
+            period: 0,
+            deadline: u32::MAX,
+            flag: false,
+        }
+    }
+}
+
+impl CountDown for FakeTimer {
+    type Time = u32;
+
+    fn start<T: Into<u32>>(&mut self, period: T) {
+        self.period = period.into();
+        self.deadline = self.now.get() + self.period;
+    }
+
+    fn wait(&mut self) -> nb::Result<(), Void> {
+        if self.now.get() >= self.deadline {
+            self.deadline += self.period;
+            self.flag = true;
+        }
+        if self.flag {
+            self.flag = false;
+            Ok(())
+        } else {
+            Err(nb::Error::WouldBlock)
+        }
+    }
+}
+
+#[test]
+fn expiry_sticks() {
+    let now = Rc::new(Cell::new(0));
+    let mut timeout = Timeout::start(FakeTimer::new(&now), 3_u32);
+    assert!(!timeout.is_expired());
+    now.set(2);
+    assert!(!timeout.is_expired());
+    now.set(3);
+    assert!(timeout.is_expired());
+    // Even though the timer itself has started over
+    now.set(4);
+    assert!(timeout.is_expired());
+    assert_eq!(timeout.free().deadline, 6);
+}
+
+#[test]
+fn stale_expiry_is_ignored() {
+    let now = Rc::new(Cell::new(100));
+    // Went off while it wasn't being looked at
+    let mut timer = FakeTimer::new(&now);
+    timer.flag = true;
+    let mut timeout = Timeout::start(timer, 10_u32);
+    assert!(!timeout.is_expired());
+    now.set(109);
+    assert!(!timeout.is_expired());
+    now.set(110);
+    assert!(timeout.is_expired());
+}
+
+#[test]
+fn polling() {
+    let now = Rc::new(Cell::new(0));
+    let tick = || now.set(now.get() + 1);
+
+    // Finishes in time
+    let mut timeout = Timeout::start(FakeTimer::new(&now), 5_u32);
+    let result: Result<u32, TimeoutError<()>> = nb::block!(timeout.poll(|| {
+        tick();
+        if now.get() == 3 {
+            Ok(42)
+        } else {
+            Err(nb::Error::WouldBlock)
+        }
+    }));
+    assert_eq!(result, Ok(42));
+
+    // Never finishes
+    let mut timeout = Timeout::start(FakeTimer::new(&now), 5_u32);
+    let result: Result<(), TimeoutError<()>> = nb::block!(timeout.poll(|| {
+        tick();
+        Err(nb::Error::WouldBlock)
+    }));
+    assert_eq!(result, Err(TimeoutError::TimedOut));
+    assert_eq!(now.get(), 8);
+
+    // Fails, which beats timing out
+    let mut timeout = Timeout::start(FakeTimer::new(&now), 0_u32);
+    let result: nb::Result<(), _> = timeout.poll(|| Err(nb::Error::Other("broken")));
+    assert_eq!(result, Err(nb::Error::Other(TimeoutError::Other("broken"))));
+}
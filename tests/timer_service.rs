@@ -0,0 +1,95 @@
+//! Host tests for the software timer queue. Run with
+//! `cargo test --target x86_64-unknown-linux-gnu --test timer_service` (or your host's target
+//! triple).
+use sandbox_stm32f4_rust::timer_service::{
+    is_due, Error, TimerId, TimerQueue, CAPACITY, MAX_DELAY_US,
+};
+
+fn nothing() {}
+
+/// Ids of the timers due by `now`, in the order they come out
+fn due(queue: &mut TimerQueue, now: u32) -> Vec<TimerId> {
+    std::iter::from_fn(|| queue.pop_due(now).map(|(id, _)| id)).collect()
+}
+
+#[test]
+fn timers_go_off_in_deadline_order() {
+    let mut queue = TimerQueue::new();
+    let c = queue.schedule(1000, 300, None, nothing).unwrap();
+    let a = queue.schedule(1000, 100, None, nothing).unwrap();
+    let b = queue.schedule(1000, 200, None, nothing).unwrap();
+    let b2 = queue.schedule(1000, 200, None, nothing).unwrap();
+    assert_eq!(queue.next_deadline(), Some(1100));
+
+    assert_eq!(due(&mut queue, 1099), []);
+    assert_eq!(due(&mut queue, 1100), [a]);
+    // Ties go off in the order they were set up
+    assert_eq!(due(&mut queue, 1250), [b, b2]);
+    // Late is better than never
+    assert_eq!(due(&mut queue, 5000), [c]);
+    assert!(queue.is_empty());
+    assert_eq!(queue.next_deadline(), None);
+}
+
+#[test]
+fn periodic_timers_repeat_until_cancelled() {
+    let mut queue = TimerQueue::new();
+    let tick = queue.schedule(0, 100, Some(100), nothing).unwrap();
+    let once = queue.schedule(0, 150, None, nothing).unwrap();
+    assert_eq!(due(&mut queue, 100), [tick]);
+    assert_eq!(due(&mut queue, 199), [once]);
+    assert_eq!(due(&mut queue, 200), [tick]);
+    // Falling behind doesn't lose any
+    assert_eq!(due(&mut queue, 500), [tick, tick, tick]);
+    assert_eq!(queue.next_deadline(), Some(600));
+
+    assert!(queue.cancel(tick));
+    assert!(!queue.cancel(tick));
+    assert!(!queue.cancel(once));
+    assert_eq!(due(&mut queue, 10_000), []);
+
+    // A period of 0 is a one-shot rather than a timer that never stops going off
+    let zero = queue.schedule(0, 10, Some(0), nothing).unwrap();
+    assert_eq!(due(&mut queue, 1000), [zero]);
+}
+
+#[test]
+fn counter_wraparound() {
+    let mut queue = TimerQueue::new();
+    let now = u32::MAX - 99;
+    let late = queue.schedule(now, 300, None, nothing).unwrap();
+    let early = queue.schedule(now, 50, None, nothing).unwrap();
+    let periodic = queue.schedule(now, 200, Some(200), nothing).unwrap();
+    assert_eq!(queue.next_deadline(), Some(u32::MAX - 49));
+
+    assert_eq!(due(&mut queue, u32::MAX), [early]);
+    assert_eq!(due(&mut queue, 99), []);
+    assert_eq!(due(&mut queue, 100), [periodic]);
+    assert_eq!(due(&mut queue, 200), [late]);
+    assert_eq!(due(&mut queue, 300), [periodic]);
+
+    assert!(is_due(u32::MAX, 0));
+    assert!(!is_due(0, u32::MAX));
+    assert!(is_due(5, 5));
+}
+
+#[test]
+fn limits() {
+    let mut queue = TimerQueue::new();
+    assert_eq!(
+        queue.schedule(0, MAX_DELAY_US + 1, None, nothing),
+        Err(Error::TooLong)
+    );
+    assert_eq!(
+        queue.schedule(0, 10, Some(MAX_DELAY_US + 1), nothing),
+        Err(Error::TooLong)
+    );
+    let ids: Vec<_> = (0..CAPACITY as u32)
+        .map(|i| queue.schedule(0, i, None, nothing).unwrap())
+        .collect();
+    assert_eq!(queue.len(), CAPACITY);
+    assert_eq!(queue.schedule(0, 10, None, nothing), Err(Error::Full));
+    // Cancelling makes room again
+    assert!(queue.cancel(ids[3]));
+    assert!(queue.schedule(0, MAX_DELAY_US, None, nothing).is_ok());
+}
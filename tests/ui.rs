@@ -0,0 +1,171 @@
+//! Host tests for the UI widgets. Run with `cargo test --target x86_64-unknown-linux-gnu --test ui`
+//! (or your host's target triple), since the default target is the microcontroller.
+mod common;
+
+use common::Canvas;
+use embedded_graphics::{prelude::*, primitives::Rectangle};
+use sandbox_stm32f4_rust::{
+    button::{Debouncer, Timings},
+    ui::{Event, Menu, Modal, ProgressBar, Screen, Spinner, StatusBar, View, Widget},
+};
+
+#[test]
+fn clicks_and_long_presses() {
+    let mut button = Debouncer::new(Timings {
+        double_click_ms: 0,
+        ..Timings::default()
+    });
+    let mut events = |pressed, now_ms| {
+        button.update(pressed, now_ms);
+        core::iter::from_fn(|| button.next_event())
+            .filter_map(Event::from_button)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(events(true, 0), []);
+    assert_eq!(events(true, 100), []);
+    assert_eq!(events(false, 150), []);
+    assert_eq!(events(false, 200), [Event::Next]);
+
+    assert_eq!(events(true, 1000), []);
+    let long_press = Timings::default().long_press_ms;
+    assert_eq!(events(true, 1000 + long_press), [Event::Select]);
+    // Releasing afterwards isn't a click
+    assert_eq!(events(false, 3000), []);
+    assert_eq!(events(false, 3100), []);
+}
+
+#[test]
+fn menu_wraps_and_selects() {
+    let items = ["one", "two", "three"];
+    let mut menu = Menu::new(&items);
+    assert_eq!(menu.handle(Event::Next), None);
+    assert_eq!(menu.handle(Event::Next), None);
+    assert_eq!(menu.handle(Event::Select), Some(2));
+    menu.handle(Event::Next);
+    assert_eq!(menu.selected(), 0);
+
+    let mut empty = Menu::new(&[]);
+    assert_eq!(empty.handle(Event::Next), None);
+    assert_eq!(empty.handle(Event::Select), None);
+}
+
+#[test]
+fn menu_highlights_selection_and_scrolls() {
+    let items = ["a", "b", "c", "d", "e"];
+    let mut menu = Menu::new(&items);
+    // Room for two rows
+    let area = Rectangle::new(Point::zero(), Point::new(127, 15));
+
+    let mut canvas = Canvas::new();
+    menu.draw(area, &mut canvas).unwrap();
+    // The highlight bar is mostly lit, the row below it mostly not
+    assert!(canvas.lit_in(0..128, 0..8) > 128 * 8 / 2);
+    assert!(canvas.lit_in(0..128, 8..16) < 128 * 8 / 2);
+
+    for _ in 0..4 {
+        menu.handle(Event::Next);
+    }
+    let mut canvas = Canvas::new();
+    menu.draw(area, &mut canvas).unwrap();
+    // The last item is selected, so it's scrolled to the bottom row
+    assert!(canvas.lit_in(0..128, 0..8) < 128 * 8 / 2);
+    assert!(canvas.lit_in(0..128, 8..16) > 128 * 8 / 2);
+    assert_eq!(canvas.lit_in(0..128, 16..64), 0);
+}
+
+#[test]
+fn spinner_steps_and_wraps() {
+    let mut spinner = Spinner::new("Contrast", 200, 0, 255, 50);
+    assert_eq!(spinner.handle(Event::Next), None);
+    assert_eq!(spinner.value(), 250);
+    spinner.handle(Event::Next);
+    assert_eq!(spinner.value(), 255);
+    spinner.handle(Event::Next);
+    assert_eq!(spinner.value(), 0);
+    assert_eq!(spinner.handle(Event::Select), Some(0));
+
+    assert_eq!(Spinner::new("Clamped", 1000, 0, 10, 1).value(), 10);
+}
+
+#[test]
+fn progress_bar_fills_proportionally() {
+    let area = Rectangle::new(Point::new(0, 0), Point::new(103, 9));
+    let inner_lit = |value| {
+        let mut canvas = Canvas::new();
+        ProgressBar { value, max: 100 }
+            .draw(area, &mut canvas)
+            .unwrap();
+        canvas.lit_in(2..102, 2..8)
+    };
+    assert_eq!(inner_lit(0), 0);
+    assert_eq!(inner_lit(50), 50 * 6);
+    assert_eq!(inner_lit(100), 100 * 6);
+    // Overflow is clamped
+    assert_eq!(inner_lit(1000), 100 * 6);
+}
+
+#[test]
+fn long_text_is_clipped_to_its_area() {
+    // The canvas asserts that nothing is drawn outside of it
+    let mut canvas = Canvas::new();
+    let long = "This line is much too long to fit on the display";
+    StatusBar {
+        left: long,
+        right: long,
+    }
+    .draw(
+        Rectangle::new(Point::zero(), Point::new(127, 9)),
+        &mut canvas,
+    )
+    .unwrap();
+    Modal::new(long).draw(Canvas::full(), &mut canvas).unwrap();
+    let items = [long];
+    Menu::new(&items).draw(Canvas::full(), &mut canvas).unwrap();
+}
+
+#[test]
+fn screen_routes_events_to_modal_first() {
+    let items = ["one", "two"];
+    let mut menu = Menu::new(&items);
+    let mut screen = Screen::new(StatusBar {
+        left: "Menu",
+        right: "12:00",
+    });
+
+    screen.show_modal("Hello");
+    // Dismisses the modal without touching the menu
+    assert_eq!(screen.handle(Event::Select, &mut menu), None);
+    assert!(screen.modal.is_none());
+    assert_eq!(menu.selected(), 0);
+
+    assert_eq!(screen.handle(Event::Next, &mut menu), None);
+    assert_eq!(screen.handle(Event::Select, &mut menu), Some(1));
+}
+
+#[test]
+fn screen_draws_status_bar_body_and_modal() {
+    let items = ["one", "two"];
+    let menu = Menu::new(&items);
+    let mut screen = Screen::new(StatusBar {
+        left: "Menu",
+        right: "",
+    });
+
+    let mut canvas = Canvas::new();
+    screen.draw(&menu, &mut canvas).unwrap();
+    // Status text, underline and then the highlighted first menu item right below
+    assert!(canvas.lit_in(0..24, 0..8) > 0);
+    assert_eq!(canvas.lit_in(0..128, 8..9), 128);
+    assert!(canvas.lit_in(0..128, 10..18) > 128 * 8 / 2);
+
+    screen.show_modal("Saved");
+    let mut with_modal = Canvas::new();
+    screen.draw(&menu, &mut with_modal).unwrap();
+    // One line of text plus padding, centered on the whole screen. The top border is a solid line
+    // as wide as the text plus padding.
+    assert_eq!(with_modal.lit_in(45..83, 24..25), 5 * 6 + 8);
+    assert_eq!(
+        with_modal.lit_in(0..128, 0..24),
+        canvas.lit_in(0..128, 0..24)
+    );
+}